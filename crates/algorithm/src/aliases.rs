@@ -0,0 +1,207 @@
+use crate::tape::TapeWriter;
+use crate::tuples::*;
+use crate::{Page, RelationRead, RelationWrite};
+use std::collections::{HashMap, HashSet};
+use std::num::NonZero;
+
+const CHUNK: usize = 256;
+
+// A group is indexed once under `payload` and stands for all of its members.
+// Groups are prepended to those written before, so it can be called many times.
+// A batch is sorted by payloads, and each of its pages is indexed by the range of
+// payloads on it, so that a group is found by reading the pages holding it.
+pub fn alias(
+    index: impl RelationRead + RelationWrite,
+    mut groups: Vec<(NonZero<u64>, Vec<NonZero<u64>>)>,
+) {
+    if groups.is_empty() {
+        return;
+    }
+    groups.sort_unstable_by_key(|&(payload, _)| payload);
+    let mut tape = TapeWriter::<_, AliasTuple>::create(&index, false);
+    let batch = tape.first();
+    let mut pages = Vec::<AliasIndexTuple>::new();
+    for (payload, members) in groups {
+        for chunk in members.chunks(CHUNK) {
+            let (page, _) = tape.push(AliasTuple {
+                payload,
+                members: chunk.iter().copied().map(Some).collect(),
+            });
+            match pages.last_mut() {
+                Some(last) if last.page == page => last.last = payload,
+                _ => pages.push(AliasIndexTuple {
+                    first: payload,
+                    last: payload,
+                    batch,
+                    page,
+                }),
+            }
+        }
+    }
+    drop(tape);
+    let mut tape = TapeWriter::<_, AliasIndexTuple>::create(&index, false);
+    for page in pages {
+        tape.push(page);
+    }
+    let aliases_index_first = tape.first();
+    drop(tape);
+    let mut meta_guard = index.write(0, false);
+    let meta_bytes = meta_guard.get_mut(1).expect("data corruption");
+    let mut meta_tuple = MetaTuple::deserialize_mut(meta_bytes);
+    let previous = std::mem::replace(meta_tuple.aliases_first(), batch);
+    prepend(&index, batch, previous);
+    let previous = std::mem::replace(meta_tuple.aliases_index_first(), aliases_index_first);
+    prepend(&index, aliases_index_first, previous);
+}
+
+fn prepend(index: &impl RelationWrite, first: u32, previous: u32) {
+    if previous == u32::MAX {
+        return;
+    }
+    let mut current = first;
+    loop {
+        let mut guard = index.write(current, false);
+        let next = guard.get_opaque().next;
+        if next == u32::MAX {
+            guard.get_opaque_mut().next = previous;
+            break;
+        }
+        current = next;
+    }
+}
+
+// Only the index of pages is read up front. Batches are sorted on their own, so a
+// group is looked up in every batch, but only in pages whose ranges cover it.
+pub struct Aliases<R> {
+    index: R,
+    batches: Vec<Vec<(NonZero<u64>, NonZero<u64>, u32)>>,
+}
+
+impl<R: RelationRead> Aliases<R> {
+    pub fn new(index: R) -> Self {
+        let meta_guard = index.read(0);
+        let meta_bytes = meta_guard.get(1).expect("data corruption");
+        let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
+        let aliases_index_first = meta_tuple.aliases_index_first();
+        drop(meta_guard);
+
+        let mut batches = Vec::<(u32, Vec<_>)>::new();
+        let mut current = aliases_index_first;
+        while current != u32::MAX {
+            let guard = index.read(current);
+            for i in 1..=guard.len() {
+                let bytes = guard.get(i).expect("data corruption");
+                let tuple = AliasIndexTuple::deserialize_ref(bytes);
+                let page = (tuple.first(), tuple.last(), tuple.page());
+                match batches.last_mut() {
+                    Some((batch, pages)) if *batch == tuple.batch() => pages.push(page),
+                    _ => batches.push((tuple.batch(), vec![page])),
+                }
+            }
+            current = guard.get_opaque().next;
+        }
+        Self {
+            index,
+            batches: batches.into_iter().map(|(_, pages)| pages).collect(),
+        }
+    }
+    pub fn get(&self, payload: NonZero<u64>) -> Vec<NonZero<u64>> {
+        let mut results = Vec::new();
+        for pages in self.batches.iter() {
+            let start = pages.partition_point(|&(_, last, _)| last < payload);
+            for &(_, _, page) in pages[start..]
+                .iter()
+                .take_while(|&&(first, _, _)| first <= payload)
+            {
+                let guard = self.index.read(page);
+                for i in 1..=guard.len() {
+                    let bytes = guard.get(i).expect("data corruption");
+                    let tuple = AliasTuple::deserialize_ref(bytes);
+                    if tuple.payload() == payload {
+                        results.extend(tuple.members().iter().flatten().copied());
+                    }
+                }
+            }
+        }
+        results
+    }
+}
+
+// Members of a group may be split into many chunks, which are read a page at a time.
+pub fn chunks(index: impl RelationRead, mut callback: impl FnMut(NonZero<u64>, &[NonZero<u64>])) {
+    let meta_guard = index.read(0);
+    let meta_bytes = meta_guard.get(1).expect("data corruption");
+    let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
+    let aliases_first = meta_tuple.aliases_first();
+    drop(meta_guard);
+
+    let mut current = aliases_first;
+    while current != u32::MAX {
+        let guard = index.read(current);
+        for i in 1..=guard.len() {
+            let bytes = guard.get(i).expect("data corruption");
+            let tuple = AliasTuple::deserialize_ref(bytes);
+            let members = tuple
+                .members()
+                .iter()
+                .flatten()
+                .copied()
+                .collect::<Vec<_>>();
+            if !members.is_empty() {
+                callback(tuple.payload(), &members);
+            }
+        }
+        current = guard.get_opaque().next;
+    }
+}
+
+// Maps payloads of rows to payloads under which they are indexed.
+#[must_use]
+pub fn resolve(index: impl RelationRead, payloads: &[NonZero<u64>]) -> Vec<NonZero<u64>> {
+    let mut owners = HashMap::new();
+    chunks(index, |payload, members| {
+        owners.extend(members.iter().map(|&member| (member, payload)));
+    });
+    payloads
+        .iter()
+        .map(|payload| owners.get(payload).copied().unwrap_or(*payload))
+        .collect()
+}
+
+// Removes dead members and returns all groups, along with groups that have no members left.
+pub(crate) fn prune(
+    index: impl RelationRead + RelationWrite,
+    check: impl Fn(),
+    callback: impl Fn(NonZero<u64>) -> bool,
+) -> (HashSet<NonZero<u64>>, HashSet<NonZero<u64>>) {
+    let meta_guard = index.read(0);
+    let meta_bytes = meta_guard.get(1).expect("data corruption");
+    let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
+    let aliases_first = meta_tuple.aliases_first();
+    drop(meta_guard);
+
+    let mut groups = HashSet::new();
+    let mut alive = HashSet::new();
+    let mut current = aliases_first;
+    while current != u32::MAX {
+        check();
+        let mut write = index.write(current, false);
+        for i in 1..=write.len() {
+            let bytes = write.get_mut(i).expect("data corruption");
+            let mut tuple = AliasTuple::deserialize_mut(bytes);
+            let payload = tuple.payload();
+            groups.insert(payload);
+            for p in tuple.members().iter_mut() {
+                if Some(true) == p.map(&callback) {
+                    *p = None;
+                }
+                if p.is_some() {
+                    alive.insert(payload);
+                }
+            }
+        }
+        current = write.get_opaque().next;
+    }
+    let dead = groups.difference(&alive).copied().collect();
+    (groups, dead)
+}
@@ -0,0 +1,66 @@
+use crate::tuples::*;
+use crate::{Page, RelationRead};
+
+// Codes of both H1 tuples and frozen tuples are counted. Offsets are counted from the
+// start of the page, as `alloc_aligned` does.
+pub fn misaligned<R: RelationRead>(index: R, check: impl Fn()) -> u64 {
+    let meta_guard = index.read(0);
+    let meta_bytes = meta_guard.get(1).expect("data corruption");
+    let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
+    let height_of_root = meta_tuple.height_of_root();
+    let root_first = meta_tuple.root_first();
+    let code_alignment = meta_tuple.code_alignment() as usize;
+    drop(meta_guard);
+
+    let mut result = 0_u64;
+    let mut state: Vec<u32> = vec![root_first];
+    for _ in (1..height_of_root).rev() {
+        let mut children = Vec::new();
+        for first in state {
+            let mut current = first;
+            while current != u32::MAX {
+                check();
+                let guard = index.read(current);
+                let base = (&*guard as *const R::Page).cast::<u8>() as usize;
+                for i in 1..=guard.len() {
+                    let bytes = guard.get(i).expect("data corruption");
+                    let elements = match H1Tuple::deserialize_ref(bytes) {
+                        H1TupleReader::_0(tuple) => {
+                            children.extend_from_slice(tuple.first());
+                            tuple.elements()
+                        }
+                        H1TupleReader::_1(tuple) => tuple.elements(),
+                    };
+                    let offset = elements.as_ptr() as usize - base;
+                    result += (offset % code_alignment != 0) as u64;
+                }
+                current = guard.get_opaque().next;
+            }
+        }
+        state = children;
+    }
+    for first in state {
+        let jump_guard = index.read(first);
+        let jump_bytes = jump_guard.get(1).expect("data corruption");
+        let jump_tuple = JumpTuple::deserialize_ref(jump_bytes);
+        let frozen_first = jump_tuple.frozen_first();
+        drop(jump_guard);
+        let mut current = frozen_first;
+        while current != u32::MAX {
+            check();
+            let guard = index.read(current);
+            let base = (&*guard as *const R::Page).cast::<u8>() as usize;
+            for i in 1..=guard.len() {
+                let bytes = guard.get(i).expect("data corruption");
+                let elements = match FrozenTuple::deserialize_ref(bytes) {
+                    FrozenTupleReader::_0(tuple) => tuple.elements(),
+                    FrozenTupleReader::_1(tuple) => tuple.elements(),
+                };
+                let offset = elements.as_ptr() as usize - base;
+                result += (offset % code_alignment != 0) as u64;
+            }
+            current = guard.get_opaque().next;
+        }
+    }
+    result
+}
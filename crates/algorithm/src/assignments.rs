@@ -0,0 +1,73 @@
+use crate::closure_lifetime_binder::{id_0, id_1};
+use crate::operator::FunctionalAccessor;
+use crate::tuples::*;
+use crate::{Page, RelationRead, tape};
+use std::num::NonZero;
+
+// Lists are numbered in the order of the tree, and each list is read page by page.
+pub fn assignments(
+    index: impl RelationRead,
+    check: impl Fn(),
+    mut callback: impl FnMut(u32, NonZero<u64>),
+) {
+    let meta_guard = index.read(0);
+    let meta_bytes = meta_guard.get(1).expect("data corruption");
+    let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
+    let height_of_root = meta_tuple.height_of_root();
+    let root_first = meta_tuple.root_first();
+    drop(meta_guard);
+
+    type State = Vec<u32>;
+    let mut state: State = vec![root_first];
+    let step = |state: State| {
+        let mut results = Vec::new();
+        for first in state {
+            tape::read_h1_tape(
+                index.clone(),
+                first,
+                || FunctionalAccessor::new((), id_0(|_, _| ()), id_1(|_, _| [(); 32])),
+                |(), _, first, _| results.push(first),
+                |_| check(),
+            );
+        }
+        results
+    };
+    for _ in (1..height_of_root).rev() {
+        state = step(state);
+    }
+    for (list, first) in state.into_iter().enumerate() {
+        let jump_guard = index.read(first);
+        let jump_bytes = jump_guard.get(1).expect("data corruption");
+        let jump_tuple = JumpTuple::deserialize_ref(jump_bytes);
+        let frozen_first = jump_tuple.frozen_first();
+        let appendable_first = jump_tuple.appendable_first();
+        drop(jump_guard);
+        let mut current = frozen_first;
+        while current != u32::MAX {
+            check();
+            let guard = index.read(current);
+            for i in 1..=guard.len() {
+                let bytes = guard.get(i).expect("data corruption");
+                if let FrozenTupleReader::_0(tuple) = FrozenTuple::deserialize_ref(bytes) {
+                    for p in tuple.payload().iter().flatten() {
+                        callback(list as u32, *p);
+                    }
+                }
+            }
+            current = guard.get_opaque().next;
+        }
+        let mut current = appendable_first;
+        while current != u32::MAX {
+            check();
+            let guard = index.read(current);
+            for i in 1..=guard.len() {
+                let bytes = guard.get(i).expect("data corruption");
+                let tuple = AppendableTuple::deserialize_ref(bytes);
+                if let Some(p) = tuple.payload() {
+                    callback(list as u32, p);
+                }
+            }
+            current = guard.get_opaque().next;
+        }
+    }
+}
@@ -17,6 +17,7 @@ pub fn build<O: Operator>(
     }
     let dims = vector_options.dims;
     let is_residual = vchordrq_options.residual_quantization;
+    let code_alignment = vchordrq_options.code_alignment;
     let mut meta = TapeWriter::<_, MetaTuple>::create(&index, false);
     assert_eq!(meta.first(), 0);
     let freepage = TapeWriter::<_, FreepageTuple>::create(&index, false);
@@ -65,10 +66,16 @@ pub fn build<O: Operator>(
                     frozen_first: frozen_tape.first(),
                     appendable_first: appendable_tape.first(),
                     tuples: 0,
+                    radius: 0.0,
                 });
                 level.push(jump.first());
             } else {
-                let mut tape = H1TapeWriter::create(&index, O::Vector::count(dims as _), false);
+                let mut tape = H1TapeWriter::create(
+                    &index,
+                    O::Vector::count(dims as _),
+                    code_alignment,
+                    false,
+                );
                 let h2_mean = structures[i].means[j].as_borrowed();
                 let h2_children = structures[i].children[j].as_slice();
                 for child in h2_children.iter().copied() {
@@ -100,27 +107,38 @@ pub fn build<O: Operator>(
                         padding_pack(chunk.iter().map(|x| rabitq::pack_to_u4(&x.signs)));
                     loop {
                         let freespace = tape.freespace();
-                        if H1Tuple::estimate_size_0(O::Vector::count(dims as _), remain.len())
-                            <= freespace as usize
+                        if H1Tuple::estimate_size_0(
+                            O::Vector::count(dims as _),
+                            remain.len(),
+                            code_alignment,
+                        ) <= freespace as usize
                         {
-                            tape.tape_put(H1Tuple::_0 {
-                                head: any_pack(chunk.iter().map(|x| x.head)),
-                                dis_u_2: any_pack(chunk.iter().map(|x| x.dis_u_2)),
-                                factor_ppc: any_pack(chunk.iter().map(|x| x.factor_ppc)),
-                                factor_ip: any_pack(chunk.iter().map(|x| x.factor_ip)),
-                                factor_err: any_pack(chunk.iter().map(|x| x.factor_err)),
-                                first: any_pack(chunk.iter().map(|x| x.extra)),
-                                prefetch: fix(chunk.iter().map(|x| x.prefetch.as_slice())),
-                                len: chunk.len() as _,
-                                elements: remain,
-                            });
+                            tape.tape_put_aligned(
+                                H1Tuple::_0 {
+                                    head: any_pack(chunk.iter().map(|x| x.head)),
+                                    dis_u_2: any_pack(chunk.iter().map(|x| x.dis_u_2)),
+                                    factor_ppc: any_pack(chunk.iter().map(|x| x.factor_ppc)),
+                                    factor_ip: any_pack(chunk.iter().map(|x| x.factor_ip)),
+                                    factor_err: any_pack(chunk.iter().map(|x| x.factor_err)),
+                                    first: any_pack(chunk.iter().map(|x| x.extra)),
+                                    prefetch: fix(chunk.iter().map(|x| x.prefetch.as_slice())),
+                                    len: chunk.len() as _,
+                                    elements: remain,
+                                },
+                                code_alignment,
+                            );
                             break;
                         }
-                        if let Some(w) = H1Tuple::fit_1(O::Vector::count(dims as _), freespace) {
+                        if let Some(w) =
+                            H1Tuple::fit_1(O::Vector::count(dims as _), freespace, code_alignment)
+                        {
                             let (left, right) = remain.split_at(std::cmp::min(w, remain.len()));
-                            tape.tape_put(H1Tuple::_1 {
-                                elements: left.to_vec(),
-                            });
+                            tape.tape_put_aligned(
+                                H1Tuple::_1 {
+                                    elements: left.to_vec(),
+                                },
+                                code_alignment,
+                            );
                             remain = right.to_vec();
                         } else {
                             tape.tape_move();
@@ -137,6 +155,8 @@ pub fn build<O: Operator>(
         height_of_root: structures.len() as u32,
         is_residual,
         rerank_in_heap: vchordrq_options.rerank_in_table,
+        code_alignment,
+        sort_lists: vchordrq_options.sort_lists,
         vectors_first: vectors.first(),
         root_prefetch: pointer_of_means
             .last()
@@ -152,6 +172,8 @@ pub fn build<O: Operator>(
             .expect("internal error: empty structure")[0],
         freepage_first: freepage.first(),
         cells: structures.iter().map(|s| s.len() as _).collect(),
+        aliases_first: u32::MAX,
+        aliases_index_first: u32::MAX,
     });
 }
 
@@ -162,17 +184,19 @@ where
     tape: TapeWriter<'a, R, H1Tuple>,
     branches: Vec<Branch<u32>>,
     prefetch: usize,
+    align: u16,
 }
 
 impl<'a, R> H1TapeWriter<'a, R>
 where
     R: RelationWrite + 'a,
 {
-    fn create(index: &'a R, prefetch: usize, tracking_freespace: bool) -> Self {
+    fn create(index: &'a R, prefetch: usize, align: u16, tracking_freespace: bool) -> Self {
         Self {
             tape: TapeWriter::create(index, tracking_freespace),
             branches: Vec::new(),
             prefetch,
+            align,
         }
     }
     fn push(&mut self, branch: Branch<u32>) {
@@ -181,25 +205,33 @@ where
             let mut remain = padding_pack(chunk.iter().map(|x| rabitq::pack_to_u4(&x.signs)));
             loop {
                 let freespace = self.tape.freespace();
-                if H1Tuple::estimate_size_0(self.prefetch, remain.len()) <= freespace as usize {
-                    self.tape.tape_put(H1Tuple::_0 {
-                        head: chunk.each_ref().map(|x| x.head),
-                        dis_u_2: chunk.each_ref().map(|x| x.dis_u_2),
-                        factor_ppc: chunk.each_ref().map(|x| x.factor_ppc),
-                        factor_ip: chunk.each_ref().map(|x| x.factor_ip),
-                        factor_err: chunk.each_ref().map(|x| x.factor_err),
-                        first: chunk.each_ref().map(|x| x.extra),
-                        prefetch: fix(chunk.each_ref().map(|x| x.prefetch.as_slice())),
-                        len: chunk.len() as _,
-                        elements: remain,
-                    });
+                if H1Tuple::estimate_size_0(self.prefetch, remain.len(), self.align)
+                    <= freespace as usize
+                {
+                    self.tape.tape_put_aligned(
+                        H1Tuple::_0 {
+                            head: chunk.each_ref().map(|x| x.head),
+                            dis_u_2: chunk.each_ref().map(|x| x.dis_u_2),
+                            factor_ppc: chunk.each_ref().map(|x| x.factor_ppc),
+                            factor_ip: chunk.each_ref().map(|x| x.factor_ip),
+                            factor_err: chunk.each_ref().map(|x| x.factor_err),
+                            first: chunk.each_ref().map(|x| x.extra),
+                            prefetch: fix(chunk.each_ref().map(|x| x.prefetch.as_slice())),
+                            len: chunk.len() as _,
+                            elements: remain,
+                        },
+                        self.align,
+                    );
                     break;
                 }
-                if let Some(w) = H1Tuple::fit_1(self.prefetch, freespace) {
+                if let Some(w) = H1Tuple::fit_1(self.prefetch, freespace, self.align) {
                     let (left, right) = remain.split_at(std::cmp::min(w, remain.len()));
-                    self.tape.tape_put(H1Tuple::_1 {
-                        elements: left.to_vec(),
-                    });
+                    self.tape.tape_put_aligned(
+                        H1Tuple::_1 {
+                            elements: left.to_vec(),
+                        },
+                        self.align,
+                    );
                     remain = right.to_vec();
                 } else {
                     self.tape.tape_move();
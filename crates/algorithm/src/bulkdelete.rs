@@ -1,7 +1,7 @@
 use crate::closure_lifetime_binder::{id_0, id_1};
 use crate::operator::{FunctionalAccessor, Operator};
 use crate::tuples::*;
-use crate::{Page, RelationRead, RelationWrite, tape};
+use crate::{Page, RelationRead, RelationWrite, aliases, tape};
 use std::num::NonZero;
 
 pub fn bulkdelete<O: Operator>(
@@ -16,6 +16,14 @@ pub fn bulkdelete<O: Operator>(
     let root_first = meta_tuple.root_first();
     let vectors_first = meta_tuple.vectors_first();
     drop(meta_guard);
+    let (groups, dead) = aliases::prune(index.clone(), &check, &callback);
+    let callback = |p: NonZero<u64>| {
+        if groups.contains(&p) {
+            dead.contains(&p)
+        } else {
+            callback(p)
+        }
+    };
     {
         type State = Vec<u32>;
         let mut state: State = vec![root_first];
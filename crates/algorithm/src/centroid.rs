@@ -0,0 +1,175 @@
+use crate::closure_lifetime_binder::{id_0, id_1};
+use crate::operator::{FunctionalAccessor, LAccess, Operator, Vector};
+use crate::tuples::*;
+use crate::{Page, RelationRead, tape, vectors};
+use distance::Distance;
+use simd::Floating;
+use vector::{VectorBorrowed, VectorOwned};
+
+type State = Vec<(Distance, u32, u16, Vec<u32>)>;
+
+// Upper levels are probed as a scan does, but by exact distances. Probed lists
+// are returned in ascending order of distances, along with all lists, which are
+// numbered as in `assignments`.
+fn probe<R: RelationRead, O: Operator>(
+    index: &R,
+    vector: &O::Vector,
+    probes: &[u32],
+) -> (Vec<u32>, State) {
+    let meta_guard = index.read(0);
+    let meta_bytes = meta_guard.get(1).expect("data corruption");
+    let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
+    let dims = meta_tuple.dims();
+    let height_of_root = meta_tuple.height_of_root();
+    assert_eq!(dims, vector.as_borrowed().dims(), "unmatched dimensions");
+    if height_of_root as usize != 1 + probes.len() {
+        panic!(
+            "usage: need {} probes, but {} probes provided",
+            height_of_root - 1,
+            probes.len()
+        );
+    }
+    let root_prefetch = meta_tuple.root_prefetch().to_vec();
+    let root_head = meta_tuple.root_head();
+    let root_first = meta_tuple.root_first();
+    drop(meta_guard);
+
+    let distance = |head: u16, prefetch: &[u32]| {
+        vectors::read_for_h1_tuple::<R, O, _>(
+            head,
+            prefetch.iter().map(|&id| index.read(id)),
+            LAccess::new(
+                O::Vector::unpack(vector.as_borrowed()),
+                O::DistanceAccessor::default(),
+            ),
+        )
+    };
+    let children = |first: u32| {
+        let mut results = Vec::new();
+        tape::read_h1_tape(
+            index.clone(),
+            first,
+            || FunctionalAccessor::new((), id_0(|_, _| ()), id_1(|_, _| [(); 32])),
+            |(), head, first, prefetch| results.push((first, head, prefetch.to_vec())),
+            |_| (),
+        );
+        results
+    };
+
+    let mut lists: Vec<u32> = vec![root_first];
+    let mut state: State = vec![(
+        distance(root_head, &root_prefetch),
+        root_first,
+        root_head,
+        root_prefetch,
+    )];
+    for i in (1..height_of_root).rev() {
+        lists = lists
+            .into_iter()
+            .flat_map(&children)
+            .map(|(first, ..)| first)
+            .collect();
+        let mut results = state
+            .into_iter()
+            .flat_map(|(_, first, ..)| children(first))
+            .map(|(first, head, prefetch)| (distance(head, &prefetch), first, head, prefetch))
+            .collect::<Vec<_>>();
+        results.sort_by_key(|&(distance, first, ..)| (distance, first));
+        results.truncate(probes[i as usize - 1] as _);
+        state = results;
+    }
+    (lists, state)
+}
+
+pub fn nearest_centroid<R: RelationRead, O: Operator>(
+    index: R,
+    vector: O::Vector,
+    probes: Vec<u32>,
+) -> (u32, Vec<f32>, Distance)
+where
+    <O::Vector as Vector>::Element: Floating,
+{
+    let (lists, state) = probe::<R, O>(&index, &vector, &probes);
+    let (distance, first, head, prefetch) = state
+        .into_iter()
+        .min_by_key(|&(distance, first, ..)| (distance, first))
+        .expect("no lists are probed");
+    let list = lists
+        .iter()
+        .position(|&x| x == first)
+        .expect("data corruption");
+    let centroid = vectors::read_for_h1_tuple::<R, O, _>(
+        head,
+        prefetch.iter().map(|&id| index.read(id)),
+        FunctionalAccessor::new(
+            Vec::<<O::Vector as Vector>::Element>::new(),
+            Vec::<<O::Vector as Vector>::Element>::extend_from_slice,
+            |elements: Vec<_>, _| elements,
+        ),
+    );
+    let centroid = <<O::Vector as Vector>::Element as Floating>::vector_to_f32(&centroid);
+    (list as u32, centroid, distance)
+}
+
+// Probed lists with distances to their centroids and numbers of their vectors.
+// `probes` is for the bottom level and `upper` is for the levels above.
+pub fn probed_lists<R: RelationRead, O: Operator>(
+    index: R,
+    vector: O::Vector,
+    upper: Vec<u32>,
+    probes: u32,
+) -> Vec<(u32, Distance, u32)> {
+    let meta_guard = index.read(0);
+    let meta_bytes = meta_guard.get(1).expect("data corruption");
+    let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
+    let height_of_root = meta_tuple.height_of_root();
+    drop(meta_guard);
+    let probes = if height_of_root > 1 {
+        std::iter::once(probes).chain(upper).collect()
+    } else {
+        Vec::new()
+    };
+    let (lists, state) = probe::<R, O>(&index, &vector, &probes);
+    state
+        .into_iter()
+        .map(|(distance, first, ..)| {
+            let list = lists
+                .iter()
+                .position(|&x| x == first)
+                .expect("data corruption");
+            (list as u32, distance, size(&index, first))
+        })
+        .collect()
+}
+
+fn size(index: &impl RelationRead, first: u32) -> u32 {
+    let jump_guard = index.read(first);
+    let jump_bytes = jump_guard.get(1).expect("data corruption");
+    let jump_tuple = JumpTuple::deserialize_ref(jump_bytes);
+    let frozen_first = jump_tuple.frozen_first();
+    let appendable_first = jump_tuple.appendable_first();
+    drop(jump_guard);
+    let mut size = 0_u32;
+    let mut current = frozen_first;
+    while current != u32::MAX {
+        let guard = index.read(current);
+        for i in 1..=guard.len() {
+            let bytes = guard.get(i).expect("data corruption");
+            if let FrozenTupleReader::_0(tuple) = FrozenTuple::deserialize_ref(bytes) {
+                size += tuple.payload().iter().flatten().count() as u32;
+            }
+        }
+        current = guard.get_opaque().next;
+    }
+    let mut current = appendable_first;
+    while current != u32::MAX {
+        let guard = index.read(current);
+        for i in 1..=guard.len() {
+            let bytes = guard.get(i).expect("data corruption");
+            let tuple = AppendableTuple::deserialize_ref(bytes);
+            size += tuple.payload().is_some() as u32;
+        }
+        current = guard.get_opaque().next;
+    }
+    size
+}
@@ -0,0 +1,52 @@
+use crate::operator::Operator;
+use crate::tuples::*;
+use crate::{Page, RelationRead, RelationWrite};
+
+// Original vectors are freed, and the index reranks by vectors fetched from the table
+// as if it were built with `rerank_in_table`, so results stay exact.
+pub fn compact<O: Operator>(index: impl RelationRead + RelationWrite, check: impl Fn()) -> u64 {
+    let vectors_first = {
+        let mut meta_guard = index.write(0, false);
+        let meta_bytes = meta_guard.get_mut(1).expect("data corruption");
+        let mut meta_tuple = MetaTuple::deserialize_mut(meta_bytes);
+        if (*meta_tuple.rerank_in_heap()).into() {
+            panic!("usage: the index does not store original vectors");
+        }
+        // scans and insertions starting from now do not touch original vectors
+        *meta_tuple.rerank_in_heap() = true.into();
+        drop(meta_guard);
+        let meta_guard = index.read(0);
+        let meta_bytes = meta_guard.get(1).expect("data corruption");
+        MetaTuple::deserialize_ref(meta_bytes).vectors_first()
+    };
+    let mut reclaimed = 0_u64;
+    let mut current = vectors_first;
+    while current != u32::MAX {
+        check();
+        let read = index.read(current);
+        let flag = (1..=read.len()).any(|i| {
+            read.get(i).is_some_and(|bytes| {
+                let tuple = VectorTuple::<O::Vector>::deserialize_ref(bytes);
+                tuple.payload().is_some()
+            })
+        });
+        if flag {
+            drop(read);
+            let mut write = index.write(current, true);
+            let freespace = write.freespace();
+            for i in 1..=write.len() {
+                if let Some(bytes) = write.get(i) {
+                    let tuple = VectorTuple::<O::Vector>::deserialize_ref(bytes);
+                    if tuple.payload().is_some() {
+                        write.free(i);
+                    }
+                }
+            }
+            reclaimed += (write.freespace() - freespace) as u64;
+            current = write.get_opaque().next;
+        } else {
+            current = read.get_opaque().next;
+        }
+    }
+    reclaimed
+}
@@ -4,6 +4,9 @@ use crate::{Page, RelationRead};
 pub struct Cost {
     pub dims: u32,
     pub is_residual: bool,
+    pub rerank_in_heap: bool,
+    pub sort_lists: bool,
+    pub code_alignment: u16,
     pub cells: Vec<u32>,
 }
 
@@ -14,12 +17,18 @@ pub fn cost(index: impl RelationRead) -> Cost {
     let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
     let dims = meta_tuple.dims();
     let is_residual = meta_tuple.is_residual();
+    let rerank_in_heap = meta_tuple.rerank_in_heap();
+    let sort_lists = meta_tuple.sort_lists();
+    let code_alignment = meta_tuple.code_alignment();
     let cells = meta_tuple.cells().to_vec();
     drop(meta_guard);
 
     Cost {
         dims,
         is_residual,
+        rerank_in_heap,
+        sort_lists,
+        code_alignment,
         cells,
     }
 }
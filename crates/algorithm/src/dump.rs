@@ -0,0 +1,253 @@
+use crate::closure_lifetime_binder::{id_0, id_1};
+use crate::operator::{FunctionalAccessor, Operator, Vector};
+use crate::tape::{fix_0, fix_1};
+use crate::tuples::*;
+use crate::{Page, RelationRead, RelationWrite, tape, vectors};
+use simd::Floating;
+use simd::fast_scan::unpack;
+use std::num::NonZero;
+use vector::VectorOwned;
+
+// Lists are numbered as in `assignments`, and a list comes before its codes. A code
+// carries the vector of its row if vectors are stored in the index, which is in the
+// projected space, like the code.
+pub enum Dumped<V> {
+    List {
+        radius: f32,
+    },
+    Code {
+        payload: NonZero<u64>,
+        code: rabitq::Code,
+        vector: Option<V>,
+    },
+}
+
+fn bottom(index: impl RelationRead, check: impl Fn()) -> Vec<u32> {
+    let meta_guard = index.read(0);
+    let meta_bytes = meta_guard.get(1).expect("data corruption");
+    let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
+    let height_of_root = meta_tuple.height_of_root();
+    let root_first = meta_tuple.root_first();
+    drop(meta_guard);
+
+    let mut state = vec![root_first];
+    for _ in (1..height_of_root).rev() {
+        let mut results = Vec::new();
+        for first in state {
+            tape::read_h1_tape(
+                index.clone(),
+                first,
+                || FunctionalAccessor::new((), id_0(|_, _| ()), id_1(|_, _| [(); 32])),
+                |(), _, first, _| results.push(first),
+                |_| check(),
+            );
+        }
+        state = results;
+    }
+    state
+}
+
+// Codes whose vectors are freed are of dead rows, so they are skipped.
+pub fn dump<R: RelationRead, O: Operator>(
+    index: R,
+    check: impl Fn(),
+    mut callback: impl FnMut(Dumped<Vec<f32>>),
+) where
+    <O::Vector as Vector>::Element: Floating,
+{
+    let meta_guard = index.read(0);
+    let meta_bytes = meta_guard.get(1).expect("data corruption");
+    let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
+    let dims = meta_tuple.dims();
+    let rerank_in_heap = meta_tuple.rerank_in_heap();
+    drop(meta_guard);
+
+    let vector = |payload: NonZero<u64>, head: u16, prefetch: &[u32]| {
+        let elements = vectors::read_for_h0_tuple::<R, O, _>(
+            head,
+            prefetch.iter().map(|&id| index.read(id)),
+            payload,
+            FunctionalAccessor::new(
+                Vec::<<O::Vector as Vector>::Element>::new(),
+                Vec::<<O::Vector as Vector>::Element>::extend_from_slice,
+                |elements: Vec<_>, _| elements,
+            ),
+        )?;
+        Some(<<O::Vector as Vector>::Element as Floating>::vector_to_f32(
+            &elements,
+        ))
+    };
+    let make = |payload, code, (head, prefetch): (u16, Vec<u32>)| {
+        let vector = if !rerank_in_heap {
+            Some(vector(payload, head, &prefetch)?)
+        } else {
+            None
+        };
+        Some(Dumped::Code {
+            payload,
+            code,
+            vector,
+        })
+    };
+
+    for first in bottom(index.clone(), &check) {
+        let jump_guard = index.read(first);
+        let jump_bytes = jump_guard.get(1).expect("data corruption");
+        let jump_tuple = JumpTuple::deserialize_ref(jump_bytes);
+        let radius = jump_tuple.radius();
+        let frozen_first = jump_tuple.frozen_first();
+        let appendable_first = jump_tuple.appendable_first();
+        drop(jump_guard);
+        callback(Dumped::List { radius });
+
+        let mut elements = Vec::<[u8; 16]>::new();
+        let mut current = frozen_first;
+        while current != u32::MAX {
+            check();
+            let guard = index.read(current);
+            let mut codes = Vec::new();
+            for i in 1..=guard.len() {
+                let bytes = guard.get(i).expect("data corruption");
+                match FrozenTuple::deserialize_ref(bytes) {
+                    FrozenTupleReader::_0(tuple) => {
+                        elements.extend_from_slice(tuple.elements());
+                        let unpacked = unpack(&elements);
+                        let metadata = tuple.metadata();
+                        let prefetch = fix_0(tuple.prefetch());
+                        for j in 0..32 {
+                            if let Some(payload) = tuple.payload()[j] {
+                                let f = |&x: &u8| [x & 1 != 0, x & 2 != 0, x & 4 != 0, x & 8 != 0];
+                                let mut signs = unpacked[j].iter().flat_map(f).collect::<Vec<_>>();
+                                signs.truncate(dims as _);
+                                let code = rabitq::Code {
+                                    dis_u_2: metadata.0[j],
+                                    factor_ppc: metadata.1[j],
+                                    factor_ip: metadata.2[j],
+                                    factor_err: metadata.3[j],
+                                    signs,
+                                };
+                                let location = (tuple.mean()[j], fix_1(prefetch[j]).to_vec());
+                                codes.push((payload, code, location));
+                            }
+                        }
+                        elements.clear();
+                    }
+                    FrozenTupleReader::_1(tuple) => {
+                        elements.extend_from_slice(tuple.elements());
+                    }
+                }
+            }
+            current = guard.get_opaque().next;
+            drop(guard);
+            for (payload, code, location) in codes {
+                if let Some(dumped) = make(payload, code, location) {
+                    callback(dumped);
+                }
+            }
+        }
+
+        let mut current = appendable_first;
+        while current != u32::MAX {
+            check();
+            let guard = index.read(current);
+            let mut codes = Vec::new();
+            for i in 1..=guard.len() {
+                let bytes = guard.get(i).expect("data corruption");
+                let tuple = AppendableTuple::deserialize_ref(bytes);
+                if let Some(payload) = tuple.payload() {
+                    let (dis_u_2, factor_ppc, factor_ip, factor_err, elements) = tuple.code();
+                    let mut signs = elements
+                        .iter()
+                        .flat_map(|x| std::array::from_fn::<_, 64, _>(|i| *x & (1 << i) != 0))
+                        .collect::<Vec<_>>();
+                    signs.truncate(dims as _);
+                    let code = rabitq::Code {
+                        dis_u_2,
+                        factor_ppc,
+                        factor_ip,
+                        factor_err,
+                        signs,
+                    };
+                    let location = (tuple.head(), tuple.prefetch().to_vec());
+                    codes.push((payload, code, location));
+                }
+            }
+            current = guard.get_opaque().next;
+            drop(guard);
+            for (payload, code, location) in codes {
+                if let Some(dumped) = make(payload, code, location) {
+                    callback(dumped);
+                }
+            }
+        }
+    }
+}
+
+// The index is built from the same structures, so its lists are those of the dump.
+// Codes are appended, and `maintain` moves them to frozen tapes.
+pub fn restore<R: RelationRead + RelationWrite, O: Operator>(
+    index: R,
+    check: impl Fn(),
+    dumped: impl Iterator<Item = Dumped<O::Vector>>,
+) {
+    let meta_guard = index.read(0);
+    let meta_bytes = meta_guard.get(1).expect("data corruption");
+    let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
+    let dims = meta_tuple.dims();
+    let rerank_in_heap = meta_tuple.rerank_in_heap();
+    let vectors_first = meta_tuple.vectors_first();
+    drop(meta_guard);
+
+    let mut lists = bottom(index.clone(), &check).into_iter();
+    let mut appendable_first = None;
+    for dumped in dumped {
+        check();
+        match dumped {
+            Dumped::List { radius } => {
+                let first = lists
+                    .next()
+                    .expect("the dump has more lists than the index");
+                let mut jump_guard = index.write(first, false);
+                let jump_bytes = jump_guard.get_mut(1).expect("data corruption");
+                let mut jump_tuple = JumpTuple::deserialize_mut(jump_bytes);
+                if let Some(x) = jump_tuple.radius() {
+                    *x = radius;
+                }
+                appendable_first = Some(*jump_tuple.appendable_first());
+            }
+            Dumped::Code {
+                payload,
+                code,
+                vector,
+            } => {
+                let appendable_first = appendable_first.expect("the dump has a code before lists");
+                assert_eq!(code.signs.len(), dims as usize, "unmatched dimensions");
+                let (prefetch, head) = match (rerank_in_heap, vector) {
+                    (true, _) => (Vec::new(), 0),
+                    (false, Some(vector)) => vectors::append::<O>(
+                        index.clone(),
+                        vectors_first,
+                        vector.as_borrowed(),
+                        payload,
+                    ),
+                    (false, None) => panic!("the dump has no vector for a code"),
+                };
+                let bytes = AppendableTuple::serialize(&AppendableTuple {
+                    head,
+                    dis_u_2: code.dis_u_2,
+                    factor_ppc: code.factor_ppc,
+                    factor_ip: code.factor_ip,
+                    factor_err: code.factor_err,
+                    payload: Some(payload),
+                    prefetch,
+                    elements: rabitq::pack_to_u64(&code.signs),
+                });
+                tape::append(index.clone(), appendable_first, &bytes, false);
+            }
+        }
+    }
+    assert!(
+        lists.next().is_none(),
+        "the dump has fewer lists than the index"
+    );
+}
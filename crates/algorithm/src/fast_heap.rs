@@ -13,6 +13,23 @@ impl<T> SortHeap<T> {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeapStrategy {
+    Auto,
+    Select,
+    Binary,
+}
+
+impl HeapStrategy {
+    pub fn threshold(self, n: usize) -> usize {
+        match self {
+            HeapStrategy::Auto => n / 384,
+            HeapStrategy::Select => n,
+            HeapStrategy::Binary => 0,
+        }
+    }
+}
+
 pub enum FastHeap<T> {
     Sorted(SortHeap<T>),
     Binary(BinaryHeap<T>),
@@ -20,8 +37,15 @@ pub enum FastHeap<T> {
 
 impl<T: Ord> FastHeap<T> {
     pub fn from_vec(vec: Vec<T>) -> Self {
+        Self::from_vec_with_strategy(vec, HeapStrategy::Auto)
+    }
+    pub fn from_vec_with_strategy(vec: Vec<T>, strategy: HeapStrategy) -> Self {
         let n = vec.len();
-        if let Some(t) = NonZero::new(n / 384) {
+        Self::from_vec_with_threshold(vec, strategy.threshold(n))
+    }
+    pub fn from_vec_with_threshold(vec: Vec<T>, threshold: usize) -> Self {
+        let n = vec.len();
+        if let Some(t) = NonZero::new(threshold.min(n)) {
             let mut inner = vec;
             let index = n - t.get();
             turboselect::select_nth_unstable(&mut inner, index);
@@ -96,6 +120,25 @@ fn test_select_heap() {
     }
 }
 
+#[test]
+fn test_heap_strategy() {
+    for n in [0, 1, 383, 384, 1000, 10000] {
+        let sequence = (0..n).map(|_| rand::random::<i32>()).collect::<Vec<_>>();
+        let results = [
+            HeapStrategy::Auto,
+            HeapStrategy::Select,
+            HeapStrategy::Binary,
+        ]
+        .map(|strategy| {
+            let mut x = FastHeap::from_vec_with_strategy(sequence.clone(), strategy);
+            std::iter::from_fn(|| x.pop()).collect::<Vec<_>>()
+        });
+        assert_eq!(results[0], results[1]);
+        assert_eq!(results[0], results[2]);
+        assert!(results[0].is_sorted_by(|a, b| a >= b));
+    }
+}
+
 #[test]
 fn test_issue_209() {
     let mut heap = FastHeap::from_vec(vec![0]);
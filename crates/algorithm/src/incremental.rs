@@ -0,0 +1,377 @@
+use crate::closure_lifetime_binder::id_2;
+use crate::operator::*;
+use crate::prefetcher::Prefetcher;
+use crate::tuples::*;
+use crate::{Bump, Page, RelationRead, RerankMethod, RerankPrecision, search, tape, vectors};
+use always_equal::AlwaysEqual;
+use distance::Distance;
+use rabitq::binary::BinaryLut;
+use rabitq::block::BlockLut;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::num::NonZero;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Instant;
+use vector::{VectorBorrowed, VectorOwned};
+
+type Item<'b> = (
+    Reverse<Distance>,
+    AlwaysEqual<&'b mut (u32, u16, &'b mut [u32])>,
+);
+
+type List<O> = (
+    Reverse<Distance>,
+    AlwaysEqual<(u32, Option<<O as Operator>::Vector>)>,
+);
+
+type Candidate = (
+    Reverse<Distance>,
+    AlwaysEqual<(NonZero<u64>, u16, Vec<u32>)>,
+);
+
+// Lists are opened lazily, in order of lower bounds of distances to their vectors,
+// so a result is returned once no unopened list or unreranked candidate can beat it.
+pub struct Incremental<R, O: Operator, F> {
+    index: R,
+    vector: O::Vector,
+    epsilon: f32,
+    code_alignment: u16,
+    default_lut: Option<(BlockLut, BinaryLut)>,
+    method: RerankMethod,
+    precision: RerankPrecision,
+    fetch: F,
+    deadline: Option<Instant>,
+    // the counter lives in the shared memory of a parallel scan, which outlives the scan
+    parallel: Option<&'static AtomicU32>,
+    claimed: Option<usize>,
+    popped: usize,
+    lists: BinaryHeap<List<O>>,
+    candidates: BinaryHeap<Candidate>,
+    cache: BinaryHeap<(Reverse<Distance>, AlwaysEqual<NonZero<u64>>)>,
+    opened: u64,
+    reranked: u64,
+}
+
+impl<R, O: Operator, F> Incremental<R, O, F> {
+    pub fn lists(&self) -> u64 {
+        self.opened
+    }
+    pub fn candidates(&self) -> u64 {
+        self.reranked + self.candidates.len() as u64
+    }
+    pub fn reranked(&self) -> u64 {
+        self.reranked
+    }
+}
+
+impl<R: RelationRead, O: Operator, F> Incremental<R, O, F>
+where
+    F: FnMut(NonZero<u64>) -> Option<O::Vector>,
+{
+    fn open(&mut self, first: u32, residual: Option<O::Vector>) {
+        let (block_lut, binary_lut) =
+            if let Some(residual) = residual.as_ref().map(|x| x.as_borrowed()) {
+                &O::Vector::preprocess(residual)
+            } else if let Some(lut) = self.default_lut.as_ref() {
+                lut
+            } else {
+                unreachable!()
+            };
+        let jump_guard = self.index.read(first);
+        let jump_bytes = jump_guard.get(1).expect("data corruption");
+        let jump_tuple = JumpTuple::deserialize_ref(jump_bytes);
+        let epsilon = self.epsilon;
+        let code_alignment = self.code_alignment;
+        let candidates = &mut self.candidates;
+        let mut callback = id_2(|(rough, err), mean, payload, prefetch: &[u32]| {
+            let lowerbound = search::lowerbound((rough, err), epsilon);
+            candidates.push((
+                Reverse(lowerbound),
+                AlwaysEqual((payload, mean, prefetch.to_vec())),
+            ));
+        });
+        tape::read_frozen_tape(
+            self.index.clone(),
+            jump_tuple.frozen_first(),
+            || {
+                RAccess::new(
+                    (&block_lut.1, block_lut.0),
+                    O::BlockAccessor::from_alignment(code_alignment),
+                )
+            },
+            &mut callback,
+            |_| (),
+        );
+        tape::read_appendable_tape(
+            self.index.clone(),
+            jump_tuple.appendable_first(),
+            |code| O::binary_process(binary_lut, code),
+            &mut callback,
+            |_| (),
+        );
+        self.opened += 1;
+    }
+    fn rerank(&mut self, payload: NonZero<u64>, head: u16, prefetch: &[u32]) -> Option<Distance> {
+        self.reranked += 1;
+        let unpack = O::Vector::unpack(self.vector.as_borrowed());
+        match (self.method, self.precision) {
+            (RerankMethod::Index, RerankPrecision::F32) => vectors::read_for_h0_tuple::<R, O, _>(
+                head,
+                prefetch.iter().map(|&id| self.index.read(id)),
+                payload,
+                LTryAccess::new(unpack, O::DistanceAccessor::default()),
+            ),
+            (RerankMethod::Index, RerankPrecision::F64) => vectors::read_for_h0_tuple::<R, O, _>(
+                head,
+                prefetch.iter().map(|&id| self.index.read(id)),
+                payload,
+                LTryAccess::new(unpack, O::PreciseDistanceAccessor::default()),
+            ),
+            (RerankMethod::Heap, precision) => {
+                let vector = (self.fetch)(payload)?;
+                let vector = O::Vector::unpack(vector.as_borrowed());
+                Some(match precision {
+                    RerankPrecision::F32 => {
+                        let mut accessor = O::DistanceAccessor::default();
+                        accessor.push(unpack.0, vector.0);
+                        accessor.finish(unpack.1, vector.1)
+                    }
+                    RerankPrecision::F64 => {
+                        let mut accessor = O::PreciseDistanceAccessor::default();
+                        accessor.push(unpack.0, vector.0);
+                        accessor.finish(unpack.1, vector.1)
+                    }
+                })
+            }
+        }
+    }
+}
+
+impl<R: RelationRead, O: Operator, F> Iterator for Incremental<R, O, F>
+where
+    F: FnMut(NonZero<u64>) -> Option<O::Vector>,
+{
+    type Item = (Distance, NonZero<u64>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let list = self.lists.peek().map(|(Reverse(d), _)| *d);
+            let candidate = self.candidates.peek().map(|(Reverse(d), _)| *d);
+            let best = self.cache.peek().map(|(Reverse(d), _)| *d);
+            match (list, candidate, best) {
+                (None, None, _) => break,
+                (l, c, Some(b)) if l.is_none_or(|l| b <= l) && c.is_none_or(|c| b <= c) => {
+                    break;
+                }
+                (Some(_), _, _)
+                    if self.opened != 0
+                        && self
+                            .deadline
+                            .is_some_and(|deadline| Instant::now() >= deadline) =>
+                {
+                    self.lists.clear();
+                }
+                (Some(l), c, _) if c.is_none_or(|c| l <= c) => {
+                    let (_, AlwaysEqual((first, residual))) = self.lists.pop().unwrap();
+                    let i = self.popped;
+                    self.popped += 1;
+                    // participants of a parallel scan pop the same lists in the same order
+                    // and take turns to claim them
+                    if let Some(index) = self.claimed {
+                        if i != index {
+                            continue;
+                        }
+                        self.claimed = claim(self.parallel);
+                    }
+                    self.open(first, residual);
+                }
+                _ => {
+                    let (_, AlwaysEqual((payload, head, prefetch))) =
+                        self.candidates.pop().unwrap();
+                    if let Some(distance) = self.rerank(payload, head, &prefetch) {
+                        self.cache.push((Reverse(distance), AlwaysEqual(payload)));
+                    }
+                }
+            }
+        }
+        let (Reverse(distance), AlwaysEqual(payload)) = self.cache.pop()?;
+        Some((distance, payload))
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn incremental_search<
+    'b,
+    R: RelationRead,
+    O: Operator,
+    P: Prefetcher<R = R, Item = Item<'b>>,
+    F: FnMut(NonZero<u64>) -> Option<O::Vector>,
+>(
+    index: R,
+    vector: O::Vector,
+    probes: Vec<u32>,
+    epsilon: f32,
+    bump: &'b impl Bump,
+    mut prefetch: impl FnMut(Vec<Item<'b>>) -> P,
+    method: RerankMethod,
+    precision: RerankPrecision,
+    fetch: F,
+    deadline: Option<Instant>,
+    parallel: Option<&'static AtomicU32>,
+) -> Incremental<R, O, F> {
+    let meta_guard = index.read(0);
+    let meta_bytes = meta_guard.get(1).expect("data corruption");
+    let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
+    let dims = meta_tuple.dims();
+    let is_residual = meta_tuple.is_residual();
+    let code_alignment = meta_tuple.code_alignment();
+    let height_of_root = meta_tuple.height_of_root();
+    assert_eq!(dims, vector.as_borrowed().dims(), "unmatched dimensions");
+    if height_of_root as usize != 1 + probes.len() {
+        panic!(
+            "usage: need {} probes, but {} probes provided",
+            height_of_root - 1,
+            probes.len()
+        );
+    }
+    let root_prefetch = meta_tuple.root_prefetch().to_vec();
+    let root_head = meta_tuple.root_head();
+    let root_first = meta_tuple.root_first();
+    drop(meta_guard);
+
+    let default_lut = if !is_residual {
+        Some(O::Vector::preprocess(vector.as_borrowed()))
+    } else {
+        None
+    };
+
+    type State<O> = Vec<(u32, Option<<O as Operator>::Vector>, Distance)>;
+    let mut state: State<O> = vec![{
+        if is_residual {
+            let list = root_prefetch.into_iter().map(|id| index.read(id));
+            let residual = vectors::read_for_h1_tuple::<R, O, _>(
+                root_head,
+                list,
+                LAccess::new(
+                    O::Vector::unpack(vector.as_borrowed()),
+                    O::ResidualAccessor::default(),
+                ),
+            );
+            (root_first, Some(residual), Distance::NEG_INFINITY)
+        } else {
+            (root_first, None, Distance::NEG_INFINITY)
+        }
+    }];
+    let mut step = |state: State<O>| {
+        let mut results = Vec::new();
+        for (first, residual, _) in state {
+            let block_lut = if let Some(residual) = residual {
+                &O::Vector::block_preprocess(residual.as_borrowed())
+            } else if let Some((block_lut, _)) = default_lut.as_ref() {
+                block_lut
+            } else {
+                unreachable!()
+            };
+            tape::read_h1_tape(
+                index.clone(),
+                first,
+                || {
+                    RAccess::new(
+                        (&block_lut.1, block_lut.0),
+                        O::BlockAccessor::from_alignment(code_alignment),
+                    )
+                },
+                |(rough, err), head, first, prefetch| {
+                    let lowerbound = Distance::from_f32(rough - err * epsilon);
+                    results.push((
+                        Reverse(lowerbound),
+                        AlwaysEqual(bump.alloc((first, head, bump.alloc_slice(prefetch)))),
+                    ));
+                },
+                |_| (),
+            );
+        }
+        let mut heap = (prefetch)(results);
+        let mut cache = BinaryHeap::<(Reverse<Distance>, _, _)>::new();
+        let vector = vector.as_borrowed();
+        std::iter::from_fn(move || {
+            while let Some(((Reverse(_), AlwaysEqual(&mut (first, head, ..))), list)) =
+                heap.pop_if(|(d, ..)| Some(*d) > cache.peek().map(|(d, ..)| *d))
+            {
+                if is_residual {
+                    let (distance, residual) = vectors::read_for_h1_tuple::<R, O, _>(
+                        head,
+                        list.into_iter(),
+                        LAccess::new(
+                            O::Vector::unpack(vector),
+                            (
+                                O::DistanceAccessor::default(),
+                                O::ResidualAccessor::default(),
+                            ),
+                        ),
+                    );
+                    cache.push((
+                        Reverse(distance),
+                        AlwaysEqual(first),
+                        AlwaysEqual(Some(residual)),
+                    ));
+                } else {
+                    let distance = vectors::read_for_h1_tuple::<R, O, _>(
+                        head,
+                        list.into_iter(),
+                        LAccess::new(O::Vector::unpack(vector), O::DistanceAccessor::default()),
+                    );
+                    cache.push((Reverse(distance), AlwaysEqual(first), AlwaysEqual(None)));
+                }
+            }
+            let (Reverse(distance), AlwaysEqual(first), AlwaysEqual(mean)) = cache.pop()?;
+            Some((first, mean, distance))
+        })
+    };
+    for i in (1..height_of_root).rev() {
+        state = step(state).take(probes[i as usize - 1] as _).collect();
+    }
+
+    let norm = vector.as_borrowed().norm();
+    let mut lists = BinaryHeap::new();
+    for (first, residual, distance) in state {
+        let lowerbound = if distance == Distance::NEG_INFINITY {
+            Distance::NEG_INFINITY
+        } else {
+            let jump_guard = index.read(first);
+            let jump_bytes = jump_guard.get(1).expect("data corruption");
+            let jump_tuple = JumpTuple::deserialize_ref(jump_bytes);
+            let radius = jump_tuple.radius();
+            if radius.is_finite() {
+                O::ball_lowerbound(norm, distance, radius)
+            } else {
+                Distance::NEG_INFINITY
+            }
+        };
+        lists.push((Reverse(lowerbound), AlwaysEqual((first, residual))));
+    }
+
+    Incremental {
+        index,
+        vector,
+        epsilon,
+        code_alignment,
+        default_lut,
+        method,
+        precision,
+        fetch,
+        deadline,
+        parallel,
+        claimed: claim(parallel),
+        popped: 0,
+        lists,
+        candidates: BinaryHeap::new(),
+        cache: BinaryHeap::new(),
+        opened: 0,
+        reranked: 0,
+    }
+}
+
+fn claim(parallel: Option<&AtomicU32>) -> Option<usize> {
+    parallel.map(|next| next.fetch_add(1, Ordering::Relaxed) as usize)
+}
@@ -33,6 +33,7 @@ pub fn insert<
     let dims = meta_tuple.dims();
     let is_residual = meta_tuple.is_residual();
     let rerank_in_heap = meta_tuple.rerank_in_heap();
+    let code_alignment = meta_tuple.code_alignment();
     let height_of_root = meta_tuple.height_of_root();
     assert_eq!(dims, vector.as_borrowed().dims(), "unmatched dimensions");
     let root_prefetch = meta_tuple.root_prefetch().to_vec();
@@ -53,10 +54,10 @@ pub fn insert<
         (Vec::new(), 0)
     };
 
-    type State<O> = (u32, Option<<O as Operator>::Vector>);
+    type State<O> = (u32, Option<<O as Operator>::Vector>, (u16, Vec<u32>));
     let mut state: State<O> = {
         if is_residual {
-            let list = root_prefetch.into_iter().map(|id| index.read(id));
+            let list = root_prefetch.iter().map(|&id| index.read(id));
             let residual = vectors::read_for_h1_tuple::<R, O, _>(
                 root_head,
                 list,
@@ -65,15 +66,15 @@ pub fn insert<
                     O::ResidualAccessor::default(),
                 ),
             );
-            (root_first, Some(residual))
+            (root_first, Some(residual), (root_head, root_prefetch))
         } else {
-            (root_first, None)
+            (root_first, None, (root_head, root_prefetch))
         }
     };
     let mut step = |state: State<O>| {
         let mut results = LinkedVec::<Item<'b>>::new();
         {
-            let (first, residual) = state;
+            let (first, residual, _) = state;
             let block_lut = if let Some(residual) = residual {
                 &O::Vector::block_preprocess(residual.as_borrowed())
             } else if let Some(block_lut) = default_block_lut.as_ref() {
@@ -84,7 +85,12 @@ pub fn insert<
             tape::read_h1_tape(
                 index.clone(),
                 first,
-                || RAccess::new((&block_lut.1, block_lut.0), O::BlockAccessor::default()),
+                || {
+                    RAccess::new(
+                        (&block_lut.1, block_lut.0),
+                        O::BlockAccessor::from_alignment(code_alignment),
+                    )
+                },
                 |(rough, err), head, first, prefetch| {
                     let lowerbound = Distance::from_f32(rough - err * 1.9);
                     results.push((
@@ -96,11 +102,12 @@ pub fn insert<
             );
         }
         let mut heap = (prefetch)(results.into_vec());
-        let mut cache = BinaryHeap::<(Reverse<Distance>, _, _)>::new();
+        let mut cache = BinaryHeap::<(Reverse<Distance>, _, _, _)>::new();
         {
-            while let Some(((Reverse(_), AlwaysEqual(&mut (first, head, ..))), list)) =
+            while let Some(((Reverse(_), AlwaysEqual(&mut (first, head, ref prefetch))), list)) =
                 heap.pop_if(|(d, _)| Some(*d) > cache.peek().map(|(d, ..)| *d))
             {
+                let centroid = AlwaysEqual((head, prefetch.to_vec()));
                 if is_residual {
                     let (distance, residual) = vectors::read_for_h1_tuple::<R, O, _>(
                         head,
@@ -117,35 +124,61 @@ pub fn insert<
                         Reverse(distance),
                         AlwaysEqual(first),
                         AlwaysEqual(Some(residual)),
+                        centroid,
                     ));
                 } else {
+                    // only the nearest one is kept, so farther ones are not computed exactly
+                    let bound = cache
+                        .peek()
+                        .map_or(Distance::INFINITY, |(Reverse(distance), ..)| *distance);
                     let distance = vectors::read_for_h1_tuple::<R, O, _>(
                         head,
                         list.into_iter(),
                         LAccess::new(
                             O::Vector::unpack(vector.as_borrowed()),
-                            O::DistanceAccessor::default(),
+                            O::bounded_distance_accessor(bound),
                         ),
                     );
-                    cache.push((Reverse(distance), AlwaysEqual(first), AlwaysEqual(None)));
+                    if let Some(distance) = distance {
+                        cache.push((
+                            Reverse(distance),
+                            AlwaysEqual(first),
+                            AlwaysEqual(None),
+                            centroid,
+                        ));
+                    }
                 }
             }
-            let (_, AlwaysEqual(first), AlwaysEqual(mean)) = cache
+            let (_, AlwaysEqual(first), AlwaysEqual(mean), AlwaysEqual(centroid)) = cache
                 .pop()
                 .expect("invariant is violated: tree is not height-balanced");
-            (first, mean)
+            (first, mean, centroid)
         }
     };
     for _ in (1..height_of_root).rev() {
         state = step(state);
     }
 
-    let (first, residual) = state;
-    let code = if let Some(residual) = residual {
+    let (first, residual, (centroid_head, centroid_prefetch)) = state;
+    let code = if let Some(residual) = residual.as_ref() {
         O::Vector::code(residual.as_borrowed())
     } else {
         O::Vector::code(vector.as_borrowed())
     };
+    let radius = if residual.is_some() {
+        code.dis_u_2.sqrt()
+    } else {
+        let list = centroid_prefetch.iter().map(|&id| index.read(id));
+        let residual = vectors::read_for_h1_tuple::<R, O, _>(
+            centroid_head,
+            list,
+            LAccess::new(
+                O::Vector::unpack(vector.as_borrowed()),
+                O::ResidualAccessor::default(),
+            ),
+        );
+        residual.as_borrowed().norm()
+    };
     let bytes = AppendableTuple::serialize(&AppendableTuple {
         head,
         dis_u_2: code.dis_u_2,
@@ -157,9 +190,25 @@ pub fn insert<
         elements: rabitq::pack_to_u64(&code.signs),
     });
 
+    // the jump tuple is locked until the vector is appended, so that `maintain`
+    // does not rewrite the tape in the meantime
     let jump_guard = index.read(first);
     let jump_bytes = jump_guard.get(1).expect("data corruption");
     let jump_tuple = JumpTuple::deserialize_ref(jump_bytes);
-
-    tape::append(index.clone(), jump_tuple.appendable_first(), &bytes, false);
+    if jump_tuple.radius() >= radius {
+        let appendable_first = jump_tuple.appendable_first();
+        tape::append(index.clone(), appendable_first, &bytes, false);
+        drop(jump_guard);
+    } else {
+        drop(jump_guard);
+        let mut jump_guard = index.write(first, false);
+        let jump_bytes = jump_guard.get_mut(1).expect("data corruption");
+        let mut jump_tuple = JumpTuple::deserialize_mut(jump_bytes);
+        if let Some(x) = jump_tuple.radius() {
+            *x = x.max(radius);
+        }
+        let appendable_first = *jump_tuple.appendable_first();
+        tape::append(index.clone(), appendable_first, &bytes, false);
+        drop(jump_guard);
+    }
 }
@@ -1,19 +1,30 @@
 #![allow(clippy::type_complexity)]
 
+mod aliases;
+mod alignment;
+mod assignments;
 mod build;
 mod bulkdelete;
 mod cache;
+mod centroid;
 mod closure_lifetime_binder;
+mod compact;
 mod cost;
+mod dump;
 mod fast_heap;
 mod freepages;
+mod incremental;
 mod insert;
 mod linked_vec;
+mod lists;
 mod maintain;
 mod prefetcher;
 mod prewarm;
+mod reconstruct;
 mod rerank;
 mod search;
+mod size;
+mod structures;
 mod tape;
 mod tuples;
 mod vectors;
@@ -21,18 +32,29 @@ mod vectors;
 pub mod operator;
 pub mod types;
 
+pub use aliases::{Aliases, alias, chunks, resolve};
+pub use alignment::misaligned;
 use always_equal::AlwaysEqual;
+pub use assignments::assignments;
 pub use build::build;
 pub use bulkdelete::bulkdelete;
 pub use cache::cache;
+pub use centroid::{nearest_centroid, probed_lists};
+pub use compact::compact;
 pub use cost::cost;
-pub use fast_heap::FastHeap;
+pub use dump::{Dumped, dump, restore};
+pub use fast_heap::{FastHeap, HeapStrategy};
+pub use incremental::{Incremental, incremental_search};
 pub use insert::insert;
+pub use lists::{Code, Codes, lists};
 pub use maintain::maintain;
 pub use prefetcher::{PlainPrefetcher, Prefetcher, SimplePrefetcher, StreamPrefetcher};
 pub use prewarm::prewarm;
-pub use rerank::{how, rerank_heap, rerank_index};
-pub use search::{default_search, maxsim_search};
+pub use reconstruct::{distances, multi_distances, reconstruct, row_distance};
+pub use rerank::{Reranker, how, rerank_heap, rerank_index};
+pub use search::{Probed, default_search, maxsim_search};
+pub use size::estimate_size;
+pub use structures::structures;
 
 use std::collections::BinaryHeap;
 use std::ops::{Deref, DerefMut};
@@ -62,6 +84,8 @@ pub trait Page: Sized {
     fn get_mut(&mut self, i: u16) -> Option<&mut [u8]>;
     #[must_use]
     fn alloc(&mut self, data: &[u8]) -> Option<u16>;
+    #[must_use]
+    fn alloc_aligned(&mut self, data: &[u8], offset: u16, align: u16) -> Option<u16>;
     fn free(&mut self, i: u16);
     #[must_use]
     fn freespace(&self) -> u16;
@@ -142,6 +166,12 @@ pub enum RerankMethod {
     Heap,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RerankPrecision {
+    F32,
+    F64,
+}
+
 pub(crate) struct Branch<T> {
     pub head: u16,
     pub dis_u_2: f32,
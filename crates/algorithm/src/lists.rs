@@ -0,0 +1,176 @@
+use crate::closure_lifetime_binder::{id_0, id_1};
+use crate::operator::{FunctionalAccessor, Operator, Vector};
+use crate::tuples::*;
+use crate::{Page, RelationRead, tape, vectors};
+use simd::Floating;
+use simd::fast_scan::unpack;
+use std::collections::VecDeque;
+use std::num::NonZero;
+
+// A code is the RaBitQ code of a vector in the projected space, relative to the
+// centroid of its list if the index is residual, so `rabitq::decode` decodes it.
+pub struct Code {
+    pub payload: NonZero<u64>,
+    pub dis_u_2: f32,
+    pub factor_ip: f32,
+    pub signs: Vec<bool>,
+}
+
+#[derive(Clone, Copy)]
+enum Position {
+    Frozen(u32),
+    Appendable(u32),
+}
+
+// Codes of a list are read a page at a time, the frozen tape before the appendable
+// one. A page is decoded under its lock, so a code appended by a concurrent insert
+// is seen whole or not at all, as a scan sees it.
+pub struct Codes<R> {
+    index: R,
+    dims: u32,
+    position: Position,
+    appendable_first: u32,
+    elements: Vec<[u8; 16]>,
+    buffer: VecDeque<Code>,
+}
+
+impl<R: RelationRead> Codes<R> {
+    fn frozen(&mut self, current: u32) -> u32 {
+        let guard = self.index.read(current);
+        for i in 1..=guard.len() {
+            let bytes = guard.get(i).expect("data corruption");
+            match FrozenTuple::deserialize_ref(bytes) {
+                FrozenTupleReader::_0(tuple) => {
+                    self.elements.extend_from_slice(tuple.elements());
+                    let unpacked = unpack(&self.elements);
+                    let metadata = tuple.metadata();
+                    for j in 0..32 {
+                        if let Some(payload) = tuple.payload()[j] {
+                            let f = |&x: &u8| [x & 1 != 0, x & 2 != 0, x & 4 != 0, x & 8 != 0];
+                            let mut signs = unpacked[j].iter().flat_map(f).collect::<Vec<_>>();
+                            signs.truncate(self.dims as _);
+                            self.buffer.push_back(Code {
+                                payload,
+                                dis_u_2: metadata.0[j],
+                                factor_ip: metadata.2[j],
+                                signs,
+                            });
+                        }
+                    }
+                    self.elements.clear();
+                }
+                FrozenTupleReader::_1(tuple) => {
+                    self.elements.extend_from_slice(tuple.elements());
+                }
+            }
+        }
+        guard.get_opaque().next
+    }
+    fn appendable(&mut self, current: u32) -> u32 {
+        let guard = self.index.read(current);
+        for i in 1..=guard.len() {
+            let bytes = guard.get(i).expect("data corruption");
+            let tuple = AppendableTuple::deserialize_ref(bytes);
+            if let Some(payload) = tuple.payload() {
+                let code = tuple.code();
+                let mut signs = code
+                    .4
+                    .iter()
+                    .flat_map(|x| std::array::from_fn::<_, 64, _>(|i| *x & (1 << i) != 0))
+                    .collect::<Vec<_>>();
+                signs.truncate(self.dims as _);
+                self.buffer.push_back(Code {
+                    payload,
+                    dis_u_2: code.0,
+                    factor_ip: code.2,
+                    signs,
+                });
+            }
+        }
+        guard.get_opaque().next
+    }
+}
+
+impl<R: RelationRead> Iterator for Codes<R> {
+    type Item = Code;
+
+    fn next(&mut self) -> Option<Code> {
+        loop {
+            if let Some(code) = self.buffer.pop_front() {
+                return Some(code);
+            }
+            self.position = match self.position {
+                Position::Frozen(u32::MAX) => Position::Appendable(self.appendable_first),
+                Position::Appendable(u32::MAX) => return None,
+                Position::Frozen(current) => Position::Frozen(self.frozen(current)),
+                Position::Appendable(current) => Position::Appendable(self.appendable(current)),
+            };
+        }
+    }
+}
+
+// Lists are numbered as in `assignments`, and centroids are in the projected space.
+// Upper levels are read when it's called, and a list is read only when it's reached.
+pub fn lists<R: RelationRead, O: Operator>(
+    index: R,
+) -> impl Iterator<Item = (u32, Vec<f32>, Codes<R>)>
+where
+    <O::Vector as Vector>::Element: Floating,
+{
+    let meta_guard = index.read(0);
+    let meta_bytes = meta_guard.get(1).expect("data corruption");
+    let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
+    let dims = meta_tuple.dims();
+    let height_of_root = meta_tuple.height_of_root();
+    let root_prefetch = meta_tuple.root_prefetch().to_vec();
+    let root_head = meta_tuple.root_head();
+    let root_first = meta_tuple.root_first();
+    drop(meta_guard);
+
+    type State = Vec<(u32, u16, Vec<u32>)>;
+    let mut state: State = vec![(root_first, root_head, root_prefetch)];
+    let step = |state: State| {
+        let mut results = Vec::new();
+        for (first, ..) in state {
+            tape::read_h1_tape(
+                index.clone(),
+                first,
+                || FunctionalAccessor::new((), id_0(|_, _| ()), id_1(|_, _| [(); 32])),
+                |(), head, first, prefetch| results.push((first, head, prefetch.to_vec())),
+                |_| (),
+            );
+        }
+        results
+    };
+    for _ in (1..height_of_root).rev() {
+        state = step(state);
+    }
+
+    state
+        .into_iter()
+        .enumerate()
+        .map(move |(list, (first, head, prefetch))| {
+            let centroid = vectors::read_for_h1_tuple::<R, O, _>(
+                head,
+                prefetch.iter().map(|&id| index.read(id)),
+                FunctionalAccessor::new(
+                    Vec::<<O::Vector as Vector>::Element>::new(),
+                    Vec::<<O::Vector as Vector>::Element>::extend_from_slice,
+                    |elements: Vec<_>, _| elements,
+                ),
+            );
+            let centroid = <<O::Vector as Vector>::Element as Floating>::vector_to_f32(&centroid);
+            let jump_guard = index.read(first);
+            let jump_bytes = jump_guard.get(1).expect("data corruption");
+            let jump_tuple = JumpTuple::deserialize_ref(jump_bytes);
+            let codes = Codes {
+                index: index.clone(),
+                dims,
+                position: Position::Frozen(jump_tuple.frozen_first()),
+                appendable_first: jump_tuple.appendable_first(),
+                elements: Vec::new(),
+                buffer: VecDeque::new(),
+            };
+            (list as u32, centroid, codes)
+        })
+}
@@ -11,6 +11,8 @@ pub fn maintain<O: Operator, R: RelationRead + RelationWrite>(index: R, check: i
     let meta_bytes = meta_guard.get(1).expect("data corruption");
     let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
     let dims = meta_tuple.dims();
+    let code_alignment = meta_tuple.code_alignment();
+    let sort_lists = meta_tuple.sort_lists();
     let height_of_root = meta_tuple.height_of_root();
     let root_first = meta_tuple.root_first();
     let freepage_first = meta_tuple.freepage_first();
@@ -60,13 +62,19 @@ pub fn maintain<O: Operator, R: RelationRead + RelationWrite>(index: R, check: i
             }),
         );
 
-        let mut tape = FrozenTapeWriter::create(&hooked_index, O::Vector::count(dims as _), false);
+        let mut tape = FrozenTapeWriter::create(
+            &hooked_index,
+            O::Vector::count(dims as _),
+            code_alignment,
+            false,
+        );
 
         let mut trace = Vec::new();
 
         let mut tuples = 0_u64;
+        let mut sorted = Vec::new();
         let mut callback = id_2(|code: (_, _, _, _, _), head, payload, prefetch: &[_]| {
-            tape.push(Branch {
+            let branch = Branch {
                 head,
                 dis_u_2: code.0,
                 factor_ppc: code.1,
@@ -75,7 +83,12 @@ pub fn maintain<O: Operator, R: RelationRead + RelationWrite>(index: R, check: i
                 signs: code.4,
                 prefetch: prefetch.to_vec(),
                 extra: payload,
-            });
+            };
+            if sort_lists {
+                sorted.push(branch);
+            } else {
+                tape.push(branch);
+            }
             tuples += 1;
         });
         let mut step = |id| {
@@ -118,11 +131,19 @@ pub fn maintain<O: Operator, R: RelationRead + RelationWrite>(index: R, check: i
             &mut step,
         );
 
+        // residuals are coded, so `dis_u_2` is the squared distance to the centroid
+        sorted.sort_by(|a, b| a.dis_u_2.total_cmp(&b.dis_u_2));
+        for branch in sorted {
+            check();
+            tape.push(branch);
+        }
+
         let (frozen_tape, branches) = tape.into_inner();
 
         let mut appendable_tape = TapeWriter::create(&hooked_index, false);
 
         for branch in branches {
+            check();
             appendable_tape.push(AppendableTuple {
                 head: branch.head,
                 dis_u_2: branch.dis_u_2,
@@ -152,17 +173,19 @@ where
     tape: TapeWriter<'a, R, FrozenTuple>,
     branches: Vec<Branch<NonZero<u64>>>,
     prefetch: usize,
+    align: u16,
 }
 
 impl<'a, R> FrozenTapeWriter<'a, R>
 where
     R: RelationWrite + 'a,
 {
-    fn create(index: &'a R, prefetch: usize, tracking_freespace: bool) -> Self {
+    fn create(index: &'a R, prefetch: usize, align: u16, tracking_freespace: bool) -> Self {
         Self {
             tape: TapeWriter::create(index, tracking_freespace),
             branches: Vec::new(),
             prefetch,
+            align,
         }
     }
     fn push(&mut self, branch: Branch<NonZero<u64>>) {
@@ -171,24 +194,32 @@ where
             let mut remain = padding_pack(chunk.iter().map(|x| rabitq::pack_to_u4(&x.signs)));
             loop {
                 let freespace = self.tape.freespace();
-                if FrozenTuple::estimate_size_0(self.prefetch, remain.len()) <= freespace as usize {
-                    self.tape.tape_put(FrozenTuple::_0 {
-                        head: chunk.each_ref().map(|x| x.head),
-                        dis_u_2: chunk.each_ref().map(|x| x.dis_u_2),
-                        factor_ppc: chunk.each_ref().map(|x| x.factor_ppc),
-                        factor_ip: chunk.each_ref().map(|x| x.factor_ip),
-                        factor_err: chunk.each_ref().map(|x| x.factor_err),
-                        payload: chunk.each_ref().map(|x| Some(x.extra)),
-                        prefetch: fix(chunk.each_ref().map(|x| x.prefetch.as_slice())),
-                        elements: remain,
-                    });
+                if FrozenTuple::estimate_size_0(self.prefetch, remain.len(), self.align)
+                    <= freespace as usize
+                {
+                    self.tape.tape_put_aligned(
+                        FrozenTuple::_0 {
+                            head: chunk.each_ref().map(|x| x.head),
+                            dis_u_2: chunk.each_ref().map(|x| x.dis_u_2),
+                            factor_ppc: chunk.each_ref().map(|x| x.factor_ppc),
+                            factor_ip: chunk.each_ref().map(|x| x.factor_ip),
+                            factor_err: chunk.each_ref().map(|x| x.factor_err),
+                            payload: chunk.each_ref().map(|x| Some(x.extra)),
+                            prefetch: fix(chunk.each_ref().map(|x| x.prefetch.as_slice())),
+                            elements: remain,
+                        },
+                        self.align,
+                    );
                     break;
                 }
-                if let Some(w) = FrozenTuple::fit_1(self.prefetch, freespace) {
+                if let Some(w) = FrozenTuple::fit_1(self.prefetch, freespace, self.align) {
                     let (left, right) = remain.split_at(std::cmp::min(w, remain.len()));
-                    self.tape.tape_put(FrozenTuple::_1 {
-                        elements: left.to_vec(),
-                    });
+                    self.tape.tape_put_aligned(
+                        FrozenTuple::_1 {
+                            elements: left.to_vec(),
+                        },
+                        self.align,
+                    );
                     remain = right.to_vec();
                 } else {
                     self.tape.tape_move();
@@ -124,6 +124,23 @@ where
     }
 }
 
+impl<E, M, T, P, F, R> TryAccessor1<E, M> for FunctionalAccessor<T, P, F>
+where
+    P: for<'a> FnMut(&'a mut T, &'a [E]),
+    F: FnOnce(T, M) -> R,
+{
+    type Output = R;
+
+    fn push(&mut self, input: &[E]) -> Option<()> {
+        (self.p)(&mut self.data, input);
+        Some(())
+    }
+
+    fn finish(self, input: M) -> Option<Self::Output> {
+        Some((self.f)(self.data, input))
+    }
+}
+
 pub struct LAccess<'a, E, M, A> {
     elements: &'a [E],
     metadata: M,
@@ -240,6 +257,24 @@ where
     }
 }
 
+impl<E, M: Copy, A> TryAccessor1<E, M> for Vec<A>
+where
+    A: TryAccessor1<E, M>,
+{
+    type Output = Vec<A::Output>;
+
+    fn push(&mut self, input: &[E]) -> Option<()> {
+        for accessor in self.iter_mut() {
+            accessor.push(input)?;
+        }
+        Some(())
+    }
+
+    fn finish(self, input: M) -> Option<Self::Output> {
+        self.into_iter().map(|x| x.finish(input)).collect()
+    }
+}
+
 pub struct LTryAccess<'a, E, M, A> {
     elements: &'a [E],
     metadata: M,
@@ -276,12 +311,30 @@ impl<E0, E1, M0, M1, A: Accessor2<E0, E1, M0, M1>> TryAccessor1<E1, M1>
     }
 }
 
+pub trait FromAlignment {
+    fn from_alignment(code_alignment: u16) -> Self;
+}
+
+// Codes are aligned on pages if `code_alignment` is not less than the width of loads,
+// but a page in memory may be less aligned than that, so the pointer is checked too.
 #[derive(Debug)]
-pub struct BlockAccessor<D>([u16; 32], PhantomData<fn(D) -> D>);
+pub struct BlockAccessor<D>([u16; 32], bool, PhantomData<fn(D) -> D>);
 
-impl<D> Default for BlockAccessor<D> {
-    fn default() -> Self {
-        Self([0u16; 32], PhantomData)
+impl<D> FromAlignment for BlockAccessor<D> {
+    fn from_alignment(code_alignment: u16) -> Self {
+        let aligned = code_alignment as usize >= simd::fast_scan::ALIGNMENT;
+        Self([0u16; 32], aligned, PhantomData)
+    }
+}
+
+impl<D> BlockAccessor<D> {
+    #[inline(always)]
+    fn scan(&self, input: &[[u8; 16]], target: &[[u8; 16]]) -> [u16; 32] {
+        if self.1 && input.as_ptr() as usize % simd::fast_scan::ALIGNMENT == 0 {
+            simd::fast_scan::scan_aligned(input, target)
+        } else {
+            simd::fast_scan::scan(input, target)
+        }
     }
 }
 
@@ -296,7 +349,7 @@ impl
     type Output = [(f32, f32); 32];
 
     fn push(&mut self, input: &[[u8; 16]], target: &[[u8; 16]]) {
-        let t = simd::fast_scan::scan(input, target);
+        let t = self.scan(input, target);
         for i in 0..32 {
             self.0[i] += t[i];
         }
@@ -334,7 +387,7 @@ impl
     type Output = [(f32, f32); 32];
 
     fn push(&mut self, input: &[[u8; 16]], target: &[[u8; 16]]) {
-        let t = simd::fast_scan::scan(input, target);
+        let t = self.scan(input, target);
         for i in 0..32 {
             self.0[i] += t[i];
         }
@@ -411,6 +464,146 @@ impl Accessor2<f16, f16, (), ()> for DistanceAccessor<VectOwned<f16>, Dot> {
     }
 }
 
+// Like `DistanceAccessor`, but accumulates in `f64` without SIMD.
+#[derive(Debug)]
+pub struct PreciseDistanceAccessor<V, D>(f64, PhantomData<fn(V) -> V>, PhantomData<fn(D) -> D>);
+
+impl<V, D> Default for PreciseDistanceAccessor<V, D> {
+    fn default() -> Self {
+        Self(0.0, PhantomData, PhantomData)
+    }
+}
+
+impl Accessor2<f32, f32, (), ()> for PreciseDistanceAccessor<VectOwned<f32>, L2> {
+    type Output = Distance;
+
+    fn push(&mut self, target: &[f32], input: &[f32]) {
+        for (&x, &y) in std::iter::zip(target, input) {
+            let d = x as f64 - y as f64;
+            self.0 += d * d;
+        }
+    }
+
+    fn finish(self, (): (), (): ()) -> Self::Output {
+        Distance::from_f32(self.0 as f32)
+    }
+}
+
+impl Accessor2<f32, f32, (), ()> for PreciseDistanceAccessor<VectOwned<f32>, Dot> {
+    type Output = Distance;
+
+    fn push(&mut self, target: &[f32], input: &[f32]) {
+        for (&x, &y) in std::iter::zip(target, input) {
+            self.0 += x as f64 * y as f64;
+        }
+    }
+
+    fn finish(self, (): (), (): ()) -> Self::Output {
+        Distance::from_f32(-self.0 as f32)
+    }
+}
+
+impl Accessor2<f16, f16, (), ()> for PreciseDistanceAccessor<VectOwned<f16>, L2> {
+    type Output = Distance;
+
+    fn push(&mut self, target: &[f16], input: &[f16]) {
+        for (&x, &y) in std::iter::zip(target, input) {
+            let d = x.to_f64() - y.to_f64();
+            self.0 += d * d;
+        }
+    }
+
+    fn finish(self, (): (), (): ()) -> Self::Output {
+        Distance::from_f32(self.0 as f32)
+    }
+}
+
+impl Accessor2<f16, f16, (), ()> for PreciseDistanceAccessor<VectOwned<f16>, Dot> {
+    type Output = Distance;
+
+    fn push(&mut self, target: &[f16], input: &[f16]) {
+        for (&x, &y) in std::iter::zip(target, input) {
+            self.0 += x.to_f64() * y.to_f64();
+        }
+    }
+
+    fn finish(self, (): (), (): ()) -> Self::Output {
+        Distance::from_f32(-self.0 as f32)
+    }
+}
+
+// For L2, accumulation stops once the distance exceeds the bound, and `None` is returned.
+// For dot product, the partial sum is not monotonic, so the bound is ignored.
+#[derive(Debug)]
+pub struct BoundedDistanceAccessor<V, D>(
+    Option<f32>,
+    f32,
+    PhantomData<fn(V) -> V>,
+    PhantomData<fn(D) -> D>,
+);
+
+impl<V, D> BoundedDistanceAccessor<V, D> {
+    pub fn new(bound: Distance) -> Self {
+        Self(Some(0.0), bound.to_f32(), PhantomData, PhantomData)
+    }
+}
+
+impl Accessor2<f32, f32, (), ()> for BoundedDistanceAccessor<VectOwned<f32>, L2> {
+    type Output = Option<Distance>;
+
+    fn push(&mut self, target: &[f32], input: &[f32]) {
+        if let Some(d2) = self.0 {
+            self.0 = f32::reduce_sum_of_d2_bounded(target, input, self.1 - d2).map(|x| d2 + x);
+        }
+    }
+
+    fn finish(self, (): (), (): ()) -> Self::Output {
+        self.0.map(Distance::from_f32)
+    }
+}
+
+impl Accessor2<f32, f32, (), ()> for BoundedDistanceAccessor<VectOwned<f32>, Dot> {
+    type Output = Option<Distance>;
+
+    fn push(&mut self, target: &[f32], input: &[f32]) {
+        if let Some(xy) = self.0.as_mut() {
+            *xy += f32::reduce_sum_of_xy(target, input);
+        }
+    }
+
+    fn finish(self, (): (), (): ()) -> Self::Output {
+        self.0.map(|xy| Distance::from_f32(-xy))
+    }
+}
+
+impl Accessor2<f16, f16, (), ()> for BoundedDistanceAccessor<VectOwned<f16>, L2> {
+    type Output = Option<Distance>;
+
+    fn push(&mut self, target: &[f16], input: &[f16]) {
+        if let Some(d2) = self.0 {
+            self.0 = f16::reduce_sum_of_d2_bounded(target, input, self.1 - d2).map(|x| d2 + x);
+        }
+    }
+
+    fn finish(self, (): (), (): ()) -> Self::Output {
+        self.0.map(Distance::from_f32)
+    }
+}
+
+impl Accessor2<f16, f16, (), ()> for BoundedDistanceAccessor<VectOwned<f16>, Dot> {
+    type Output = Option<Distance>;
+
+    fn push(&mut self, target: &[f16], input: &[f16]) {
+        if let Some(xy) = self.0.as_mut() {
+            *xy += f16::reduce_sum_of_xy(target, input);
+        }
+    }
+
+    fn finish(self, (): (), (): ()) -> Self::Output {
+        self.0.map(|xy| Distance::from_f32(-xy))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ResidualAccessor<V: Vector>(Vec<V::Element>);
 
@@ -559,7 +752,7 @@ pub struct Dot;
 pub trait Operator: 'static + Debug + Copy {
     type Vector: Vector;
 
-    type BlockAccessor: Default
+    type BlockAccessor: FromAlignment
         + for<'a> Accessor2<
             [u8; 16],
             [u8; 16],
@@ -577,6 +770,15 @@ pub trait Operator: 'static + Debug + Copy {
             Output = Distance,
         >;
 
+    type PreciseDistanceAccessor: Default
+        + Accessor2<
+            <Self::Vector as Vector>::Element,
+            <Self::Vector as Vector>::Element,
+            <Self::Vector as Vector>::Metadata,
+            <Self::Vector as Vector>::Metadata,
+            Output = Distance,
+        >;
+
     type ResidualAccessor: Default
         + Accessor2<
             <Self::Vector as Vector>::Element,
@@ -586,9 +788,27 @@ pub trait Operator: 'static + Debug + Copy {
             Output = Self::Vector,
         >;
 
+    type BoundedDistanceAccessor: Accessor2<
+            <Self::Vector as Vector>::Element,
+            <Self::Vector as Vector>::Element,
+            <Self::Vector as Vector>::Metadata,
+            <Self::Vector as Vector>::Metadata,
+            Output = Option<Distance>,
+        >;
+
+    fn bounded_distance_accessor(bound: Distance) -> Self::BoundedDistanceAccessor;
+
     const SUPPORTS_RESIDUAL: bool;
 
     fn binary_process(lut: &BinaryLut, code: BinaryCode<'_>) -> (f32, f32);
+
+    // lower bound of distances between the vector and any point in the ball,
+    // given the distance between the vector and the center of the ball
+    fn ball_lowerbound(norm: f32, distance: Distance, radius: f32) -> Distance;
+
+    // lower bound of distances between the vector and any point out of the ball,
+    // given the distance between the vector and the center of the ball
+    fn shell_lowerbound(distance: Distance, radius: f32) -> Distance;
 }
 
 #[derive(Debug)]
@@ -609,13 +829,31 @@ impl Operator for Op<VectOwned<f32>, L2> {
 
     type DistanceAccessor = DistanceAccessor<VectOwned<f32>, L2>;
 
+    type PreciseDistanceAccessor = PreciseDistanceAccessor<VectOwned<f32>, L2>;
+
     type ResidualAccessor = ResidualAccessor<VectOwned<f32>>;
 
+    type BoundedDistanceAccessor = BoundedDistanceAccessor<VectOwned<f32>, L2>;
+
     const SUPPORTS_RESIDUAL: bool = true;
 
     fn binary_process(lut: &BinaryLut, code: BinaryCode<'_>) -> (f32, f32) {
         rabitq::binary::process_l2(lut, code)
     }
+
+    fn bounded_distance_accessor(bound: Distance) -> Self::BoundedDistanceAccessor {
+        BoundedDistanceAccessor::new(bound)
+    }
+
+    fn ball_lowerbound(_: f32, distance: Distance, radius: f32) -> Distance {
+        let gap = (distance.to_f32().sqrt() - radius).max(0.0);
+        Distance::from_f32(gap * gap)
+    }
+
+    fn shell_lowerbound(distance: Distance, radius: f32) -> Distance {
+        let gap = (radius - distance.to_f32().sqrt()).max(0.0);
+        Distance::from_f32(gap * gap)
+    }
 }
 
 impl Operator for Op<VectOwned<f32>, Dot> {
@@ -625,13 +863,29 @@ impl Operator for Op<VectOwned<f32>, Dot> {
 
     type DistanceAccessor = DistanceAccessor<VectOwned<f32>, Dot>;
 
+    type PreciseDistanceAccessor = PreciseDistanceAccessor<VectOwned<f32>, Dot>;
+
     type ResidualAccessor = ResidualAccessor<VectOwned<f32>>;
 
+    type BoundedDistanceAccessor = BoundedDistanceAccessor<VectOwned<f32>, Dot>;
+
     const SUPPORTS_RESIDUAL: bool = false;
 
     fn binary_process(lut: &BinaryLut, code: BinaryCode<'_>) -> (f32, f32) {
         rabitq::binary::process_dot(lut, code)
     }
+
+    fn bounded_distance_accessor(bound: Distance) -> Self::BoundedDistanceAccessor {
+        BoundedDistanceAccessor::new(bound)
+    }
+
+    fn ball_lowerbound(norm: f32, distance: Distance, radius: f32) -> Distance {
+        Distance::from_f32(distance.to_f32() - norm * radius)
+    }
+
+    fn shell_lowerbound(_: Distance, _: f32) -> Distance {
+        Distance::NEG_INFINITY
+    }
 }
 
 impl Operator for Op<VectOwned<f16>, L2> {
@@ -641,13 +895,31 @@ impl Operator for Op<VectOwned<f16>, L2> {
 
     type DistanceAccessor = DistanceAccessor<VectOwned<f16>, L2>;
 
+    type PreciseDistanceAccessor = PreciseDistanceAccessor<VectOwned<f16>, L2>;
+
     type ResidualAccessor = ResidualAccessor<VectOwned<f16>>;
 
+    type BoundedDistanceAccessor = BoundedDistanceAccessor<VectOwned<f16>, L2>;
+
     const SUPPORTS_RESIDUAL: bool = true;
 
     fn binary_process(lut: &BinaryLut, code: BinaryCode<'_>) -> (f32, f32) {
         rabitq::binary::process_l2(lut, code)
     }
+
+    fn bounded_distance_accessor(bound: Distance) -> Self::BoundedDistanceAccessor {
+        BoundedDistanceAccessor::new(bound)
+    }
+
+    fn ball_lowerbound(_: f32, distance: Distance, radius: f32) -> Distance {
+        let gap = (distance.to_f32().sqrt() - radius).max(0.0);
+        Distance::from_f32(gap * gap)
+    }
+
+    fn shell_lowerbound(distance: Distance, radius: f32) -> Distance {
+        let gap = (radius - distance.to_f32().sqrt()).max(0.0);
+        Distance::from_f32(gap * gap)
+    }
 }
 
 impl Operator for Op<VectOwned<f16>, Dot> {
@@ -657,11 +929,27 @@ impl Operator for Op<VectOwned<f16>, Dot> {
 
     type DistanceAccessor = DistanceAccessor<VectOwned<f16>, Dot>;
 
+    type PreciseDistanceAccessor = PreciseDistanceAccessor<VectOwned<f16>, Dot>;
+
     type ResidualAccessor = ResidualAccessor<VectOwned<f16>>;
 
+    type BoundedDistanceAccessor = BoundedDistanceAccessor<VectOwned<f16>, Dot>;
+
     const SUPPORTS_RESIDUAL: bool = false;
 
     fn binary_process(lut: &BinaryLut, code: BinaryCode<'_>) -> (f32, f32) {
         rabitq::binary::process_dot(lut, code)
     }
+
+    fn bounded_distance_accessor(bound: Distance) -> Self::BoundedDistanceAccessor {
+        BoundedDistanceAccessor::new(bound)
+    }
+
+    fn ball_lowerbound(norm: f32, distance: Distance, radius: f32) -> Distance {
+        Distance::from_f32(distance.to_f32() - norm * radius)
+    }
+
+    fn shell_lowerbound(_: Distance, _: f32) -> Distance {
+        Distance::NEG_INFINITY
+    }
 }
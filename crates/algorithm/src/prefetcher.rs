@@ -1,4 +1,7 @@
-use crate::{Fetch, Heap, ReadStream, RelationPrefetch, RelationRead, RelationReadStream};
+use crate::{
+    FastHeap, Fetch, Heap, HeapStrategy, ReadStream, RelationPrefetch, RelationRead,
+    RelationReadStream,
+};
 use std::collections::{BinaryHeap, VecDeque, binary_heap, vec_deque};
 use std::iter::Chain;
 
@@ -30,6 +33,15 @@ impl<R, H: Heap> PlainPrefetcher<R, H> {
     }
 }
 
+impl<R, T: Ord> PlainPrefetcher<R, FastHeap<T>> {
+    pub fn with_strategy(relation: R, vec: Vec<T>, strategy: HeapStrategy) -> Self {
+        Self {
+            relation,
+            heap: FastHeap::from_vec_with_strategy(vec, strategy),
+        }
+    }
+}
+
 impl<R, H: Heap> IntoIterator for PlainPrefetcher<R, H> {
     type Item = H::Item;
 
@@ -0,0 +1,234 @@
+use crate::closure_lifetime_binder::{id_0, id_1, id_2};
+use crate::operator::{FunctionalAccessor, LTryAccess, Operator, Vector};
+use crate::tuples::*;
+use crate::{Page, RelationRead, tape, vectors};
+use distance::Distance;
+use simd::Floating;
+use simd::fast_scan::unpack;
+use std::num::NonZero;
+
+struct Location {
+    dis_u_2: f32,
+    factor_ip: f32,
+    signs: Vec<bool>,
+    head: u16,
+    prefetch: Vec<u32>,
+    centroid_head: u16,
+    centroid_prefetch: Vec<u32>,
+}
+
+fn locate<R: RelationRead>(index: R, payloads: &[NonZero<u64>]) -> Vec<Option<Location>> {
+    let meta_guard = index.read(0);
+    let meta_bytes = meta_guard.get(1).expect("data corruption");
+    let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
+    let height_of_root = meta_tuple.height_of_root();
+    let root_prefetch = meta_tuple.root_prefetch().to_vec();
+    let root_head = meta_tuple.root_head();
+    let root_first = meta_tuple.root_first();
+    drop(meta_guard);
+
+    type State = Vec<(u32, u16, Vec<u32>)>;
+    let mut state: State = vec![(root_first, root_head, root_prefetch)];
+    let step = |state: State| {
+        let mut results = Vec::new();
+        for (first, ..) in state {
+            tape::read_h1_tape(
+                index.clone(),
+                first,
+                || FunctionalAccessor::new((), id_0(|_, _| ()), id_1(|_, _| [(); 32])),
+                |(), head, first, prefetch| results.push((first, head, prefetch.to_vec())),
+                |_| (),
+            );
+        }
+        results
+    };
+    for _ in (1..height_of_root).rev() {
+        state = step(state);
+    }
+
+    let mut found = payloads.iter().map(|_| None).collect::<Vec<_>>();
+    for (first, centroid_head, centroid_prefetch) in state {
+        let jump_guard = index.read(first);
+        let jump_bytes = jump_guard.get(1).expect("data corruption");
+        let jump_tuple = JumpTuple::deserialize_ref(jump_bytes);
+        let frozen_first = jump_tuple.frozen_first();
+        let appendable_first = jump_tuple.appendable_first();
+        drop(jump_guard);
+
+        let mut callback = id_2(|code: (f32, f32, Vec<bool>), head, x, prefetch: &[u32]| {
+            for (i, _) in payloads.iter().enumerate().filter(|(_, p)| **p == x) {
+                found[i] = Some(Location {
+                    dis_u_2: code.0,
+                    factor_ip: code.1,
+                    signs: code.2.clone(),
+                    head,
+                    prefetch: prefetch.to_vec(),
+                    centroid_head,
+                    centroid_prefetch: centroid_prefetch.clone(),
+                });
+            }
+        });
+        tape::read_frozen_tape(
+            index.clone(),
+            frozen_first,
+            || {
+                FunctionalAccessor::new(
+                    Vec::<[u8; 16]>::new(),
+                    Vec::<[u8; 16]>::extend_from_slice,
+                    |elements: Vec<_>, input: (&[f32; 32], &[f32; 32], &[f32; 32], &[f32; 32])| {
+                        let unpacked = unpack(&elements);
+                        std::array::from_fn(|i| {
+                            let f = |&x| [x & 1 != 0, x & 2 != 0, x & 4 != 0, x & 8 != 0];
+                            let signs = unpacked[i].iter().flat_map(f).collect::<Vec<_>>();
+                            (input.0[i], input.2[i], signs)
+                        })
+                    },
+                )
+            },
+            &mut callback,
+            |_| (),
+        );
+        tape::read_appendable_tape(
+            index.clone(),
+            appendable_first,
+            |code| {
+                let signs = code
+                    .4
+                    .iter()
+                    .flat_map(|x| std::array::from_fn::<_, 64, _>(|i| *x & (1 << i) != 0))
+                    .collect::<Vec<_>>();
+                (code.0, code.2, signs)
+            },
+            &mut callback,
+            |_| (),
+        );
+    }
+    found
+}
+
+pub fn reconstruct<R: RelationRead, O: Operator>(
+    index: R,
+    payloads: &[NonZero<u64>],
+) -> Vec<Option<Vec<f32>>>
+where
+    <O::Vector as Vector>::Element: Floating,
+{
+    let meta_guard = index.read(0);
+    let meta_bytes = meta_guard.get(1).expect("data corruption");
+    let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
+    let dims = meta_tuple.dims();
+    let is_residual = meta_tuple.is_residual();
+    drop(meta_guard);
+
+    let locations = locate(index.clone(), payloads);
+    locations
+        .into_iter()
+        .map(|location| {
+            let location = location?;
+            let mut result =
+                rabitq::decode(dims, location.dis_u_2, location.factor_ip, &location.signs);
+            if is_residual {
+                let list = location.centroid_prefetch.iter().map(|&id| index.read(id));
+                let centroid = vectors::read_for_h1_tuple::<R, O, _>(
+                    location.centroid_head,
+                    list,
+                    FunctionalAccessor::new(
+                        Vec::<<O::Vector as Vector>::Element>::new(),
+                        Vec::<<O::Vector as Vector>::Element>::extend_from_slice,
+                        |elements: Vec<_>, _| elements,
+                    ),
+                );
+                let centroid =
+                    <<O::Vector as Vector>::Element as Floating>::vector_to_f32(&centroid);
+                f32::vector_add_inplace(&mut result, &centroid);
+            }
+            Some(result)
+        })
+        .collect()
+}
+
+pub fn distances<R: RelationRead, O: Operator>(
+    index: R,
+    vector: O::Vector,
+    payloads: &[NonZero<u64>],
+) -> Vec<Option<Distance>> {
+    let locations = locate(index.clone(), payloads);
+    locations
+        .into_iter()
+        .zip(payloads.iter().copied())
+        .map(|(location, payload)| {
+            let location = location?;
+            let list = location.prefetch.iter().map(|&id| index.read(id));
+            vectors::read_for_h0_tuple::<R, O, _>(
+                location.head,
+                list,
+                payload,
+                LTryAccess::new(
+                    O::Vector::unpack(vector.as_borrowed()),
+                    O::DistanceAccessor::default(),
+                ),
+            )
+        })
+        .collect()
+}
+
+// Each vector is read once for all queries.
+pub fn multi_distances<R: RelationRead, O: Operator>(
+    index: R,
+    queries: &[O::Vector],
+    payloads: &[NonZero<u64>],
+) -> Vec<Option<Vec<Distance>>> {
+    let locations = locate(index.clone(), payloads);
+    locations
+        .into_iter()
+        .zip(payloads.iter().copied())
+        .map(|(location, payload)| {
+            let location = location?;
+            let list = location.prefetch.iter().map(|&id| index.read(id));
+            vectors::read_for_h0_tuple::<R, O, _>(
+                location.head,
+                list,
+                payload,
+                queries
+                    .iter()
+                    .map(|vector| {
+                        LTryAccess::new(
+                            O::Vector::unpack(vector.as_borrowed()),
+                            O::DistanceAccessor::default(),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect()
+}
+
+// The vector of `lhs` is read, and then it is compared with the vector of `rhs`.
+// A payload that is not indexed is returned as the error.
+pub fn row_distance<R: RelationRead, O: Operator>(
+    index: R,
+    lhs: NonZero<u64>,
+    rhs: NonZero<u64>,
+) -> Result<Distance, NonZero<u64>> {
+    let mut locations = locate(index.clone(), &[lhs, rhs]).into_iter();
+    let l = locations.next().flatten().ok_or(lhs)?;
+    let r = locations.next().flatten().ok_or(rhs)?;
+    let (elements, metadata) = vectors::read_for_h0_tuple::<R, O, _>(
+        l.head,
+        l.prefetch.iter().map(|&id| index.read(id)),
+        lhs,
+        FunctionalAccessor::new(
+            Vec::<<O::Vector as Vector>::Element>::new(),
+            Vec::<<O::Vector as Vector>::Element>::extend_from_slice,
+            |elements: Vec<_>, metadata| (elements, metadata),
+        ),
+    )
+    .ok_or(lhs)?;
+    vectors::read_for_h0_tuple::<R, O, _>(
+        r.head,
+        r.prefetch.iter().map(|&id| index.read(id)),
+        rhs,
+        LTryAccess::new((&elements, metadata), O::DistanceAccessor::default()),
+    )
+    .ok_or(rhs)
+}
@@ -2,7 +2,7 @@ use crate::closure_lifetime_binder::id_4;
 use crate::operator::*;
 use crate::prefetcher::Prefetcher;
 use crate::tuples::{MetaTuple, WithReader};
-use crate::{Page, RelationRead, RerankMethod, vectors};
+use crate::{Page, RelationRead, RerankMethod, RerankPrecision, vectors};
 use always_equal::AlwaysEqual;
 use distance::Distance;
 use std::cmp::Reverse;
@@ -31,6 +31,7 @@ pub struct Reranker<T, F, P> {
     prefetcher: P,
     cache: BinaryHeap<Result>,
     f: F,
+    reranked: u64,
     _phantom: PhantomData<fn(T) -> T>,
 }
 
@@ -46,6 +47,7 @@ where
             .prefetcher
             .pop_if(|((d, _), ..)| Some(*d) > self.cache.peek().map(|(d, ..)| *d))
         {
+            self.reranked += 1;
             if let Some(distance) = (self.f)(payload, list, head) {
                 self.cache.push((Reverse(distance), AlwaysEqual(payload)));
             };
@@ -56,6 +58,10 @@ where
 }
 
 impl<T, F, P> Reranker<T, F, P> {
+    pub fn reranked(&self) -> u64 {
+        self.reranked
+    }
+
     pub fn finish(self) -> (P, impl Iterator<Item = Result>) {
         (self.prefetcher, self.cache.into_iter())
     }
@@ -69,6 +75,7 @@ pub fn rerank_index<
 >(
     vector: O::Vector,
     prefetcher: P,
+    precision: RerankPrecision,
 ) -> Reranker<
     T,
     impl FnMut(NonZero<u64>, Vec<<P::R as RelationRead>::ReadGuard<'_>>, u16) -> Option<Distance>,
@@ -77,16 +84,23 @@ pub fn rerank_index<
     Reranker {
         prefetcher,
         cache: BinaryHeap::new(),
+        reranked: 0,
         f: id_4::<_, P::R, _, _, _>(move |payload, list, head| {
-            vectors::read_for_h0_tuple::<P::R, O, _>(
-                head,
-                list.into_iter(),
-                payload,
-                LTryAccess::new(
-                    O::Vector::unpack(vector.as_borrowed()),
-                    O::DistanceAccessor::default(),
+            let unpack = O::Vector::unpack(vector.as_borrowed());
+            match precision {
+                RerankPrecision::F32 => vectors::read_for_h0_tuple::<P::R, O, _>(
+                    head,
+                    list.into_iter(),
+                    payload,
+                    LTryAccess::new(unpack, O::DistanceAccessor::default()),
                 ),
-            )
+                RerankPrecision::F64 => vectors::read_for_h0_tuple::<P::R, O, _>(
+                    head,
+                    list.into_iter(),
+                    payload,
+                    LTryAccess::new(unpack, O::PreciseDistanceAccessor::default()),
+                ),
+            }
         }),
         _phantom: PhantomData,
     }
@@ -101,6 +115,7 @@ pub fn rerank_heap<
     vector: O::Vector,
     prefetcher: P,
     mut fetch: impl FnMut(NonZero<u64>) -> Option<O::Vector> + 'b,
+    precision: RerankPrecision,
 ) -> Reranker<
     T,
     impl FnMut(NonZero<u64>, Vec<<P::R as RelationRead>::ReadGuard<'_>>, u16) -> Option<Distance>,
@@ -109,13 +124,23 @@ pub fn rerank_heap<
     Reranker {
         prefetcher,
         cache: BinaryHeap::new(),
+        reranked: 0,
         f: id_4::<_, P::R, _, _, _>(move |payload, _, _| {
             let unpack = O::Vector::unpack(vector.as_borrowed());
             let vector = fetch(payload)?;
             let vector = O::Vector::unpack(vector.as_borrowed());
-            let mut accessor = O::DistanceAccessor::default();
-            accessor.push(unpack.0, vector.0);
-            let distance = accessor.finish(unpack.1, vector.1);
+            let distance = match precision {
+                RerankPrecision::F32 => {
+                    let mut accessor = O::DistanceAccessor::default();
+                    accessor.push(unpack.0, vector.0);
+                    accessor.finish(unpack.1, vector.1)
+                }
+                RerankPrecision::F64 => {
+                    let mut accessor = O::PreciseDistanceAccessor::default();
+                    accessor.push(unpack.0, vector.0);
+                    accessor.finish(unpack.1, vector.1)
+                }
+            };
             Some(distance)
         }),
         _phantom: PhantomData,
@@ -6,9 +6,12 @@ use crate::tuples::*;
 use crate::{Bump, Page, RelationRead, tape, vectors};
 use always_equal::AlwaysEqual;
 use distance::Distance;
+use std::cell::Cell;
 use std::cmp::Reverse;
 use std::collections::BinaryHeap;
 use std::num::NonZero;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Instant;
 use vector::{VectorBorrowed, VectorOwned};
 
 type Item<'b> = (
@@ -18,6 +21,23 @@ type Item<'b> = (
 
 type Extra<'b> = &'b mut (NonZero<u64>, u16, &'b mut [u32]);
 
+// Lists selected by upper levels for a query, and the number of centroids whose
+// distances are computed to select them.
+#[derive(Debug, Clone)]
+pub struct Probed<V> {
+    pub lists: Vec<(u32, Option<V>)>,
+    pub centroids: u64,
+}
+
+// If `probed` is given, upper levels are skipped and these lists are probed.
+// Otherwise, the selected lists are stored in it. If `limit` is given, no more
+// results are needed, so tails of sorted lists are pruned by upper bounds. If
+// `adaptive` is also given, probing stops once a list improves the `limit`-th
+// upper bound by less than this fraction of it. Probed lists are counted in `opened`.
+// At most `max_per_list` candidates, the nearest by estimated distances, are taken
+// from a list. Candidates take at most `work_mem` bytes, and the ones with the
+// greatest lower bounds are dropped to fit.
+#[allow(clippy::too_many_arguments)]
 pub fn default_search<'b, R: RelationRead, O: Operator, P: Prefetcher<R = R, Item = Item<'b>>>(
     index: R,
     vector: O::Vector,
@@ -25,12 +45,25 @@ pub fn default_search<'b, R: RelationRead, O: Operator, P: Prefetcher<R = R, Ite
     epsilon: f32,
     bump: &'b impl Bump,
     mut prefetch: impl FnMut(Vec<Item<'b>>) -> P,
-) -> Vec<((Reverse<Distance>, AlwaysEqual<()>), AlwaysEqual<Extra<'b>>)> {
+    parallel: Option<&AtomicU32>,
+    deadline: Option<Instant>,
+    limit: Option<u32>,
+    adaptive: Option<f32>,
+    max_per_list: Option<u32>,
+    work_mem: Option<usize>,
+    opened: &Cell<u64>,
+    probed: &mut Option<Probed<O::Vector>>,
+) -> Vec<(
+    (Reverse<Distance>, AlwaysEqual<Distance>),
+    AlwaysEqual<Extra<'b>>,
+)> {
     let meta_guard = index.read(0);
     let meta_bytes = meta_guard.get(1).expect("data corruption");
     let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
     let dims = meta_tuple.dims();
     let is_residual = meta_tuple.is_residual();
+    let sort_lists = meta_tuple.sort_lists();
+    let code_alignment = meta_tuple.code_alignment();
     let height_of_root = meta_tuple.height_of_root();
     assert_eq!(dims, vector.as_borrowed().dims(), "unmatched dimensions");
     if height_of_root as usize != 1 + probes.len() {
@@ -68,7 +101,9 @@ pub fn default_search<'b, R: RelationRead, O: Operator, P: Prefetcher<R = R, Ite
             (root_first, None)
         }
     }];
+    let centroids = Cell::new(0_u64);
     let mut step = |state: State<O>| {
+        let centroids = &centroids;
         let mut results = LinkedVec::new();
         for (first, residual) in state {
             let block_lut = if let Some(residual) = residual {
@@ -81,7 +116,12 @@ pub fn default_search<'b, R: RelationRead, O: Operator, P: Prefetcher<R = R, Ite
             tape::read_h1_tape(
                 index.clone(),
                 first,
-                || RAccess::new((&block_lut.1, block_lut.0), O::BlockAccessor::default()),
+                || {
+                    RAccess::new(
+                        (&block_lut.1, block_lut.0),
+                        O::BlockAccessor::from_alignment(code_alignment),
+                    )
+                },
                 |(rough, err), head, first, prefetch| {
                     let lowerbound = Distance::from_f32(rough - err * epsilon);
                     results.push((
@@ -99,6 +139,7 @@ pub fn default_search<'b, R: RelationRead, O: Operator, P: Prefetcher<R = R, Ite
             while let Some(((Reverse(_), AlwaysEqual(&mut (first, head, ..))), list)) =
                 heap.pop_if(|(d, ..)| Some(*d) > cache.peek().map(|(d, ..)| *d))
             {
+                centroids.set(centroids.get() + 1);
                 if is_residual {
                     let (distance, residual) = vectors::read_for_h1_tuple::<R, O, _>(
                         head,
@@ -129,12 +170,50 @@ pub fn default_search<'b, R: RelationRead, O: Operator, P: Prefetcher<R = R, Ite
             Some((first, mean))
         })
     };
-    for i in (1..height_of_root).rev() {
-        state = step(state).take(probes[i as usize - 1] as _).collect();
+    if let Some(probed) = probed.as_ref() {
+        state = probed.lists.clone();
+    } else {
+        for i in (1..height_of_root).rev() {
+            state = step(state).take(probes[i as usize - 1] as _).collect();
+        }
+        *probed = Some(Probed {
+            lists: state.clone(),
+            centroids: centroids.get(),
+        });
     }
 
+    // participants of a parallel scan select the same lists and take turns to claim them
+    let claim = || parallel.map(|next| next.fetch_add(1, Ordering::Relaxed) as usize);
+    let mut claimed = claim();
     let mut results = LinkedVec::new();
-    for (first, residual) in state {
+    // upper bounds of the best `limit` candidates
+    let limit = limit.filter(|&limit| limit != 0);
+    let mut upperbounds = BinaryHeap::<Distance>::new();
+    let worst = Cell::new(Distance::INFINITY);
+    // participants of a parallel scan probe different lists, so none of them can stop
+    let adaptive = adaptive.filter(|_| limit.is_some() && parallel.is_none());
+    let mut previous = Distance::INFINITY;
+    // candidates are owned until the scan ends, so dropped ones take no memory
+    type Bounded = Reverse<(
+        (Reverse<Distance>, AlwaysEqual<Distance>),
+        AlwaysEqual<(NonZero<u64>, u16, Vec<u32>)>,
+    )>;
+    let mut bounded = BinaryHeap::<Bounded>::new();
+    let mut used = 0_usize;
+    let bytes = |prefetch: &[u32]| size_of::<Bounded>() + size_of_val(prefetch);
+    for (i, (first, residual)) in state.into_iter().enumerate() {
+        if let Some(index) = claimed {
+            if i != index {
+                continue;
+            }
+            claimed = claim();
+        }
+        // codes are sorted by distances to the centroid, and the distance between
+        // the vector and the centroid is the norm of the residual
+        let center = residual
+            .as_ref()
+            .filter(|_| sort_lists && limit.is_some())
+            .map(|residual| Distance::from_f32(residual.as_borrowed().norm().powi(2)));
         let (block_lut, binary_lut) =
             if let Some(residual) = residual.as_ref().map(|x| x.as_borrowed()) {
                 &O::Vector::preprocess(residual)
@@ -146,19 +225,73 @@ pub fn default_search<'b, R: RelationRead, O: Operator, P: Prefetcher<R = R, Ite
         let jump_guard = index.read(first);
         let jump_bytes = jump_guard.get(1).expect("data corruption");
         let jump_tuple = JumpTuple::deserialize_ref(jump_bytes);
-        let mut callback = id_2(|(rough, err), mean, payload, prefetch| {
-            let lowerbound = Distance::from_f32(rough - err * epsilon);
-            results.push((
-                (Reverse(lowerbound), AlwaysEqual(())),
-                AlwaysEqual(bump.alloc((payload, mean, bump.alloc_slice(prefetch)))),
+        let mut accept = id_2(
+            |(rough, err): (f32, f32), mean: u16, payload: NonZero<u64>, prefetch: &[u32]| {
+                let lowerbound = lowerbound((rough, err), epsilon);
+                if let Some(limit) = limit.filter(|_| rough.is_finite() && err.is_finite()) {
+                    upperbounds.push(Distance::from_f32(rough + err * epsilon));
+                    if upperbounds.len() > limit as usize {
+                        upperbounds.pop();
+                    }
+                    if upperbounds.len() == limit as usize {
+                        worst.set(*upperbounds.peek().unwrap());
+                    }
+                }
+                let rough = Distance::from_f32(rough);
+                let Some(work_mem) = work_mem else {
+                    results.push((
+                        (Reverse(lowerbound), AlwaysEqual(rough)),
+                        AlwaysEqual(bump.alloc((payload, mean, bump.alloc_slice(prefetch)))),
+                    ));
+                    return;
+                };
+                used += bytes(prefetch);
+                bounded.push(Reverse((
+                    (Reverse(lowerbound), AlwaysEqual(rough)),
+                    AlwaysEqual((payload, mean, prefetch.to_vec())),
+                )));
+                while used > work_mem {
+                    let Some(Reverse((_, AlwaysEqual((_, _, worst))))) = bounded.pop() else {
+                        break;
+                    };
+                    used -= bytes(&worst);
+                }
+            },
+        );
+        // the nearest `max_per_list` candidates by estimated distances are kept, so
+        // the whole list is read before they're accepted
+        let mut top =
+            BinaryHeap::<(Distance, AlwaysEqual<(f32, u16, NonZero<u64>, Vec<u32>)>)>::new();
+        let mut callback = id_2(|(rough, err): (f32, f32), mean, payload, prefetch| {
+            let Some(max) = max_per_list.filter(|_| rough.is_finite() && err.is_finite()) else {
+                return accept((rough, err), mean, payload, prefetch);
+            };
+            top.push((
+                Distance::from_f32(rough),
+                AlwaysEqual((err, mean, payload, prefetch.to_vec())),
             ));
+            if top.len() > max as usize {
+                top.pop();
+            }
         });
-        tape::read_frozen_tape(
+        tape::read_frozen_tape_until(
             index.clone(),
             jump_tuple.frozen_first(),
-            || RAccess::new((&block_lut.1, block_lut.0), O::BlockAccessor::default()),
+            || {
+                RAccess::new(
+                    (&block_lut.1, block_lut.0),
+                    O::BlockAccessor::from_alignment(code_alignment),
+                )
+            },
             &mut callback,
             |_| (),
+            |dis_u_2| {
+                let Some(center) = center else {
+                    return false;
+                };
+                let radius = dis_u_2.iter().copied().fold(f32::INFINITY, f32::min).sqrt();
+                O::shell_lowerbound(center, radius) > worst.get()
+            },
         );
         tape::read_appendable_tape(
             index.clone(),
@@ -167,8 +300,33 @@ pub fn default_search<'b, R: RelationRead, O: Operator, P: Prefetcher<R = R, Ite
             &mut callback,
             |_| (),
         );
+        drop(callback);
+        for (rough, AlwaysEqual((err, mean, payload, prefetch))) in top.into_sorted_vec() {
+            accept((rough.to_f32(), err), mean, payload, prefetch.as_slice());
+        }
+        opened.set(opened.get() + 1);
+        // lists are in order of distance, so the nearest ones are always probed
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break;
+        }
+        if let Some(adaptive) = adaptive {
+            let (previous, current) = (previous.to_f32(), worst.get().to_f32());
+            if previous.is_finite() && previous - current <= adaptive * previous.abs() {
+                break;
+            }
+        }
+        previous = worst.get();
     }
-    results.into_vec()
+    let mut results = results.into_vec();
+    results.extend(bounded.into_iter().map(
+        |Reverse((key, AlwaysEqual((payload, mean, prefetch))))| {
+            (
+                key,
+                AlwaysEqual(bump.alloc((payload, mean, bump.alloc_slice(&prefetch)))),
+            )
+        },
+    ));
+    results
 }
 
 pub fn maxsim_search<'b, R: RelationRead, O: Operator, P: Prefetcher<R = R, Item = Item<'b>>>(
@@ -191,6 +349,7 @@ pub fn maxsim_search<'b, R: RelationRead, O: Operator, P: Prefetcher<R = R, Item
     let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
     let dims = meta_tuple.dims();
     let is_residual = meta_tuple.is_residual();
+    let code_alignment = meta_tuple.code_alignment();
     let height_of_root = meta_tuple.height_of_root();
     assert_eq!(dims, vector.as_borrowed().dims(), "unmatched dimensions");
     if height_of_root as usize != 1 + probes.len() {
@@ -241,7 +400,12 @@ pub fn maxsim_search<'b, R: RelationRead, O: Operator, P: Prefetcher<R = R, Item
             tape::read_h1_tape(
                 index.clone(),
                 first,
-                || RAccess::new((&block_lut.1, block_lut.0), O::BlockAccessor::default()),
+                || {
+                    RAccess::new(
+                        (&block_lut.1, block_lut.0),
+                        O::BlockAccessor::from_alignment(code_alignment),
+                    )
+                },
                 |(rough, err), head, first, prefetch| {
                     let lowerbound = Distance::from_f32(rough - err * epsilon);
                     results.push((
@@ -309,7 +473,7 @@ pub fn maxsim_search<'b, R: RelationRead, O: Operator, P: Prefetcher<R = R, Item
         let jump_bytes = jump_guard.get(1).expect("data corruption");
         let jump_tuple = JumpTuple::deserialize_ref(jump_bytes);
         let mut callback = id_2(|(rough, err), mean, payload, prefetch| {
-            let lowerbound = Distance::from_f32(rough - err * epsilon);
+            let lowerbound = lowerbound((rough, err), epsilon);
             let rough = Distance::from_f32(rough);
             results.push((
                 (Reverse(lowerbound), AlwaysEqual(rough)),
@@ -319,7 +483,12 @@ pub fn maxsim_search<'b, R: RelationRead, O: Operator, P: Prefetcher<R = R, Item
         tape::read_frozen_tape(
             index.clone(),
             jump_tuple.frozen_first(),
-            || RAccess::new((&block_lut.1, block_lut.0), O::BlockAccessor::default()),
+            || {
+                RAccess::new(
+                    (&block_lut.1, block_lut.0),
+                    O::BlockAccessor::from_alignment(code_alignment),
+                )
+            },
             &mut callback,
             |_| (),
         );
@@ -345,3 +514,12 @@ pub fn maxsim_search<'b, R: RelationRead, O: Operator, P: Prefetcher<R = R, Item
     }
     (results.into_vec(), estimation_by_threshold)
 }
+
+// an invalid code gives no bound, so the candidate is reranked first
+pub(crate) fn lowerbound((rough, err): (f32, f32), epsilon: f32) -> Distance {
+    if rough.is_finite() && err.is_finite() {
+        Distance::from_f32(rough - err * epsilon)
+    } else {
+        Distance::NEG_INFINITY
+    }
+}
@@ -0,0 +1,62 @@
+use crate::operator::Vector;
+use crate::tuples::*;
+use crate::types::VchordrqIndexOptions;
+use vector::vect::VectOwned;
+
+// The number of pages of an index of `vector`s right after a build. A build inserts
+// codes into appendable tapes and then moves them into frozen tapes on the pages
+// freed by former lists, so the index keeps the size of the insertion, which is
+// larger. Lists are assumed to be balanced, codes to have `bits` bits per dimension,
+// and pages to hold `contents` bytes, including a line pointer for each tuple.
+pub fn estimate_size(
+    contents: usize,
+    rows: u64,
+    dims: u32,
+    bits: u32,
+    lists: u32,
+    rerank_in_heap: bool,
+) -> u64 {
+    let pages = |n: u64, size: usize| {
+        let per_page = contents / (size.next_multiple_of(ALIGN) + 4);
+        if per_page != 0 {
+            n.div_ceil(per_page as u64)
+        } else {
+            n * size.div_ceil(contents) as u64
+        }
+    };
+    let dims = dims as usize;
+    let bits = bits as usize;
+    let align = VchordrqIndexOptions::default().code_alignment;
+    let count = <VectOwned<f32> as Vector>::count(dims);
+    let leaves = lists.max(1) as u64;
+    let nodes = if lists != 0 { 1 + lists as u64 } else { 1 };
+
+    // the meta page and the first page of free pages
+    let mut result = 2_u64;
+
+    let vectors = if rerank_in_heap { nodes } else { nodes + rows };
+    result += if count == 1 {
+        pages(
+            vectors,
+            VectorTuple::<VectOwned<f32>>::estimate_size_0(dims),
+        )
+    } else {
+        let chunk = dims.div_ceil(count);
+        let size = VectorTuple::<VectOwned<f32>>::estimate_size_1(chunk);
+        pages(vectors * count as u64, size)
+    }
+    .max(1);
+
+    if lists != 0 {
+        let elements = (dims * bits).div_ceil(4);
+        let size = H1Tuple::estimate_size_0(count, elements, align);
+        result += pages((lists as u64).div_ceil(32), size).max(1);
+    }
+
+    // a jump tuple, the first page of the frozen tape and the appendable tape
+    let prefetch = if rerank_in_heap { 0 } else { count };
+    let size = AppendableTuple::estimate_size(prefetch, (dims * bits).div_ceil(64));
+    result += leaves * (2 + pages(rows.div_ceil(leaves), size).max(1));
+
+    result
+}
@@ -0,0 +1,67 @@
+use crate::closure_lifetime_binder::{id_0, id_1};
+use crate::operator::{FunctionalAccessor, Operator, Vector};
+use crate::tuples::*;
+use crate::types::Structure;
+use crate::{Page, RelationRead, tape, vectors};
+use simd::Floating;
+
+// The inverse of `build`: levels are returned from the bottom to the root, and
+// means are in the projected space.
+pub fn structures<R: RelationRead, O: Operator>(index: R) -> Vec<Structure<Vec<f32>>>
+where
+    <O::Vector as Vector>::Element: Floating,
+{
+    let meta_guard = index.read(0);
+    let meta_bytes = meta_guard.get(1).expect("data corruption");
+    let meta_tuple = MetaTuple::deserialize_ref(meta_bytes);
+    let height_of_root = meta_tuple.height_of_root();
+    let root_prefetch = meta_tuple.root_prefetch().to_vec();
+    let root_head = meta_tuple.root_head();
+    let root_first = meta_tuple.root_first();
+    drop(meta_guard);
+
+    let mean = |head: u16, prefetch: &[u32]| {
+        let elements = vectors::read_for_h1_tuple::<R, O, _>(
+            head,
+            prefetch.iter().map(|&id| index.read(id)),
+            FunctionalAccessor::new(
+                Vec::<<O::Vector as Vector>::Element>::new(),
+                Vec::<<O::Vector as Vector>::Element>::extend_from_slice,
+                |elements: Vec<_>, _| elements,
+            ),
+        );
+        <<O::Vector as Vector>::Element as Floating>::vector_to_f32(&elements)
+    };
+
+    type State = Vec<(u32, u16, Vec<u32>)>;
+    let mut state: State = vec![(root_first, root_head, root_prefetch)];
+    let mut levels = Vec::new();
+    for _ in (1..height_of_root).rev() {
+        let mut means = Vec::new();
+        let mut children = Vec::new();
+        let mut results = State::new();
+        for (first, head, prefetch) in state {
+            means.push(mean(head, &prefetch));
+            let start = results.len() as u32;
+            tape::read_h1_tape(
+                index.clone(),
+                first,
+                || FunctionalAccessor::new((), id_0(|_, _| ()), id_1(|_, _| [(); 32])),
+                |(), head, first, prefetch| results.push((first, head, prefetch.to_vec())),
+                |_| (),
+            );
+            children.push((start..results.len() as u32).collect());
+        }
+        levels.push(Structure { means, children });
+        state = results;
+    }
+    levels.push(Structure {
+        means: state
+            .iter()
+            .map(|(_, head, prefetch)| mean(*head, prefetch))
+            .collect(),
+        children: vec![Vec::new(); state.len()],
+    });
+    levels.reverse();
+    levels
+}
@@ -68,9 +68,17 @@ where
             }
         }
     }
-    pub fn tape_put(&mut self, x: T) -> (u32, u16) {
+}
+
+impl<'a, R, T> TapeWriter<'a, R, T>
+where
+    R: RelationWrite + 'a,
+    T: WithElements,
+{
+    pub fn tape_put_aligned(&mut self, x: T, align: u16) -> (u32, u16) {
+        let offset = x.elements_offset();
         let bytes = T::serialize(&x);
-        if let Some(i) = self.head.alloc(&bytes) {
+        if let Some(i) = self.head.alloc_aligned(&bytes, offset, align) {
             (self.head.id(), i)
         } else {
             panic!("implementation: a free page cannot accommodate a single tuple")
@@ -122,11 +130,29 @@ pub fn read_h1_tape<A, T>(
 }
 
 pub fn read_frozen_tape<A, T>(
+    index: impl RelationRead,
+    first: u32,
+    accessor: impl Fn() -> A,
+    callback: impl for<'a> FnMut(T, u16, NonZero<u64>, &'a [u32]),
+    step: impl FnMut(u32),
+) where
+    A: for<'a> Accessor1<
+            [u8; 16],
+            (&'a [f32; 32], &'a [f32; 32], &'a [f32; 32], &'a [f32; 32]),
+            Output = [T; 32],
+        >,
+{
+    read_frozen_tape_until(index, first, accessor, callback, step, |_| false)
+}
+
+// The tape is read until `stop` returns true for `dis_u_2` of a block.
+pub fn read_frozen_tape_until<A, T>(
     index: impl RelationRead,
     first: u32,
     accessor: impl Fn() -> A,
     mut callback: impl for<'a> FnMut(T, u16, NonZero<u64>, &'a [u32]),
     mut step: impl FnMut(u32),
+    mut stop: impl FnMut(&[f32; 32]) -> bool,
 ) where
     A: for<'a> Accessor1<
             [u8; 16],
@@ -145,6 +171,9 @@ pub fn read_frozen_tape<A, T>(
             let tuple = FrozenTuple::deserialize_ref(bytes);
             match tuple {
                 FrozenTupleReader::_0(tuple) => {
+                    if stop(tuple.metadata().0) {
+                        return;
+                    }
                     let mut x = x.take().unwrap_or_else(&accessor);
                     x.push(tuple.elements());
                     let values = x.finish(tuple.metadata());
@@ -188,7 +217,10 @@ pub fn read_appendable_tape<T>(
     }
 }
 
-#[allow(clippy::collapsible_else_if)]
+// A tuple is put in the tail, which is found by following `skip`. If the tail is
+// full, a page is extended without holding the lock of the tail, so that other
+// inserters of the list don't wait for the extension. The page is linked before it's
+// filled, so a tuple is never written to a page outside of the list.
 pub fn append(
     index: impl RelationRead + RelationWrite,
     first: u32,
@@ -199,48 +231,56 @@ pub fn append(
     let mut current = first;
     loop {
         let read = index.read(current);
-        if read.freespace() as usize >= bytes.len() || read.get_opaque().next == u32::MAX {
+        if read.get_opaque().next == u32::MAX {
             drop(read);
             let mut write = index.write(current, tracking_freespace);
             if write.get_opaque().next == u32::MAX {
                 if let Some(i) = write.alloc(bytes) {
                     return (current, i);
                 }
-                let mut extend = index.extend(tracking_freespace);
-                write.get_opaque_mut().next = extend.id();
-                drop(write);
-                let fresh = extend.id();
-                if let Some(i) = extend.alloc(bytes) {
-                    drop(extend);
-                    let mut past = index.write(first, tracking_freespace);
-                    past.get_opaque_mut().skip = fresh.max(past.get_opaque().skip);
-                    return (fresh, i);
-                } else {
+                if write.len() == 0 {
                     panic!("implementation: a clear page cannot accommodate a single tuple");
                 }
+                drop(write);
+                let fresh = index.extend(tracking_freespace).id();
+                link(&index, current, fresh, tracking_freespace);
+                let mut past = index.write(first, tracking_freespace);
+                past.get_opaque_mut().skip = fresh.max(past.get_opaque().skip);
+                drop(past);
+                // other inserters may have filled it, or linked pages after it
+                current = fresh;
+                continue;
             }
-            if current == first && write.get_opaque().skip != first {
-                current = write.get_opaque().skip;
-            } else {
-                current = write.get_opaque().next;
-            }
+            current = write.get_opaque().next;
+        } else if current == first && read.get_opaque().skip != first {
+            current = read.get_opaque().skip;
         } else {
-            if current == first && read.get_opaque().skip != first {
-                current = read.get_opaque().skip;
-            } else {
-                current = read.get_opaque().next;
-            }
+            current = read.get_opaque().next;
+        }
+    }
+}
+
+// Other inserters may have linked their pages meanwhile, so the page is linked to
+// the new tail.
+fn link(index: &impl RelationWrite, mut tail: u32, fresh: u32, tracking_freespace: bool) {
+    loop {
+        let mut write = index.write(tail, tracking_freespace);
+        let next = write.get_opaque().next;
+        if next == u32::MAX {
+            write.get_opaque_mut().next = fresh;
+            return;
         }
+        tail = next;
     }
 }
 
-fn fix_0<T>(x: &[[T; 32]]) -> [&[T]; 32] {
+pub(crate) fn fix_0<T>(x: &[[T; 32]]) -> [&[T]; 32] {
     let step = x.len();
     let flat = x.as_flattened();
     std::array::from_fn(|i| &flat[i * step..][..step])
 }
 
-fn fix_1(x: &[u32]) -> &[u32] {
+pub(crate) fn fix_1(x: &[u32]) -> &[u32] {
     if let Some(i) = x.iter().position(|&x| x == u32::MAX) {
         &x[..i]
     } else {
@@ -7,7 +7,10 @@ use zerocopy::{FromBytes, FromZeros, Immutable, IntoBytes, KnownLayout};
 pub const ALIGN: usize = 8;
 pub type Tag = u64;
 const MAGIC: Tag = Tag::from_ne_bytes(*b"vchordrq");
-const VERSION: u64 = 7;
+const VERSION: u64 = 8;
+// indexes of version 7 are read as they are, since fields added later take their
+// padding or have defaults
+const MIN_VERSION: u64 = 7;
 
 pub trait Tuple: 'static {
     fn serialize(&self) -> Vec<u8>;
@@ -18,6 +21,10 @@ pub trait WithReader: Tuple {
     fn deserialize_ref(source: &[u8]) -> Self::Reader<'_>;
 }
 
+pub trait WithElements: Tuple {
+    fn elements_offset(&self) -> u16;
+}
+
 pub trait WithWriter: Tuple {
     type Writer<'a>;
     fn deserialize_mut(source: &mut [u8]) -> Self::Writer<'_>;
@@ -31,7 +38,10 @@ struct MetaTupleHeader {
     height_of_root: u32,
     is_residual: Bool,
     rerank_in_heap: Bool,
-    _padding_0: [ZeroU8; 2],
+    // log2 of code alignment, zero for indexes built without it
+    code_alignment: u8,
+    // whether codes of frozen tapes are sorted by distances to centroids
+    sort_lists: Bool,
     vectors_first: u32,
     // raw vector
     root_prefetch_s: u16,
@@ -44,6 +54,8 @@ struct MetaTupleHeader {
     // statistics
     cells_s: u16,
     cells_e: u16,
+    aliases_first: u32,
+    aliases_index_first: u32,
     _padding_2: [ZeroU8; 4],
 }
 
@@ -52,12 +64,16 @@ pub struct MetaTuple {
     pub height_of_root: u32,
     pub is_residual: bool,
     pub rerank_in_heap: bool,
+    pub code_alignment: u16,
+    pub sort_lists: bool,
     pub vectors_first: u32,
     pub root_prefetch: Vec<u32>,
     pub root_head: u16,
     pub root_first: u32,
     pub freepage_first: u32,
     pub cells: Vec<u32>,
+    pub aliases_first: u32,
+    pub aliases_index_first: u32,
 }
 
 impl Tuple for MetaTuple {
@@ -70,12 +86,16 @@ impl Tuple for MetaTuple {
                 height_of_root,
                 is_residual,
                 rerank_in_heap,
+                code_alignment,
+                sort_lists,
                 vectors_first,
                 root_prefetch,
                 root_head,
                 root_first,
                 freepage_first,
                 cells,
+                aliases_first,
+                aliases_index_first,
             } => {
                 buffer.extend((MAGIC as Tag).to_ne_bytes());
                 buffer.extend(std::iter::repeat_n(0, size_of::<MetaTupleHeader>()));
@@ -98,7 +118,11 @@ impl Tuple for MetaTuple {
                         height_of_root: *height_of_root,
                         is_residual: (*is_residual).into(),
                         rerank_in_heap: (*rerank_in_heap).into(),
-                        _padding_0: Default::default(),
+                        code_alignment: {
+                            assert!(code_alignment.is_power_of_two());
+                            code_alignment.trailing_zeros() as u8
+                        },
+                        sort_lists: (*sort_lists).into(),
                         vectors_first: *vectors_first,
                         root_prefetch_s,
                         root_prefetch_e,
@@ -108,6 +132,8 @@ impl Tuple for MetaTuple {
                         freepage_first: *freepage_first,
                         cells_s,
                         cells_e,
+                        aliases_first: *aliases_first,
+                        aliases_index_first: *aliases_index_first,
                         _padding_2: Default::default(),
                     }
                     .as_bytes(),
@@ -125,7 +151,7 @@ impl WithReader for MetaTuple {
         match tag {
             MAGIC => {
                 let checker = RefChecker::new(source);
-                if VERSION != *checker.prefix::<u64>(size_of::<Tag>()) {
+                if !(MIN_VERSION..=VERSION).contains(checker.prefix::<u64>(size_of::<Tag>())) {
                     panic!("deserialization: bad version number");
                 }
                 let header: &MetaTupleHeader = checker.prefix(size_of::<Tag>());
@@ -142,6 +168,24 @@ impl WithReader for MetaTuple {
     }
 }
 
+impl WithWriter for MetaTuple {
+    type Writer<'a> = MetaTupleWriter<'a>;
+    fn deserialize_mut(source: &mut [u8]) -> MetaTupleWriter<'_> {
+        let tag = Tag::from_ne_bytes(std::array::from_fn(|i| source[i]));
+        match tag {
+            MAGIC => {
+                let mut checker = MutChecker::new(source);
+                let header: &mut MetaTupleHeader = checker.prefix(size_of::<Tag>());
+                if !(MIN_VERSION..=VERSION).contains(&header.version) {
+                    panic!("deserialization: bad version number");
+                }
+                MetaTupleWriter { header }
+            }
+            _ => panic!("deserialization: bad magic number"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct MetaTupleReader<'a> {
     header: &'a MetaTupleHeader,
@@ -162,6 +206,15 @@ impl<'a> MetaTupleReader<'a> {
     pub fn rerank_in_heap(self) -> bool {
         self.header.rerank_in_heap.into()
     }
+    pub fn code_alignment(self) -> u16 {
+        match self.header.code_alignment {
+            0 => ALIGN as u16,
+            x => 1 << x,
+        }
+    }
+    pub fn sort_lists(self) -> bool {
+        self.header.sort_lists.into()
+    }
     pub fn vectors_first(self) -> u32 {
         self.header.vectors_first
     }
@@ -180,6 +233,37 @@ impl<'a> MetaTupleReader<'a> {
     pub fn cells(self) -> &'a [u32] {
         self.cells
     }
+    pub fn aliases_first(self) -> u32 {
+        if self.header.version < 8 {
+            u32::MAX
+        } else {
+            self.header.aliases_first
+        }
+    }
+    pub fn aliases_index_first(self) -> u32 {
+        if self.header.version < 8 {
+            u32::MAX
+        } else {
+            self.header.aliases_index_first
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MetaTupleWriter<'a> {
+    header: &'a mut MetaTupleHeader,
+}
+
+impl MetaTupleWriter<'_> {
+    pub fn rerank_in_heap(&mut self) -> &mut Bool {
+        &mut self.header.rerank_in_heap
+    }
+    pub fn aliases_first(&mut self) -> &mut u32 {
+        &mut self.header.aliases_first
+    }
+    pub fn aliases_index_first(&mut self) -> &mut u32 {
+        &mut self.header.aliases_index_first
+    }
 }
 
 #[repr(C, align(8))]
@@ -292,6 +376,24 @@ pub enum VectorTuple<V: Vector> {
     },
 }
 
+impl<V: Vector> VectorTuple<V> {
+    pub fn estimate_size_0(elements: usize) -> usize {
+        let mut size = 0_usize;
+        size += size_of::<Tag>();
+        size += size_of::<VectorTupleHeader0>();
+        size += size_of::<V::Metadata>().next_multiple_of(ALIGN);
+        size += (elements * size_of::<V::Element>()).next_multiple_of(ALIGN);
+        size
+    }
+    pub fn estimate_size_1(elements: usize) -> usize {
+        let mut size = 0_usize;
+        size += size_of::<Tag>();
+        size += size_of::<VectorTupleHeader1>();
+        size += (elements * size_of::<V::Element>()).next_multiple_of(ALIGN);
+        size
+    }
+}
+
 impl<V: Vector> Tuple for VectorTuple<V> {
     fn serialize(&self) -> Vec<u8> {
         let mut buffer = Vec::<u8>::new();
@@ -473,19 +575,21 @@ pub enum H1Tuple {
 }
 
 impl H1Tuple {
-    pub fn estimate_size_0(prefetch: usize, elements: usize) -> usize {
+    pub fn estimate_size_0(prefetch: usize, elements: usize, align: u16) -> usize {
         let mut size = 0_usize;
         size += size_of::<Tag>();
         size += size_of::<H1TupleHeader0>();
         size += (prefetch * size_of::<[u32; 32]>()).next_multiple_of(ALIGN);
         size += (elements * size_of::<[u8; 16]>()).next_multiple_of(ALIGN);
+        size += align as usize - ALIGN;
         size
     }
-    pub fn fit_1(prefetch: usize, freespace: u16) -> Option<usize> {
+    pub fn fit_1(prefetch: usize, freespace: u16, align: u16) -> Option<usize> {
         let mut freespace = freespace as isize;
         freespace -= size_of::<Tag>() as isize;
         freespace -= size_of::<H1TupleHeader1>() as isize;
         freespace -= (prefetch * size_of::<[u32; 32]>()).next_multiple_of(ALIGN) as isize;
+        freespace -= align as isize - ALIGN as isize;
         if freespace >= 0 {
             Some(freespace as usize / size_of::<[u8; 16]>())
         } else {
@@ -494,6 +598,20 @@ impl H1Tuple {
     }
 }
 
+impl WithElements for H1Tuple {
+    fn elements_offset(&self) -> u16 {
+        let offset = match self {
+            Self::_0 { prefetch, .. } => {
+                size_of::<Tag>()
+                    + size_of::<H1TupleHeader0>()
+                    + size_of_val(prefetch.as_slice()).next_multiple_of(ALIGN)
+            }
+            Self::_1 { .. } => size_of::<Tag>() + size_of::<H1TupleHeader1>(),
+        };
+        offset as u16
+    }
+}
+
 impl Tuple for H1Tuple {
     fn serialize(&self) -> Vec<u8> {
         let mut buffer = Vec::<u8>::new();
@@ -651,22 +769,42 @@ struct JumpTupleHeader {
     tuples: u64,
 }
 
+// jump tuples of version 7 end before it
+#[repr(C, align(8))]
+#[derive(Debug, Clone, PartialEq, FromBytes, IntoBytes, Immutable, KnownLayout)]
+struct JumpTupleExtension {
+    // upper bound of distances between the centroid and vectors in the list
+    radius: f32,
+    _padding_0: [ZeroU8; 4],
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct JumpTuple {
     pub frozen_first: u32,
     pub appendable_first: u32,
     pub tuples: u64,
+    pub radius: f32,
 }
 
 impl Tuple for JumpTuple {
     fn serialize(&self) -> Vec<u8> {
-        JumpTupleHeader {
-            frozen_first: self.frozen_first,
-            appendable_first: self.appendable_first,
-            tuples: self.tuples,
-        }
-        .as_bytes()
-        .to_vec()
+        let mut buffer = Vec::<u8>::new();
+        buffer.extend(
+            JumpTupleHeader {
+                frozen_first: self.frozen_first,
+                appendable_first: self.appendable_first,
+                tuples: self.tuples,
+            }
+            .as_bytes(),
+        );
+        buffer.extend(
+            JumpTupleExtension {
+                radius: self.radius,
+                _padding_0: Default::default(),
+            }
+            .as_bytes(),
+        );
+        buffer
     }
 }
 
@@ -675,22 +813,27 @@ impl WithReader for JumpTuple {
     fn deserialize_ref(source: &[u8]) -> JumpTupleReader<'_> {
         let checker = RefChecker::new(source);
         let header: &JumpTupleHeader = checker.prefix(0_u16);
-        JumpTupleReader { header }
+        let extension = (source.len() > size_of::<JumpTupleHeader>())
+            .then(|| checker.prefix(size_of::<JumpTupleHeader>()));
+        JumpTupleReader { header, extension }
     }
 }
 
 impl WithWriter for JumpTuple {
     type Writer<'a> = JumpTupleWriter<'a>;
     fn deserialize_mut(source: &mut [u8]) -> JumpTupleWriter<'_> {
+        let extended = source.len() > size_of::<JumpTupleHeader>();
         let mut checker = MutChecker::new(source);
         let header: &mut JumpTupleHeader = checker.prefix(0_u16);
-        JumpTupleWriter { header }
+        let extension = extended.then(|| checker.prefix(size_of::<JumpTupleHeader>()));
+        JumpTupleWriter { header, extension }
     }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct JumpTupleReader<'a> {
     header: &'a JumpTupleHeader,
+    extension: Option<&'a JumpTupleExtension>,
 }
 
 impl JumpTupleReader<'_> {
@@ -703,11 +846,16 @@ impl JumpTupleReader<'_> {
     pub fn tuples(self) -> u64 {
         self.header.tuples
     }
+    // lists of version 7 have no bound
+    pub fn radius(self) -> f32 {
+        self.extension.map_or(f32::INFINITY, |x| x.radius)
+    }
 }
 
 #[derive(Debug)]
 pub struct JumpTupleWriter<'a> {
     header: &'a mut JumpTupleHeader,
+    extension: Option<&'a mut JumpTupleExtension>,
 }
 
 impl JumpTupleWriter<'_> {
@@ -720,6 +868,9 @@ impl JumpTupleWriter<'_> {
     pub fn tuples(&mut self) -> &mut u64 {
         &mut self.header.tuples
     }
+    pub fn radius(&mut self) -> Option<&mut f32> {
+        self.extension.as_mut().map(|x| &mut x.radius)
+    }
 }
 
 #[repr(C, align(8))]
@@ -764,19 +915,21 @@ pub enum FrozenTuple {
 }
 
 impl FrozenTuple {
-    pub fn estimate_size_0(prefetch: usize, elements: usize) -> usize {
+    pub fn estimate_size_0(prefetch: usize, elements: usize, align: u16) -> usize {
         let mut size = 0_usize;
         size += size_of::<Tag>();
         size += size_of::<FrozenTupleHeader0>();
         size += (prefetch * size_of::<[u32; 32]>()).next_multiple_of(ALIGN);
         size += (elements * size_of::<[u8; 16]>()).next_multiple_of(ALIGN);
+        size += align as usize - ALIGN;
         size
     }
-    pub fn fit_1(prefetch: usize, freespace: u16) -> Option<usize> {
+    pub fn fit_1(prefetch: usize, freespace: u16, align: u16) -> Option<usize> {
         let mut freespace = freespace as isize;
         freespace -= size_of::<Tag>() as isize;
         freespace -= size_of::<FrozenTupleHeader1>() as isize;
         freespace -= (prefetch * size_of::<[u32; 32]>()).next_multiple_of(ALIGN) as isize;
+        freespace -= align as isize - ALIGN as isize;
         if freespace >= 0 {
             Some(freespace as usize / size_of::<[u8; 16]>())
         } else {
@@ -785,6 +938,20 @@ impl FrozenTuple {
     }
 }
 
+impl WithElements for FrozenTuple {
+    fn elements_offset(&self) -> u16 {
+        let offset = match self {
+            Self::_0 { prefetch, .. } => {
+                size_of::<Tag>()
+                    + size_of::<FrozenTupleHeader0>()
+                    + size_of_val(prefetch.as_slice()).next_multiple_of(ALIGN)
+            }
+            Self::_1 { .. } => size_of::<Tag>() + size_of::<FrozenTupleHeader1>(),
+        };
+        offset as u16
+    }
+}
+
 impl Tuple for FrozenTuple {
     fn serialize(&self) -> Vec<u8> {
         let mut buffer = Vec::<u8>::new();
@@ -1007,6 +1174,16 @@ pub struct AppendableTuple {
     pub elements: Vec<u64>,
 }
 
+impl AppendableTuple {
+    pub fn estimate_size(prefetch: usize, elements: usize) -> usize {
+        let mut size = 0_usize;
+        size += size_of::<AppendableTupleHeader>();
+        size += (prefetch * size_of::<u32>()).next_multiple_of(ALIGN);
+        size += elements * size_of::<u64>();
+        size
+    }
+}
+
 impl Tuple for AppendableTuple {
     fn serialize(&self) -> Vec<u8> {
         let mut buffer = Vec::<u8>::new();
@@ -1111,6 +1288,154 @@ impl AppendableTupleWriter<'_> {
     }
 }
 
+#[repr(C, align(8))]
+#[derive(Debug, Clone, PartialEq, FromBytes, IntoBytes, Immutable, KnownLayout)]
+struct AliasTupleHeader {
+    payload: Option<NonZero<u64>>,
+    members_s: u16,
+    members_e: u16,
+    _padding_0: [ZeroU8; 4],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AliasTuple {
+    pub payload: NonZero<u64>,
+    pub members: Vec<Option<NonZero<u64>>>,
+}
+
+impl Tuple for AliasTuple {
+    fn serialize(&self) -> Vec<u8> {
+        let mut buffer = Vec::<u8>::new();
+        buffer.extend(std::iter::repeat_n(0, size_of::<AliasTupleHeader>()));
+        let members_s = buffer.len() as u16;
+        buffer.extend(self.members.as_bytes());
+        let members_e = buffer.len() as u16;
+        buffer[..size_of::<AliasTupleHeader>()].copy_from_slice(
+            AliasTupleHeader {
+                payload: Some(self.payload),
+                members_s,
+                members_e,
+                _padding_0: Default::default(),
+            }
+            .as_bytes(),
+        );
+        buffer
+    }
+}
+
+impl WithReader for AliasTuple {
+    type Reader<'a> = AliasTupleReader<'a>;
+
+    fn deserialize_ref(source: &[u8]) -> AliasTupleReader<'_> {
+        let checker = RefChecker::new(source);
+        let header: &AliasTupleHeader = checker.prefix(0_u16);
+        let members = checker.bytes(header.members_s, header.members_e);
+        AliasTupleReader { header, members }
+    }
+}
+
+impl WithWriter for AliasTuple {
+    type Writer<'a> = AliasTupleWriter<'a>;
+
+    fn deserialize_mut(source: &mut [u8]) -> AliasTupleWriter<'_> {
+        let mut checker = MutChecker::new(source);
+        let header: &mut AliasTupleHeader = checker.prefix(0_u16);
+        let members = checker.bytes(header.members_s, header.members_e);
+        AliasTupleWriter { header, members }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AliasTupleReader<'a> {
+    header: &'a AliasTupleHeader,
+    members: &'a [Option<NonZero<u64>>],
+}
+
+impl<'a> AliasTupleReader<'a> {
+    pub fn payload(self) -> NonZero<u64> {
+        self.header.payload.expect("data corruption")
+    }
+    pub fn members(self) -> &'a [Option<NonZero<u64>>] {
+        self.members
+    }
+}
+
+#[derive(Debug)]
+pub struct AliasTupleWriter<'a> {
+    header: &'a mut AliasTupleHeader,
+    members: &'a mut [Option<NonZero<u64>>],
+}
+
+impl AliasTupleWriter<'_> {
+    pub fn payload(&self) -> NonZero<u64> {
+        self.header.payload.expect("data corruption")
+    }
+    pub fn members(&mut self) -> &mut [Option<NonZero<u64>>] {
+        self.members
+    }
+}
+
+// an alias page, with the range of payloads on it, since a tape of aliases is sorted
+#[repr(C, align(8))]
+#[derive(Debug, Clone, PartialEq, FromBytes, IntoBytes, Immutable, KnownLayout)]
+struct AliasIndexTupleHeader {
+    first: Option<NonZero<u64>>,
+    last: Option<NonZero<u64>>,
+    batch: u32,
+    page: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AliasIndexTuple {
+    pub first: NonZero<u64>,
+    pub last: NonZero<u64>,
+    pub batch: u32,
+    pub page: u32,
+}
+
+impl Tuple for AliasIndexTuple {
+    fn serialize(&self) -> Vec<u8> {
+        AliasIndexTupleHeader {
+            first: Some(self.first),
+            last: Some(self.last),
+            batch: self.batch,
+            page: self.page,
+        }
+        .as_bytes()
+        .to_vec()
+    }
+}
+
+impl WithReader for AliasIndexTuple {
+    type Reader<'a> = AliasIndexTupleReader<'a>;
+
+    fn deserialize_ref(source: &[u8]) -> AliasIndexTupleReader<'_> {
+        let checker = RefChecker::new(source);
+        let header: &AliasIndexTupleHeader = checker.prefix(0_u16);
+        AliasIndexTupleReader { header }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AliasIndexTupleReader<'a> {
+    header: &'a AliasIndexTupleHeader,
+}
+
+impl AliasIndexTupleReader<'_> {
+    pub fn first(self) -> NonZero<u64> {
+        self.header.first.expect("data corruption")
+    }
+    pub fn last(self) -> NonZero<u64> {
+        self.header.last.expect("data corruption")
+    }
+    pub fn batch(self) -> u32 {
+        self.header.batch
+    }
+    pub fn page(self) -> u32 {
+        self.header.page
+    }
+}
+
 #[repr(transparent)]
 #[derive(
     Debug,
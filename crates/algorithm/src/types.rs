@@ -3,13 +3,24 @@ use serde::{Deserialize, Serialize};
 use validator::{Validate, ValidationError};
 use vector::vect::{VectBorrowed, VectOwned};
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 #[serde(deny_unknown_fields)]
+#[validate(schema(function = "Self::validate_self"))]
 pub struct VchordrqIndexOptions {
     #[serde(default = "VchordrqIndexOptions::default_residual_quantization")]
     pub residual_quantization: bool,
     #[serde(default = "VchordrqIndexOptions::default_rerank_in_table")]
     pub rerank_in_table: bool,
+    #[serde(default = "VchordrqIndexOptions::default_code_alignment")]
+    #[validate(range(min = 8, max = 64))]
+    pub code_alignment: u16,
+    // codes of a list are sorted by distances to its centroid, so that tails of lists
+    // are pruned by shell lower bounds; it takes effect only with residual quantization,
+    // where distance type is L2, and only for scans with `vchordrq.max_scan_tuples` set,
+    // since the bound to beat is the distance of the last wanted candidate; incremental
+    // scans read lists as they are, so the order is kept but not used
+    #[serde(default = "VchordrqIndexOptions::default_sort_lists")]
+    pub sort_lists: bool,
 }
 
 impl VchordrqIndexOptions {
@@ -19,6 +30,37 @@ impl VchordrqIndexOptions {
     fn default_rerank_in_table() -> bool {
         false
     }
+    fn default_code_alignment() -> u16 {
+        // the width of the widest load of fast scan, so that codes are loaded by aligned loads
+        simd::fast_scan::ALIGNMENT as u16
+    }
+    fn default_sort_lists() -> bool {
+        false
+    }
+    pub fn validate_self(&self) -> Result<(), ValidationError> {
+        if !self.code_alignment.is_power_of_two() {
+            return Err(ValidationError::new(
+                "code_alignment must be a power of two",
+            ));
+        }
+        if self.sort_lists && !self.residual_quantization {
+            return Err(ValidationError::new(
+                "sort_lists requires residual_quantization",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for VchordrqIndexOptions {
+    fn default() -> Self {
+        Self {
+            residual_quantization: Self::default_residual_quantization(),
+            rerank_in_table: Self::default_rerank_in_table(),
+            code_alignment: Self::default_code_alignment(),
+            sort_lists: Self::default_sort_lists(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
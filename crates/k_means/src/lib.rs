@@ -39,13 +39,7 @@ pub fn k_means(
             .build_scoped(
                 |thread| thread.run(),
                 move |pool| {
-                    let compute = |centroids: &[Vec<f32>]| {
-                        if n >= 1024 && c >= 1024 {
-                            rabitq_index(dims, n, c, samples, centroids)
-                        } else {
-                            flat_index(dims, n, c, samples, centroids)
-                        }
-                    };
+                    let compute = |centroids: &[Vec<f32>]| assign(dims, c, samples, centroids);
                     let mut lloyd_k_means =
                         pool.install(|| LloydKMeans::new(c, dims, samples, is_spherical, compute));
                     for i in 0..iterations {
@@ -61,6 +55,90 @@ pub fn k_means(
     }
 }
 
+// Lloyd's algorithm that reads samples in passes, so that samples are never held in memory.
+// `pass` visits all `n` samples in the same order each time. Samples of a pass are
+// buffered in chunks, which are assigned by all threads as `k_means` assigns samples.
+// Centroids are held in memory.
+#[allow(clippy::too_many_arguments)]
+pub fn k_means_by_passes(
+    num_threads: usize,
+    mut check: impl FnMut(usize),
+    c: usize,
+    dims: usize,
+    n: usize,
+    mut pass: impl FnMut(&mut dyn FnMut(usize, &[f32])),
+    is_spherical: bool,
+    iterations: usize,
+) -> Vec<Vec<f32>> {
+    assert!(c > 0);
+    assert!(dims > 0);
+    if n <= c {
+        let mut samples = Vec::with_capacity(n);
+        pass(&mut |_, sample| samples.push(sample.to_vec()));
+        return quick_centers(c, dims, samples, is_spherical);
+    }
+    let chunk = (PASS_BUFFER / (dims * size_of::<f32>())).max(1);
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build_scoped(
+            |thread| thread.run(),
+            move |pool| {
+                let mut rng = StdRng::from_seed([7; 32]);
+                let mut centroids = vec![Vec::new(); c];
+                let slots = rand::seq::index::sample(&mut rng, n, c)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(slot, index)| (index, slot))
+                    .collect::<std::collections::HashMap<_, _>>();
+                pass(&mut |i, sample| {
+                    if let Some(&slot) = slots.get(&i) {
+                        centroids[slot] = sample.to_vec();
+                    }
+                });
+                for i in 0..iterations {
+                    check(i);
+                    let mut sum = vec![vec![f32::zero(); dims]; c];
+                    let mut count = vec![0.0f32; c];
+                    let mut buffer = Vec::with_capacity(chunk);
+                    let mut flush = |buffer: &mut Vec<Vec<f32>>| {
+                        let targets = pool.install(|| assign(dims, c, buffer, &centroids));
+                        for (sample, target) in buffer.drain(..).zip(targets) {
+                            f32::vector_add_inplace(&mut sum[target], &sample);
+                            count[target] += 1.0;
+                        }
+                    };
+                    pass(&mut |_, sample| {
+                        buffer.push(sample.to_vec());
+                        if buffer.len() == chunk {
+                            flush(&mut buffer);
+                        }
+                    });
+                    flush(&mut buffer);
+                    centroids = pool.install(|| {
+                        (0..c)
+                            .into_par_iter()
+                            .map(|i| f32::vector_mul_scalar(&sum[i], 1.0 / count[i]))
+                            .collect()
+                    });
+                    split_empty_clusters(&mut centroids, &mut count, n, &mut rng);
+                    if is_spherical {
+                        pool.install(|| {
+                            (&mut centroids).into_par_iter().for_each(|centroid| {
+                                let l = f32::reduce_sum_of_x2(centroid).sqrt();
+                                f32::vector_mul_scalar_inplace(centroid, 1.0 / l);
+                            })
+                        });
+                    }
+                }
+                centroids
+            },
+        )
+        .expect("failed to build thread pool")
+}
+
+// bytes of samples buffered by `k_means_by_passes` before they are assigned
+const PASS_BUFFER: usize = 16 << 20;
+
 pub fn k_means_lookup(vector: &[f32], centroids: &[Vec<f32>]) -> usize {
     assert_ne!(centroids.len(), 0);
     let mut result = (f32::INFINITY, 0);
@@ -102,6 +180,15 @@ fn quick_centers(
     centroids
 }
 
+fn assign(dims: usize, c: usize, samples: &[Vec<f32>], centroids: &[Vec<f32>]) -> Vec<usize> {
+    let n = samples.len();
+    if n >= 1024 && c >= 1024 {
+        rabitq_index(dims, n, c, samples, centroids)
+    } else {
+        flat_index(dims, n, c, samples, centroids)
+    }
+}
+
 fn rabitq_index(
     dims: usize,
     n: usize,
@@ -285,25 +372,7 @@ impl<'a, F: Fn(&[Vec<f32>]) -> Vec<usize>> LloydKMeans<'a, F> {
             .map(|i| f32::vector_mul_scalar(&sum[i], 1.0 / count[i]))
             .collect::<Vec<_>>();
 
-        for i in 0..c {
-            if count[i] != 0.0f32 {
-                continue;
-            }
-            let mut o = 0;
-            loop {
-                let alpha = rand.random_range(0.0..1.0f32);
-                let beta = (count[o] - 1.0) / (n - c) as f32;
-                if alpha < beta {
-                    break;
-                }
-                o = (o + 1) % c;
-            }
-            centroids[i] = centroids[o].clone();
-            vector_mul_scalars_inplace(&mut centroids[i], [1.0 + DELTA, 1.0 - DELTA]);
-            vector_mul_scalars_inplace(&mut centroids[o], [1.0 - DELTA, 1.0 + DELTA]);
-            count[i] = count[o] / 2.0;
-            count[o] -= count[i];
-        }
+        split_empty_clusters(&mut centroids, &mut count, n, rand);
 
         if self.is_spherical {
             (&mut centroids).into_par_iter().for_each(|centroid| {
@@ -327,6 +396,35 @@ impl<'a, F: Fn(&[Vec<f32>]) -> Vec<usize>> LloydKMeans<'a, F> {
     }
 }
 
+// an empty cluster takes over half of a cluster, which is picked with probability by its size
+fn split_empty_clusters(
+    centroids: &mut [Vec<f32>],
+    count: &mut [f32],
+    n: usize,
+    rand: &mut StdRng,
+) {
+    let c = centroids.len();
+    for i in 0..c {
+        if count[i] != 0.0f32 {
+            continue;
+        }
+        let mut o = 0;
+        loop {
+            let alpha = rand.random_range(0.0..1.0f32);
+            let beta = (count[o] - 1.0) / (n - c) as f32;
+            if alpha < beta {
+                break;
+            }
+            o = (o + 1) % c;
+        }
+        centroids[i] = centroids[o].clone();
+        vector_mul_scalars_inplace(&mut centroids[i], [1.0 + DELTA, 1.0 - DELTA]);
+        vector_mul_scalars_inplace(&mut centroids[o], [1.0 - DELTA, 1.0 + DELTA]);
+        count[i] = count[o] / 2.0;
+        count[o] -= count[i];
+    }
+}
+
 fn vector_mul_scalars_inplace(this: &mut [f32], scalars: [f32; 2]) {
     let n: usize = this.len();
     for i in 0..n {
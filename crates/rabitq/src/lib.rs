@@ -52,19 +52,17 @@ pub fn code(dims: u32, vector: &[f32]) -> Code {
     let max_x1 = 1.0f32 / (dims as f32 - 1.0).sqrt();
     let factor_err = 2.0f32 * max_x1 * (x_x0 * x_x0 - dis_u * dis_u).sqrt();
     let factor_ip = -2.0f32 / fac_norm * x_x0;
-    let cnt_pos = vector
-        .iter()
-        .map(|x| x.is_sign_positive() as i32)
-        .sum::<i32>();
-    let cnt_neg = vector
-        .iter()
-        .map(|x| x.is_sign_negative() as i32)
-        .sum::<i32>();
-    let factor_ppc = factor_ip * (cnt_pos - cnt_neg) as f32;
-    let mut signs = Vec::new();
-    for i in 0..dims {
-        signs.push(vector[i as usize].is_sign_positive());
+    // signs and their counts are taken in one traversal, and every component is
+    // either positive or negative by its sign bit, so counts are unchanged
+    let mut signs = Vec::with_capacity(dims as usize);
+    let mut cnt_pos = 0_i32;
+    for &x in &vector[..dims as usize] {
+        let sign = x.is_sign_positive();
+        cnt_pos += sign as i32;
+        signs.push(sign);
     }
+    let cnt_neg = dims as i32 - cnt_pos;
+    let factor_ppc = factor_ip * (cnt_pos - cnt_neg) as f32;
     Code {
         dis_u_2: sum_of_x_2,
         factor_ppc,
@@ -74,6 +72,23 @@ pub fn code(dims: u32, vector: &[f32]) -> Code {
     }
 }
 
+// approximate the coded vector by its projection onto the quantized direction
+pub fn decode(dims: u32, dis_u_2: f32, factor_ip: f32, signs: &[bool]) -> Vec<f32> {
+    if dis_u_2 <= 0.0 || !factor_ip.is_normal() {
+        return vec![0.0; dims as usize];
+    }
+    let dis_u = dis_u_2.sqrt();
+    let fac_norm = (dims as f32).sqrt();
+    let x_x0 = -factor_ip * fac_norm / 2.0;
+    let x0 = dis_u / x_x0;
+    let scale = dis_u * x0 / fac_norm;
+    signs
+        .iter()
+        .take(dims as usize)
+        .map(|&sign| if sign { scale } else { -scale })
+        .collect()
+}
+
 pub fn preprocess(vector: &[f32]) -> (BlockLut, BinaryLut) {
     use simd::Floating;
     let dis_v_2 = f32::reduce_sum_of_x2(vector);
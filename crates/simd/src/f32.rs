@@ -1853,6 +1853,28 @@ mod reduce_sum_of_d2 {
         }
         d2
     }
+
+    #[test]
+    fn reduce_sum_of_d2_bounded_test() {
+        use crate::Floating;
+        use rand::Rng;
+        let mut rng = rand::rng();
+        for _ in 0..if cfg!(not(miri)) { 256 } else { 1 } {
+            let n = rng.random_range(1..4016);
+            let lhs = (0..n)
+                .map(|_| rng.random_range(-1.0..=1.0))
+                .collect::<Vec<_>>();
+            let rhs = (0..n)
+                .map(|_| rng.random_range(-1.0..=1.0))
+                .collect::<Vec<_>>();
+            let full = reduce_sum_of_d2(&lhs, &rhs);
+            let unbounded = f32::reduce_sum_of_d2_bounded(&lhs, &rhs, f32::INFINITY);
+            assert!(unbounded.is_some_and(|x| (x - full).abs() < 0.02));
+            assert_eq!(f32::reduce_sum_of_d2_bounded(&lhs, &rhs, full * 0.5), None);
+            let bounded = f32::reduce_sum_of_d2_bounded(&lhs, &rhs, full * 1.5);
+            assert!(bounded.is_some_and(|x| (x - full).abs() < 0.02));
+        }
+    }
 }
 
 mod reduce_sum_of_xy_sparse {
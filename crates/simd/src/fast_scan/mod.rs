@@ -432,7 +432,7 @@ mod scan {
 
     #[cfg(target_arch = "aarch64")]
     #[crate::target_cpu(enable = "a2")]
-    fn scan_a2(code: &[[u8; 16]], lut: &[[u8; 16]]) -> [u16; 32] {
+    pub(super) fn scan_a2(code: &[[u8; 16]], lut: &[[u8; 16]]) -> [u16; 32] {
         // bounds checking is not enforced by compiler, so check it manually
         assert_eq!(code.len(), lut.len());
         let n = code.len();
@@ -545,3 +545,368 @@ mod scan {
 pub fn scan(code: &[[u8; 16]], lut: &[[u8; 16]]) -> [u16; 32] {
     scan::scan(code, lut)
 }
+
+// the width of the widest load of codes, which is of `scan_v4`
+pub const ALIGNMENT: usize = 64;
+
+mod scan_aligned {
+    // codes are loaded by aligned loads, so `code` must be aligned to `ALIGNMENT`, while
+    // `lut` is still loaded by unaligned loads; loads of NEON need no alignment, so
+    // `scan_a2` is shared
+
+    #[cfg(target_arch = "aarch64")]
+    use super::scan::scan_a2;
+
+    #[inline]
+    #[cfg(target_arch = "x86_64")]
+    #[crate::target_cpu(enable = "v4")]
+    fn scan_v4(code: &[[u8; 16]], lut: &[[u8; 16]]) -> [u16; 32] {
+        // bounds checking is not enforced by compiler, so check it manually
+        assert_eq!(code.len(), lut.len());
+        // alignment checking is not enforced by compiler, so check it manually
+        assert_eq!(code.as_ptr() as usize % super::ALIGNMENT, 0);
+        let n = code.len();
+
+        use std::arch::x86_64::*;
+
+        #[inline]
+        #[crate::target_cpu(enable = "v4")]
+        fn combine2x2(x0x1: __m256i, y0y1: __m256i) -> __m256i {
+            let x1y0 = _mm256_permute2f128_si256(x0x1, y0y1, 0x21);
+            let x0y1 = _mm256_blend_epi32(x0x1, y0y1, 0xf0);
+            _mm256_add_epi16(x1y0, x0y1)
+        }
+
+        #[inline]
+        #[crate::target_cpu(enable = "v4")]
+        fn combine4x2(x0x1x2x3: __m512i, y0y1y2y3: __m512i) -> __m256i {
+            let x0x1 = _mm512_castsi512_si256(x0x1x2x3);
+            let x2x3 = _mm512_extracti64x4_epi64(x0x1x2x3, 1);
+            let y0y1 = _mm512_castsi512_si256(y0y1y2y3);
+            let y2y3 = _mm512_extracti64x4_epi64(y0y1y2y3, 1);
+            let x01y01 = combine2x2(x0x1, y0y1);
+            let x23y23 = combine2x2(x2x3, y2y3);
+            _mm256_add_epi16(x01y01, x23y23)
+        }
+
+        let mut accu_0 = _mm512_setzero_si512();
+        let mut accu_1 = _mm512_setzero_si512();
+        let mut accu_2 = _mm512_setzero_si512();
+        let mut accu_3 = _mm512_setzero_si512();
+
+        let mut i = 0_usize;
+        while i + 4 <= n {
+            let code = unsafe { _mm512_load_si512(code.as_ptr().add(i).cast()) };
+
+            let mask = _mm512_set1_epi8(0xf);
+            let clo = _mm512_and_si512(code, mask);
+            let chi = _mm512_and_si512(_mm512_srli_epi16(code, 4), mask);
+
+            let lut = unsafe { _mm512_loadu_si512(lut.as_ptr().add(i).cast()) };
+            let res_lo = _mm512_shuffle_epi8(lut, clo);
+            accu_0 = _mm512_add_epi16(accu_0, res_lo);
+            accu_1 = _mm512_add_epi16(accu_1, _mm512_srli_epi16(res_lo, 8));
+            let res_hi = _mm512_shuffle_epi8(lut, chi);
+            accu_2 = _mm512_add_epi16(accu_2, res_hi);
+            accu_3 = _mm512_add_epi16(accu_3, _mm512_srli_epi16(res_hi, 8));
+
+            i += 4;
+        }
+        if i + 2 <= n {
+            let code = unsafe { _mm256_load_si256(code.as_ptr().add(i).cast()) };
+
+            let mask = _mm256_set1_epi8(0xf);
+            let clo = _mm256_and_si256(code, mask);
+            let chi = _mm256_and_si256(_mm256_srli_epi16(code, 4), mask);
+
+            let lut = unsafe { _mm256_loadu_si256(lut.as_ptr().add(i).cast()) };
+            let res_lo = _mm512_zextsi256_si512(_mm256_shuffle_epi8(lut, clo));
+            accu_0 = _mm512_add_epi16(accu_0, res_lo);
+            accu_1 = _mm512_add_epi16(accu_1, _mm512_srli_epi16(res_lo, 8));
+            let res_hi = _mm512_zextsi256_si512(_mm256_shuffle_epi8(lut, chi));
+            accu_2 = _mm512_add_epi16(accu_2, res_hi);
+            accu_3 = _mm512_add_epi16(accu_3, _mm512_srli_epi16(res_hi, 8));
+
+            i += 2;
+        }
+        if i < n {
+            let code = unsafe { _mm_load_si128(code.as_ptr().add(i).cast()) };
+
+            let mask = _mm_set1_epi8(0xf);
+            let clo = _mm_and_si128(code, mask);
+            let chi = _mm_and_si128(_mm_srli_epi16(code, 4), mask);
+
+            let lut = unsafe { _mm_loadu_si128(lut.as_ptr().add(i).cast()) };
+            let res_lo = _mm512_zextsi128_si512(_mm_shuffle_epi8(lut, clo));
+            accu_0 = _mm512_add_epi16(accu_0, res_lo);
+            accu_1 = _mm512_add_epi16(accu_1, _mm512_srli_epi16(res_lo, 8));
+            let res_hi = _mm512_zextsi128_si512(_mm_shuffle_epi8(lut, chi));
+            accu_2 = _mm512_add_epi16(accu_2, res_hi);
+            accu_3 = _mm512_add_epi16(accu_3, _mm512_srli_epi16(res_hi, 8));
+
+            i += 1;
+        }
+        debug_assert_eq!(i, n);
+
+        let mut result = [0_u16; 32];
+
+        accu_0 = _mm512_sub_epi16(accu_0, _mm512_slli_epi16(accu_1, 8));
+        unsafe {
+            _mm256_storeu_si256(
+                result.as_mut_ptr().add(0).cast(),
+                combine4x2(accu_0, accu_1),
+            );
+        }
+
+        accu_2 = _mm512_sub_epi16(accu_2, _mm512_slli_epi16(accu_3, 8));
+        unsafe {
+            _mm256_storeu_si256(
+                result.as_mut_ptr().add(16).cast(),
+                combine4x2(accu_2, accu_3),
+            );
+        }
+
+        result
+    }
+
+    #[cfg(all(target_arch = "x86_64", test, not(miri)))]
+    #[test]
+    fn scan_v4_test() {
+        if !crate::is_cpu_detected!("v4") {
+            println!("test {} ... skipped (v4)", module_path!());
+            return;
+        }
+        for _ in 0..if cfg!(not(miri)) { 256 } else { 1 } {
+            for n in 90..110 {
+                let code = aligned(n);
+                let lut = (0..n)
+                    .map(|_| std::array::from_fn(|_| rand::random()))
+                    .collect::<Vec<[u8; 16]>>();
+                unsafe {
+                    assert_eq!(
+                        scan_v4(code.as_flattened(), &lut),
+                        fallback(code.as_flattened(), &lut)
+                    );
+                }
+            }
+        }
+    }
+
+    #[inline]
+    #[cfg(target_arch = "x86_64")]
+    #[crate::target_cpu(enable = "v3")]
+    fn scan_v3(code: &[[u8; 16]], lut: &[[u8; 16]]) -> [u16; 32] {
+        // bounds checking is not enforced by compiler, so check it manually
+        assert_eq!(code.len(), lut.len());
+        // alignment checking is not enforced by compiler, so check it manually
+        assert_eq!(code.as_ptr() as usize % super::ALIGNMENT, 0);
+        let n = code.len();
+
+        use std::arch::x86_64::*;
+
+        #[inline]
+        #[crate::target_cpu(enable = "v3")]
+        fn combine2x2(x0x1: __m256i, y0y1: __m256i) -> __m256i {
+            let x1y0 = _mm256_permute2f128_si256(x0x1, y0y1, 0x21);
+            let x0y1 = _mm256_blend_epi32(x0x1, y0y1, 0xf0);
+            _mm256_add_epi16(x1y0, x0y1)
+        }
+
+        let mut accu_0 = _mm256_setzero_si256();
+        let mut accu_1 = _mm256_setzero_si256();
+        let mut accu_2 = _mm256_setzero_si256();
+        let mut accu_3 = _mm256_setzero_si256();
+
+        let mut i = 0_usize;
+        while i + 2 <= n {
+            let code = unsafe { _mm256_load_si256(code.as_ptr().add(i).cast()) };
+
+            let mask = _mm256_set1_epi8(0xf);
+            let clo = _mm256_and_si256(code, mask);
+            let chi = _mm256_and_si256(_mm256_srli_epi16(code, 4), mask);
+
+            let lut = unsafe { _mm256_loadu_si256(lut.as_ptr().add(i).cast()) };
+            let res_lo = _mm256_shuffle_epi8(lut, clo);
+            accu_0 = _mm256_add_epi16(accu_0, res_lo);
+            accu_1 = _mm256_add_epi16(accu_1, _mm256_srli_epi16(res_lo, 8));
+            let res_hi = _mm256_shuffle_epi8(lut, chi);
+            accu_2 = _mm256_add_epi16(accu_2, res_hi);
+            accu_3 = _mm256_add_epi16(accu_3, _mm256_srli_epi16(res_hi, 8));
+
+            i += 2;
+        }
+        if i < n {
+            let code = unsafe { _mm_load_si128(code.as_ptr().add(i).cast()) };
+
+            let mask = _mm_set1_epi8(0xf);
+            let clo = _mm_and_si128(code, mask);
+            let chi = _mm_and_si128(_mm_srli_epi16(code, 4), mask);
+
+            let lut = unsafe { _mm_loadu_si128(lut.as_ptr().add(i).cast()) };
+            let res_lo = _mm256_zextsi128_si256(_mm_shuffle_epi8(lut, clo));
+            accu_0 = _mm256_add_epi16(accu_0, res_lo);
+            accu_1 = _mm256_add_epi16(accu_1, _mm256_srli_epi16(res_lo, 8));
+            let res_hi = _mm256_zextsi128_si256(_mm_shuffle_epi8(lut, chi));
+            accu_2 = _mm256_add_epi16(accu_2, res_hi);
+            accu_3 = _mm256_add_epi16(accu_3, _mm256_srli_epi16(res_hi, 8));
+
+            i += 1;
+        }
+        debug_assert_eq!(i, n);
+
+        let mut result = [0_u16; 32];
+
+        accu_0 = _mm256_sub_epi16(accu_0, _mm256_slli_epi16(accu_1, 8));
+        unsafe {
+            _mm256_storeu_si256(
+                result.as_mut_ptr().add(0).cast(),
+                combine2x2(accu_0, accu_1),
+            );
+        }
+
+        accu_2 = _mm256_sub_epi16(accu_2, _mm256_slli_epi16(accu_3, 8));
+        unsafe {
+            _mm256_storeu_si256(
+                result.as_mut_ptr().add(16).cast(),
+                combine2x2(accu_2, accu_3),
+            );
+        }
+
+        result
+    }
+
+    #[cfg(all(target_arch = "x86_64", test, not(miri)))]
+    #[test]
+    fn scan_v3_test() {
+        if !crate::is_cpu_detected!("v3") {
+            println!("test {} ... skipped (v3)", module_path!());
+            return;
+        }
+        for _ in 0..if cfg!(not(miri)) { 256 } else { 1 } {
+            for n in 90..110 {
+                let code = aligned(n);
+                let lut = (0..n)
+                    .map(|_| std::array::from_fn(|_| rand::random()))
+                    .collect::<Vec<[u8; 16]>>();
+                unsafe {
+                    assert_eq!(
+                        scan_v3(code.as_flattened(), &lut),
+                        fallback(code.as_flattened(), &lut)
+                    );
+                }
+            }
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[crate::target_cpu(enable = "v2")]
+    fn scan_v2(code: &[[u8; 16]], lut: &[[u8; 16]]) -> [u16; 32] {
+        // bounds checking is not enforced by compiler, so check it manually
+        assert_eq!(code.len(), lut.len());
+        // alignment checking is not enforced by compiler, so check it manually
+        assert_eq!(code.as_ptr() as usize % super::ALIGNMENT, 0);
+        let n = code.len();
+
+        use std::arch::x86_64::*;
+
+        let mut accu_0 = _mm_setzero_si128();
+        let mut accu_1 = _mm_setzero_si128();
+        let mut accu_2 = _mm_setzero_si128();
+        let mut accu_3 = _mm_setzero_si128();
+
+        let mut i = 0_usize;
+        while i < n {
+            let code = unsafe { _mm_load_si128(code.as_ptr().add(i).cast()) };
+
+            let mask = _mm_set1_epi8(0xf);
+            let clo = _mm_and_si128(code, mask);
+            let chi = _mm_and_si128(_mm_srli_epi16(code, 4), mask);
+
+            let lut = unsafe { _mm_loadu_si128(lut.as_ptr().add(i).cast()) };
+            let res_lo = _mm_shuffle_epi8(lut, clo);
+            accu_0 = _mm_add_epi16(accu_0, res_lo);
+            accu_1 = _mm_add_epi16(accu_1, _mm_srli_epi16(res_lo, 8));
+            let res_hi = _mm_shuffle_epi8(lut, chi);
+            accu_2 = _mm_add_epi16(accu_2, res_hi);
+            accu_3 = _mm_add_epi16(accu_3, _mm_srli_epi16(res_hi, 8));
+
+            i += 1;
+        }
+        debug_assert_eq!(i, n);
+
+        let mut result = [0_u16; 32];
+
+        accu_0 = _mm_sub_epi16(accu_0, _mm_slli_epi16(accu_1, 8));
+        unsafe {
+            _mm_storeu_si128(result.as_mut_ptr().add(0).cast(), accu_0);
+            _mm_storeu_si128(result.as_mut_ptr().add(8).cast(), accu_1);
+        }
+
+        accu_2 = _mm_sub_epi16(accu_2, _mm_slli_epi16(accu_3, 8));
+        unsafe {
+            _mm_storeu_si128(result.as_mut_ptr().add(16).cast(), accu_2);
+            _mm_storeu_si128(result.as_mut_ptr().add(24).cast(), accu_3);
+        }
+
+        result
+    }
+
+    #[cfg(all(target_arch = "x86_64", test, not(miri)))]
+    #[test]
+    fn scan_v2_test() {
+        if !crate::is_cpu_detected!("v2") {
+            println!("test {} ... skipped (v2)", module_path!());
+            return;
+        }
+        for _ in 0..if cfg!(not(miri)) { 256 } else { 1 } {
+            for n in 90..110 {
+                let code = aligned(n);
+                let lut = (0..n)
+                    .map(|_| std::array::from_fn(|_| rand::random()))
+                    .collect::<Vec<[u8; 16]>>();
+                unsafe {
+                    assert_eq!(
+                        scan_v2(code.as_flattened(), &lut),
+                        fallback(code.as_flattened(), &lut)
+                    );
+                }
+            }
+        }
+    }
+
+    // `n` random codes, which are aligned to `ALIGNMENT`
+    #[cfg(all(target_arch = "x86_64", test, not(miri)))]
+    fn aligned(n: usize) -> AlignedCodes {
+        let mut code = vec![Aligned([[0_u8; 16]; 4]); n.div_ceil(4)];
+        for x in code.iter_mut() {
+            x.0 = std::array::from_fn(|_| std::array::from_fn(|_| rand::random()));
+        }
+        AlignedCodes(code, n)
+    }
+
+    #[cfg(all(target_arch = "x86_64", test, not(miri)))]
+    #[derive(Clone, Copy)]
+    #[repr(C, align(64))]
+    struct Aligned([[u8; 16]; 4]);
+
+    #[cfg(all(target_arch = "x86_64", test, not(miri)))]
+    struct AlignedCodes(Vec<Aligned>, usize);
+
+    #[cfg(all(target_arch = "x86_64", test, not(miri)))]
+    impl AlignedCodes {
+        fn as_flattened(&self) -> &[[u8; 16]] {
+            unsafe { std::slice::from_raw_parts(self.0.as_ptr().cast(), self.1) }
+        }
+    }
+
+    #[crate::multiversion(@"v4", @"v3", @"v2", @"a2")]
+    pub fn scan(code: &[[u8; 16]], lut: &[[u8; 16]]) -> [u16; 32] {
+        super::scan::scan(code, lut)
+    }
+}
+
+// Codes are loaded by aligned loads, so they must be aligned to `ALIGNMENT`.
+#[inline(always)]
+pub fn scan_aligned(code: &[[u8; 16]], lut: &[[u8; 16]]) -> [u16; 32] {
+    scan_aligned::scan(code, lut)
+}
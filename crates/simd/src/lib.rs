@@ -32,6 +32,19 @@ pub trait Floating:
     fn reduce_min_max_of_x(this: &[Self]) -> (f32, f32);
     fn reduce_sum_of_xy(lhs: &[Self], rhs: &[Self]) -> f32;
     fn reduce_sum_of_d2(lhs: &[Self], rhs: &[Self]) -> f32;
+    // returns `None` as soon as the partial sum exceeds `bound`
+    fn reduce_sum_of_d2_bounded(lhs: &[Self], rhs: &[Self], bound: f32) -> Option<f32> {
+        const CHUNK: usize = 256;
+        assert!(lhs.len() == rhs.len());
+        let mut d2 = 0.0f32;
+        for (lhs, rhs) in std::iter::zip(lhs.chunks(CHUNK), rhs.chunks(CHUNK)) {
+            d2 += Self::reduce_sum_of_d2(lhs, rhs);
+            if d2 > bound {
+                return None;
+            }
+        }
+        Some(d2)
+    }
     fn reduce_sum_of_xy_sparse(lidx: &[u32], lval: &[Self], ridx: &[u32], rval: &[Self]) -> f32;
     fn reduce_sum_of_d2_sparse(lidx: &[u32], lval: &[Self], ridx: &[u32], rval: &[Self]) -> f32;
 
@@ -1,4 +1,5 @@
 use super::memory_scalar8::{Scalar8Input, Scalar8Output};
+use super::typmod::check_typmod;
 use pgrx::datum::Internal;
 use pgrx::pg_sys::Oid;
 use vector::VectorBorrowed;
@@ -21,7 +22,7 @@ fn _vchord_scalar8_send(vector: Scalar8Input<'_>) -> Vec<u8> {
 
 #[pgrx::pg_extern(immutable, strict, parallel_safe)]
 fn _vchord_scalar8_recv(internal: Internal, oid: Oid, typmod: i32) -> Scalar8Output {
-    let _ = (oid, typmod);
+    let _ = oid;
     let buf = unsafe { internal.get_mut::<pgrx::pg_sys::StringInfoData>().unwrap() };
 
     let dims = {
@@ -30,6 +31,9 @@ fn _vchord_scalar8_recv(internal: Internal, oid: Oid, typmod: i32) -> Scalar8Out
         buf.cursor += 4;
         u32::from_be_bytes(raw)
     };
+    if dims == 0 {
+        pgrx::error!("vector must have at least 1 dimension");
+    }
     let sum_of_x2 = {
         assert!(buf.cursor < i32::MAX - 4 && buf.cursor + 4 <= buf.len);
         let raw = unsafe { buf.data.add(buf.cursor as _).cast::<[u8; 4]>().read() };
@@ -67,6 +71,7 @@ fn _vchord_scalar8_recv(internal: Internal, oid: Oid, typmod: i32) -> Scalar8Out
         result
     };
 
+    check_typmod(typmod, code.len() as u32);
     if let Some(x) = Scalar8Borrowed::new_checked(sum_of_x2, k, b, sum_of_code, &code) {
         Scalar8Output::new(x)
     } else {
@@ -0,0 +1,376 @@
+use crate::datatype::memory_halfvec::{HalfvecInput, HalfvecOutput};
+use crate::datatype::memory_vector::{VectorInput, VectorOutput};
+use crate::datatype::memory_vector_array::VectorArrayInput;
+use half::f16;
+use simd::Floating;
+use vector::VectorBorrowed;
+use vector::vect::VectBorrowed;
+
+// tolerance of `|‖v‖² - 1|` for a vector to be considered normalized
+const NORMALIZED_EPSILON: f64 = 1e-4;
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_vector_validate(
+    vector: VectorInput,
+    expected_dims: i32,
+    require_finite: bool,
+    require_normalized: bool,
+) -> bool {
+    validate(
+        vector.as_borrowed().slice(),
+        expected_dims,
+        require_finite,
+        require_normalized,
+    );
+    true
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_halfvec_validate(
+    vector: HalfvecInput,
+    expected_dims: i32,
+    require_finite: bool,
+    require_normalized: bool,
+) -> bool {
+    validate(
+        vector.as_borrowed().slice(),
+        expected_dims,
+        require_finite,
+        require_normalized,
+    );
+    true
+}
+
+fn validate<S: Floating>(
+    slice: &[S],
+    expected_dims: i32,
+    require_finite: bool,
+    require_normalized: bool,
+) {
+    if expected_dims <= 0 {
+        pgrx::error!("expected dimensions must be positive, but got {expected_dims}");
+    }
+    if slice.len() != expected_dims as usize {
+        pgrx::error!(
+            "vector has {} dimensions, but {} dimensions are expected",
+            slice.len(),
+            expected_dims
+        );
+    }
+    let elements = S::vector_to_f32_borrowed(slice);
+    let elements = elements.as_ref();
+    if require_finite {
+        if let Some(i) = elements.iter().position(|x| !x.is_finite()) {
+            pgrx::error!(
+                "vector has a non-finite element {} at position {}",
+                elements[i],
+                i + 1
+            );
+        }
+        // distances are computed in f32, so they overflow along with the squared norm
+        if !elements.iter().map(|&x| x * x).sum::<f32>().is_finite() {
+            pgrx::error!("vector has a squared norm that overflows, so its distances are infinite");
+        }
+    }
+    if require_normalized {
+        let norm_2 = elements.iter().map(|&x| x as f64 * x as f64).sum::<f64>();
+        if norm_2.is_nan() || (norm_2 - 1.0).abs() > NORMALIZED_EPSILON {
+            pgrx::error!("vector is not normalized, its norm is {}", norm_2.sqrt());
+        }
+    }
+}
+
+// `t` outside `[0, 1]` extrapolates along the line through `a` and `b`
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_vector_lerp(a: VectorInput<'_>, b: VectorInput<'_>, t: f64) -> VectorOutput {
+    let a = a.as_borrowed();
+    let b = b.as_borrowed();
+    if a.dims() != b.dims() {
+        pgrx::error!("dimension is not matched");
+    }
+    if !t.is_finite() {
+        pgrx::error!("interpolation parameter must be finite, but got {t}");
+    }
+    let t = t as f32;
+    let result = std::iter::zip(a.slice(), b.slice())
+        .map(|(&x, &y)| x + t * (y - x))
+        .collect::<Vec<_>>();
+    if let Some(i) = result.iter().position(|x| !x.is_finite()) {
+        pgrx::error!(
+            "interpolated vector has a non-finite element at position {}",
+            i + 1
+        );
+    }
+    VectorOutput::new(VectBorrowed::new(&result))
+}
+
+// `m` is a `dims` by `dims` matrix in row-major order, and the result is `xᵀM`, so
+// `vector_transform(x, m) <#> y` is `-xᵀMy`. Indexing the expression stores
+// transformed vectors, so an insert pays the `O(dims²)` transform once, and a
+// scan pays nothing other than an inner product search. A query ordered by the
+// expression recomputes it for each row that is not served by the index.
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_vector_transform(x: VectorInput<'_>, m: pgrx::Array<'_, f32>) -> VectorOutput {
+    let x = x.as_borrowed().slice();
+    let dims = x.len();
+    if m.contains_nulls() {
+        pgrx::error!("matrix must not contain nulls");
+    }
+    let m = m.iter_deny_null().collect::<Vec<_>>();
+    if m.len() != dims * dims {
+        pgrx::error!(
+            "matrix has {} elements, but a {dims} by {dims} matrix is expected",
+            m.len()
+        );
+    }
+    let mut result = vec![0.0f32; dims];
+    for (&x, row) in std::iter::zip(x, m.chunks_exact(dims)) {
+        for (r, &y) in std::iter::zip(&mut result, row) {
+            *r += x * y;
+        }
+    }
+    if let Some(i) = result.iter().position(|x| !x.is_finite()) {
+        pgrx::error!(
+            "transformed vector has a non-finite element at position {}",
+            i + 1
+        );
+    }
+    VectorOutput::new(VectBorrowed::new(&result))
+}
+
+// The inverse of concatenation. Postgres doesn't keep type modifiers of results
+// of functions, so slices are cast to `vector(chunk_dim)` if a type modifier is
+// needed.
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_vector_unchunk(
+    v: VectorInput<'_>,
+    chunk_dim: i32,
+) -> pgrx::iter::SetOfIterator<'static, VectorOutput> {
+    let v = v.as_borrowed().slice();
+    if chunk_dim <= 0 {
+        pgrx::error!("chunk_dim must be positive");
+    }
+    let chunk_dim = chunk_dim as usize;
+    if v.len() % chunk_dim != 0 {
+        pgrx::error!(
+            "the vector has {} dimensions, which is not a multiple of {chunk_dim}",
+            v.len()
+        );
+    }
+    let chunks = v
+        .chunks_exact(chunk_dim)
+        .map(|chunk| VectorOutput::new(VectBorrowed::new(chunk)))
+        .collect::<Vec<_>>();
+    pgrx::iter::SetOfIterator::new(chunks)
+}
+
+// states of halfvec aggregates are `{count, sum_1, ..., sum_n}`, accumulated in double precision
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_vector_array_mean(array: VectorArrayInput<'_>) -> VectorOutput {
+    let mut sum = vec![0.0f64; array.dims() as usize];
+    let mut count = 0_u64;
+    for vector in array.iter() {
+        for (s, &x) in sum.iter_mut().zip(vector.slice()) {
+            *s += x as f64;
+        }
+        count += 1;
+    }
+    if count == 0 {
+        pgrx::error!("the vector array is empty");
+    }
+    let mean = sum
+        .into_iter()
+        .map(|s| (s / count as f64) as f32)
+        .collect::<Vec<_>>();
+    VectorOutput::new(VectBorrowed::new(&mean))
+}
+
+// The population variance, which is computed in two passes for stability.
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_vector_dimension_variance(sample: pgrx::Array<'_, VectorInput<'_>>) -> VectorOutput {
+    if sample.contains_nulls() {
+        pgrx::error!("sample must not contain nulls");
+    }
+    let sample = sample.iter_deny_null().collect::<Vec<_>>();
+    let Some(first) = sample.first() else {
+        pgrx::error!("sample must not be empty");
+    };
+    let dims = first.as_borrowed().dims();
+    if sample.iter().any(|x| x.as_borrowed().dims() != dims) {
+        pgrx::error!("dimension is not matched");
+    }
+    let n = sample.len() as f64;
+    let mut mean = vec![0.0f64; dims as usize];
+    for vector in sample.iter() {
+        for (m, &x) in mean.iter_mut().zip(vector.as_borrowed().slice()) {
+            *m += x as f64;
+        }
+    }
+    mean.iter_mut().for_each(|m| *m /= n);
+    let mut variance = vec![0.0f64; dims as usize];
+    for vector in sample.iter() {
+        for ((v, m), &x) in variance
+            .iter_mut()
+            .zip(&mean)
+            .zip(vector.as_borrowed().slice())
+        {
+            *v += (x as f64 - m) * (x as f64 - m);
+        }
+    }
+    let variance = variance
+        .into_iter()
+        .map(|v| (v / n) as f32)
+        .collect::<Vec<_>>();
+    VectorOutput::new(VectBorrowed::new(&variance))
+}
+
+// The two-nearest-neighbor estimator: for a point, the ratio of the distances to its
+// second and first nearest neighbors follows a Pareto distribution whose shape is
+// the intrinsic dimension, which is estimated by maximum likelihood. Neighbors are
+// found by brute force, and points with a duplicate are skipped.
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_vector_intrinsic_dim(sample: pgrx::Array<'_, VectorInput<'_>>) -> f64 {
+    if sample.contains_nulls() {
+        pgrx::error!("sample must not contain nulls");
+    }
+    let sample = sample.iter_deny_null().collect::<Vec<_>>();
+    if sample.len() < 3 {
+        pgrx::error!("sample must have at least 3 vectors");
+    }
+    let dims = sample[0].as_borrowed().dims();
+    if sample.iter().any(|x| x.as_borrowed().dims() != dims) {
+        pgrx::error!("dimension is not matched");
+    }
+    let mut n = 0_u64;
+    let mut sum = 0.0_f64;
+    for (i, x) in sample.iter().enumerate() {
+        pgrx::check_for_interrupts!();
+        let x = x.as_borrowed().slice();
+        let (mut r1, mut r2) = (f32::INFINITY, f32::INFINITY);
+        for (j, y) in sample.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+            let d = f32::reduce_sum_of_d2(x, y.as_borrowed().slice());
+            if d < r1 {
+                (r1, r2) = (d, r1);
+            } else if d < r2 {
+                r2 = d;
+            }
+        }
+        if r1 > 0.0 {
+            n += 1;
+            // distances are squared, so the logarithm is halved
+            sum += 0.5 * (r2 as f64 / r1 as f64).ln();
+        }
+    }
+    if sum == 0.0 {
+        pgrx::error!(
+            "intrinsic dimension cannot be estimated, since the sample has too many duplicates"
+        );
+    }
+    n as f64 / sum
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_halfvec_accum(state: pgrx::Array<'_, f64>, value: HalfvecInput<'_>) -> Vec<f64> {
+    let mut state = state.iter_deny_null().collect::<Vec<_>>();
+    let value = value.as_borrowed();
+    if state.len() == 1 {
+        state.resize(1 + value.dims() as usize, 0.0);
+    } else if state.len() != 1 + value.dims() as usize {
+        pgrx::error!("dimension is not matched");
+    }
+    state[0] += 1.0;
+    for (s, x) in std::iter::zip(&mut state[1..], value.slice()) {
+        *s += x.to_f32() as f64;
+    }
+    state
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_halfvec_combine(lhs: pgrx::Array<'_, f64>, rhs: pgrx::Array<'_, f64>) -> Vec<f64> {
+    combine(lhs, rhs)
+}
+
+// a state of length 1 has no vector accumulated yet
+fn combine(lhs: pgrx::Array<'_, f64>, rhs: pgrx::Array<'_, f64>) -> Vec<f64> {
+    let mut lhs = lhs.iter_deny_null().collect::<Vec<_>>();
+    let rhs = rhs.iter_deny_null().collect::<Vec<_>>();
+    if lhs.is_empty() || rhs.is_empty() {
+        pgrx::error!("state must not be empty");
+    }
+    if lhs.len() == 1 {
+        return rhs;
+    }
+    if rhs.len() == 1 {
+        return lhs;
+    }
+    if lhs.len() != rhs.len() {
+        pgrx::error!("dimension is not matched");
+    }
+    for (l, r) in std::iter::zip(&mut lhs, rhs) {
+        *l += r;
+    }
+    lhs
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_halfvec_sum(state: pgrx::Array<'_, f64>) -> Option<HalfvecOutput> {
+    let state = state.iter_deny_null().collect::<Vec<_>>();
+    if state[0] == 0.0 {
+        return None;
+    }
+    Some(narrow(state[1..].iter().copied()))
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_halfvec_avg(state: pgrx::Array<'_, f64>) -> Option<HalfvecOutput> {
+    let state = state.iter_deny_null().collect::<Vec<_>>();
+    if state[0] == 0.0 {
+        return None;
+    }
+    let count = state[0];
+    Some(narrow(state[1..].iter().map(|&x| x / count)))
+}
+
+// weighted states are `{weight, weighted_sum_1, ..., weighted_sum_n}`
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_vector_accum(
+    state: pgrx::Array<'_, f64>,
+    value: VectorInput<'_>,
+    weight: f64,
+) -> Vec<f64> {
+    let mut state = state.iter_deny_null().collect::<Vec<_>>();
+    let value = value.as_borrowed();
+    if state.is_empty() {
+        pgrx::error!("state must not be empty");
+    }
+    if !weight.is_finite() {
+        pgrx::error!("weight must be finite");
+    }
+    if state.len() == 1 {
+        state.resize(1 + value.dims() as usize, 0.0);
+    } else if state.len() != 1 + value.dims() as usize {
+        pgrx::error!("dimension is not matched");
+    }
+    state[0] += weight;
+    for (s, x) in std::iter::zip(&mut state[1..], value.slice()) {
+        *s += weight * *x as f64;
+    }
+    state
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_vector_combine(lhs: pgrx::Array<'_, f64>, rhs: pgrx::Array<'_, f64>) -> Vec<f64> {
+    combine(lhs, rhs)
+}
+
+// saturates instead of overflowing to infinity
+fn narrow(elements: impl Iterator<Item = f64>) -> HalfvecOutput {
+    let max = f16::MAX.to_f64();
+    let result = elements
+        .map(|x| f16::from_f64(x.clamp(-max, max)))
+        .collect::<Vec<_>>();
+    HalfvecOutput::new(VectBorrowed::new(&result))
+}
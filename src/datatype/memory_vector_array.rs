@@ -0,0 +1,211 @@
+use pgrx::datum::{FromDatum, IntoDatum};
+use pgrx::pg_sys::{Datum, Oid};
+use pgrx::pgrx_sql_entity_graph::metadata::*;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use vector::vect::VectBorrowed;
+
+// Vectors are stored one after another, and all of them have `dims` dimensions.
+#[repr(C, align(8))]
+struct VectorArrayHeader {
+    varlena: u32,
+    dims: u16,
+    unused: u16,
+    count: u32,
+    elements: [f32; 0],
+}
+
+impl VectorArrayHeader {
+    fn size_of(dims: usize, count: usize) -> usize {
+        if dims > 65535 {
+            panic!("vector is too large");
+        }
+        let Some(len) = dims.checked_mul(count).filter(|&len| len <= (1 << 28)) else {
+            panic!("vector array is too large");
+        };
+        (size_of::<Self>() + size_of::<f32>() * len).next_multiple_of(8)
+    }
+    unsafe fn as_borrowed<'a>(this: NonNull<Self>) -> (u32, &'a [f32]) {
+        unsafe {
+            let this = this.as_ptr();
+            let dims = (&raw const (*this).dims).read() as usize;
+            let count = (&raw const (*this).count).read() as usize;
+            (
+                dims as u32,
+                std::slice::from_raw_parts((&raw const (*this).elements).cast(), dims * count),
+            )
+        }
+    }
+}
+
+pub struct VectorArrayInput<'a>(NonNull<VectorArrayHeader>, PhantomData<&'a ()>, bool);
+
+impl VectorArrayInput<'_> {
+    unsafe fn from_ptr(p: NonNull<VectorArrayHeader>) -> Self {
+        let q = unsafe {
+            NonNull::new(pgrx::pg_sys::pg_detoast_datum(p.as_ptr().cast()).cast()).unwrap()
+        };
+        VectorArrayInput(q, PhantomData, p != q)
+    }
+    pub fn dims(&self) -> u32 {
+        unsafe { VectorArrayHeader::as_borrowed(self.0).0 }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = VectBorrowed<'_, f32>> {
+        let (dims, elements) = unsafe { VectorArrayHeader::as_borrowed(self.0) };
+        elements.chunks_exact(dims as usize).map(VectBorrowed::new)
+    }
+}
+
+impl Drop for VectorArrayInput<'_> {
+    fn drop(&mut self) {
+        if self.2 {
+            unsafe {
+                pgrx::pg_sys::pfree(self.0.as_ptr().cast());
+            }
+        }
+    }
+}
+
+pub struct VectorArrayOutput(NonNull<VectorArrayHeader>);
+
+impl VectorArrayOutput {
+    unsafe fn from_ptr(p: NonNull<VectorArrayHeader>) -> Self {
+        let q = unsafe {
+            NonNull::new(pgrx::pg_sys::pg_detoast_datum_copy(p.as_ptr().cast()).cast()).unwrap()
+        };
+        Self(q)
+    }
+    pub fn new(dims: u32, elements: &[f32]) -> Self {
+        assert!(dims != 0 && elements.len() % dims as usize == 0);
+        unsafe {
+            let count = elements.len() / dims as usize;
+            let size = VectorArrayHeader::size_of(dims as _, count);
+
+            let ptr = pgrx::pg_sys::palloc0(size) as *mut VectorArrayHeader;
+            (&raw mut (*ptr).varlena).write((size << 2) as u32);
+            (&raw mut (*ptr).dims).write(dims as _);
+            (&raw mut (*ptr).unused).write(0);
+            (&raw mut (*ptr).count).write(count as _);
+            std::ptr::copy_nonoverlapping(
+                elements.as_ptr(),
+                (&raw mut (*ptr).elements).cast(),
+                elements.len(),
+            );
+            Self(NonNull::new(ptr).unwrap())
+        }
+    }
+    fn into_raw(self) -> *mut VectorArrayHeader {
+        let result = self.0.as_ptr();
+        std::mem::forget(self);
+        result
+    }
+}
+
+impl Drop for VectorArrayOutput {
+    fn drop(&mut self) {
+        unsafe {
+            pgrx::pg_sys::pfree(self.0.as_ptr().cast());
+        }
+    }
+}
+
+// FromDatum
+
+impl FromDatum for VectorArrayInput<'_> {
+    unsafe fn from_polymorphic_datum(datum: Datum, is_null: bool, _typoid: Oid) -> Option<Self> {
+        if is_null {
+            None
+        } else {
+            let ptr = NonNull::new(datum.cast_mut_ptr()).unwrap();
+            unsafe { Some(Self::from_ptr(ptr)) }
+        }
+    }
+}
+
+impl FromDatum for VectorArrayOutput {
+    unsafe fn from_polymorphic_datum(datum: Datum, is_null: bool, _typoid: Oid) -> Option<Self> {
+        if is_null {
+            None
+        } else {
+            let ptr = NonNull::new(datum.cast_mut_ptr()).unwrap();
+            unsafe { Some(Self::from_ptr(ptr)) }
+        }
+    }
+}
+
+// IntoDatum
+
+impl IntoDatum for VectorArrayOutput {
+    fn into_datum(self) -> Option<Datum> {
+        Some(Datum::from(self.into_raw()))
+    }
+
+    fn type_oid() -> Oid {
+        Oid::INVALID
+    }
+
+    fn is_compatible_with(_: Oid) -> bool {
+        true
+    }
+}
+
+// UnboxDatum
+
+unsafe impl pgrx::datum::UnboxDatum for VectorArrayOutput {
+    type As<'src> = VectorArrayOutput;
+    #[inline]
+    unsafe fn unbox<'src>(datum: pgrx::datum::Datum<'src>) -> Self::As<'src>
+    where
+        Self: 'src,
+    {
+        let datum = datum.sans_lifetime();
+        let ptr = NonNull::new(datum.cast_mut_ptr()).unwrap();
+        unsafe { Self::from_ptr(ptr) }
+    }
+}
+
+// SqlTranslatable
+
+unsafe impl SqlTranslatable for VectorArrayInput<'_> {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::As(String::from("vector_array")))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::As(String::from("vector_array"))))
+    }
+}
+
+unsafe impl SqlTranslatable for VectorArrayOutput {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::As(String::from("vector_array")))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::As(String::from("vector_array"))))
+    }
+}
+
+// ArgAbi
+
+unsafe impl<'fcx> pgrx::callconv::ArgAbi<'fcx> for VectorArrayInput<'fcx> {
+    unsafe fn unbox_arg_unchecked(arg: pgrx::callconv::Arg<'_, 'fcx>) -> Self {
+        let index = arg.index();
+        unsafe {
+            arg.unbox_arg_using_from_datum()
+                .unwrap_or_else(|| panic!("argument {index} must not be null"))
+        }
+    }
+}
+
+// BoxRet
+
+unsafe impl pgrx::callconv::BoxRet for VectorArrayOutput {
+    unsafe fn box_into<'fcx>(
+        self,
+        fcinfo: &mut pgrx::callconv::FcInfo<'fcx>,
+    ) -> pgrx::datum::Datum<'fcx> {
+        match self.into_datum() {
+            Some(datum) => unsafe { fcinfo.return_raw_datum(datum) },
+            None => fcinfo.return_null(),
+        }
+    }
+}
@@ -1,10 +1,13 @@
 pub mod binary_scalar8;
 pub mod functions_scalar8;
+pub mod functions_vector;
 pub mod memory_halfvec;
 pub mod memory_scalar8;
 pub mod memory_vector;
+pub mod memory_vector_array;
 pub mod operators_halfvec;
 pub mod operators_scalar8;
 pub mod operators_vector;
 pub mod text_scalar8;
+pub mod text_vector_array;
 pub mod typmod;
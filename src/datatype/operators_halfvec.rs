@@ -1,4 +1,5 @@
 use crate::datatype::memory_halfvec::{HalfvecInput, HalfvecOutput};
+use crate::datatype::memory_vector::VectorInput;
 use pgrx::Array;
 use std::num::NonZero;
 use vector::VectorBorrowed;
@@ -93,3 +94,38 @@ fn _vchord_halfvec_operator_maxsim(
     }
     maxsim
 }
+
+#[pgrx::pg_extern(immutable, strict, parallel_safe)]
+fn _vchord_halfvec_vector_operator_cosine(lhs: HalfvecInput<'_>, rhs: VectorInput<'_>) -> f64 {
+    let lhs = lhs.as_borrowed();
+    let rhs = rhs.as_borrowed();
+    if lhs.dims() != rhs.dims() {
+        pgrx::error!("dimension is not matched");
+    }
+    let lhs = lhs.slice().iter().map(|x| x.to_f32()).collect::<Vec<_>>();
+    VectBorrowed::operator_cos(VectBorrowed::new(&lhs), rhs).to_f32() as f64
+}
+
+#[pgrx::pg_extern(immutable, strict, parallel_safe)]
+fn _vchord_halfvec_vector_operator_l2(lhs: HalfvecInput<'_>, rhs: VectorInput<'_>) -> f64 {
+    let lhs = lhs.as_borrowed();
+    let rhs = rhs.as_borrowed();
+    if lhs.dims() != rhs.dims() {
+        pgrx::error!("dimension is not matched");
+    }
+    let lhs = lhs.slice().iter().map(|x| x.to_f32()).collect::<Vec<_>>();
+    VectBorrowed::operator_l2(VectBorrowed::new(&lhs), rhs)
+        .to_f32()
+        .sqrt() as f64
+}
+
+#[pgrx::pg_extern(immutable, strict, parallel_safe)]
+fn _vchord_halfvec_vector_operator_ip(lhs: HalfvecInput<'_>, rhs: VectorInput<'_>) -> f64 {
+    let lhs = lhs.as_borrowed();
+    let rhs = rhs.as_borrowed();
+    if lhs.dims() != rhs.dims() {
+        pgrx::error!("dimension is not matched");
+    }
+    let lhs = lhs.slice().iter().map(|x| x.to_f32()).collect::<Vec<_>>();
+    VectBorrowed::operator_dot(VectBorrowed::new(&lhs), rhs).to_f32() as f64
+}
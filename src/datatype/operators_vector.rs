@@ -1,4 +1,6 @@
+use crate::datatype::memory_halfvec::HalfvecInput;
 use crate::datatype::memory_vector::{VectorInput, VectorOutput};
+use crate::datatype::memory_vector_array::VectorArrayInput;
 use pgrx::Array;
 use std::num::NonZero;
 use vector::VectorBorrowed;
@@ -93,3 +95,57 @@ fn _vchord_vector_operator_maxsim(
     }
     maxsim
 }
+
+#[pgrx::pg_extern(immutable, strict, parallel_safe)]
+fn _vchord_vector_array_operator_maxsim(
+    lhs: VectorArrayInput<'_>,
+    rhs: VectorArrayInput<'_>,
+) -> f32 {
+    if lhs.dims() != rhs.dims() {
+        pgrx::error!("dimension is not matched");
+    }
+    let mut maxsim = 0.0f32;
+    for rhs in rhs.iter() {
+        let mut d = f32::INFINITY;
+        for lhs in lhs.iter() {
+            d = d.min(VectBorrowed::operator_dot(lhs, rhs).to_f32());
+        }
+        maxsim += d;
+    }
+    maxsim
+}
+
+#[pgrx::pg_extern(immutable, strict, parallel_safe)]
+fn _vchord_vector_halfvec_operator_cosine(lhs: VectorInput<'_>, rhs: HalfvecInput<'_>) -> f64 {
+    let lhs = lhs.as_borrowed();
+    let rhs = rhs.as_borrowed();
+    if lhs.dims() != rhs.dims() {
+        pgrx::error!("dimension is not matched");
+    }
+    let rhs = rhs.slice().iter().map(|x| x.to_f32()).collect::<Vec<_>>();
+    VectBorrowed::operator_cos(lhs, VectBorrowed::new(&rhs)).to_f32() as f64
+}
+
+#[pgrx::pg_extern(immutable, strict, parallel_safe)]
+fn _vchord_vector_halfvec_operator_l2(lhs: VectorInput<'_>, rhs: HalfvecInput<'_>) -> f64 {
+    let lhs = lhs.as_borrowed();
+    let rhs = rhs.as_borrowed();
+    if lhs.dims() != rhs.dims() {
+        pgrx::error!("dimension is not matched");
+    }
+    let rhs = rhs.slice().iter().map(|x| x.to_f32()).collect::<Vec<_>>();
+    VectBorrowed::operator_l2(lhs, VectBorrowed::new(&rhs))
+        .to_f32()
+        .sqrt() as f64
+}
+
+#[pgrx::pg_extern(immutable, strict, parallel_safe)]
+fn _vchord_vector_halfvec_operator_ip(lhs: VectorInput<'_>, rhs: HalfvecInput<'_>) -> f64 {
+    let lhs = lhs.as_borrowed();
+    let rhs = rhs.as_borrowed();
+    if lhs.dims() != rhs.dims() {
+        pgrx::error!("dimension is not matched");
+    }
+    let rhs = rhs.slice().iter().map(|x| x.to_f32()).collect::<Vec<_>>();
+    VectBorrowed::operator_dot(lhs, VectBorrowed::new(&rhs)).to_f32() as f64
+}
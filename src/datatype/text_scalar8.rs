@@ -1,102 +1,24 @@
 use super::memory_scalar8::Scalar8Output;
+use super::typmod::check_typmod;
 use crate::datatype::memory_scalar8::Scalar8Input;
 use pgrx::pg_sys::Oid;
 use std::ffi::{CStr, CString};
+use std::str::FromStr;
 use vector::scalar8::Scalar8Borrowed;
 
 #[pgrx::pg_extern(immutable, strict, parallel_safe)]
 fn _vchord_scalar8_in(input: &CStr, oid: Oid, typmod: i32) -> Scalar8Output {
-    let _ = (oid, typmod);
-    let mut input = input.to_bytes().iter();
-    let mut p0 = Vec::<f32>::new();
-    let mut p1 = Vec::<u8>::new();
-    {
-        loop {
-            let Some(c) = input.next().copied() else {
-                pgrx::error!("incorrect vector")
-            };
-            match c {
-                b' ' => (),
-                b'(' => break,
-                _ => pgrx::error!("incorrect vector"),
-            }
-        }
-    }
-    {
-        let mut s = Option::<String>::None;
-        loop {
-            let Some(c) = input.next().copied() else {
-                pgrx::error!("incorrect vector")
-            };
-            s = match (s, c) {
-                (s, b' ') => s,
-                (None, c @ (b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' | b'.' | b'+' | b'-')) => {
-                    Some(String::from(c as char))
-                }
-                (Some(s), c @ (b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' | b'.' | b'+' | b'-')) => {
-                    let mut x = s;
-                    x.push(c as char);
-                    Some(x)
-                }
-                (Some(s), b',') => {
-                    p0.push(s.parse().expect("failed to parse number"));
-                    None
-                }
-                (None, b',') => {
-                    pgrx::error!("incorrect vector")
-                }
-                (Some(s), b')') => {
-                    p0.push(s.parse().expect("failed to parse number"));
-                    break;
-                }
-                (None, b')') => break,
-                _ => pgrx::error!("incorrect vector"),
-            };
-        }
-    }
-    {
-        loop {
-            let Some(c) = input.next().copied() else {
-                pgrx::error!("incorrect vector")
-            };
-            match c {
-                b' ' => (),
-                b'[' => break,
-                _ => pgrx::error!("incorrect vector"),
-            }
-        }
-    }
-    {
-        let mut s = Option::<String>::None;
-        loop {
-            let Some(c) = input.next().copied() else {
-                pgrx::error!("incorrect vector")
-            };
-            s = match (s, c) {
-                (s, b' ') => s,
-                (None, c @ (b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' | b'.' | b'+' | b'-')) => {
-                    Some(String::from(c as char))
-                }
-                (Some(s), c @ (b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' | b'.' | b'+' | b'-')) => {
-                    let mut x = s;
-                    x.push(c as char);
-                    Some(x)
-                }
-                (Some(s), b',') => {
-                    p1.push(s.parse().expect("failed to parse number"));
-                    None
-                }
-                (None, b',') => {
-                    pgrx::error!("incorrect vector")
-                }
-                (Some(s), b']') => {
-                    p1.push(s.parse().expect("failed to parse number"));
-                    break;
-                }
-                (None, b']') => break,
-                _ => pgrx::error!("incorrect vector"),
-            };
-        }
+    let _ = oid;
+    let Ok(input) = input.to_str() else {
+        pgrx::error!("incorrect vector");
+    };
+    let (p0, input) = list::<f32>(input, '(', ')');
+    let (p1, input) = list::<u8>(input, '[', ']');
+    if !input.trim_ascii().is_empty() {
+        pgrx::error!(
+            "incorrect vector: unexpected trailing characters {:?}",
+            input.trim_ascii()
+        );
     }
     if p0.len() != 4 {
         pgrx::error!("incorrect vector");
@@ -109,6 +31,7 @@ fn _vchord_scalar8_in(input: &CStr, oid: Oid, typmod: i32) -> Scalar8Output {
     let b = p0[2];
     let sum_of_code = p0[3];
     let code = p1;
+    check_typmod(typmod, code.len() as u32);
     if let Some(x) = Scalar8Borrowed::new_checked(sum_of_x2, k, b, sum_of_code, &code) {
         Scalar8Output::new(x)
     } else {
@@ -136,3 +59,28 @@ fn _vchord_scalar8_out(vector: Scalar8Input<'_>) -> CString {
     buffer.push(']');
     CString::new(buffer).unwrap()
 }
+
+// parses a comma-separated list between `open` and `close`, and whitespace
+// is allowed around brackets and numbers, but not inside numbers
+pub fn list<T: FromStr>(input: &str, open: char, close: char) -> (Vec<T>, &str) {
+    let Some(input) = input.trim_ascii_start().strip_prefix(open) else {
+        pgrx::error!("incorrect vector: expected {:?}", open);
+    };
+    let Some((inner, rest)) = input.split_once(close) else {
+        pgrx::error!("incorrect vector: expected {:?}", close);
+    };
+    if inner.trim_ascii().is_empty() {
+        return (Vec::new(), rest);
+    }
+    let mut result = Vec::new();
+    for token in inner.split(',').map(str::trim_ascii) {
+        if token.is_empty() {
+            pgrx::error!("incorrect vector: missing number");
+        }
+        let Ok(x) = token.parse() else {
+            pgrx::error!("incorrect vector: invalid number {:?}", token);
+        };
+        result.push(x);
+    }
+    (result, rest)
+}
@@ -0,0 +1,100 @@
+use super::memory_vector_array::{VectorArrayInput, VectorArrayOutput};
+use super::text_scalar8::list;
+use super::typmod::{Typmod, check_typmod};
+use pgrx::pg_sys::Oid;
+use std::ffi::{CStr, CString};
+
+// The text form is a list of vectors between braces, followed by the number of
+// dimensions, such as `{[1,2],[3,4]}/2`. The suffix is optional unless the list is
+// empty and the type has no dimensions.
+#[pgrx::pg_extern(immutable, strict, parallel_safe)]
+fn _vchord_vector_array_in(input: &CStr, oid: Oid, typmod: i32) -> VectorArrayOutput {
+    let _ = oid;
+    let Ok(input) = input.to_str() else {
+        pgrx::error!("incorrect vector array");
+    };
+    let Some(mut input) = input.trim_ascii_start().strip_prefix('{') else {
+        pgrx::error!("incorrect vector array: expected {:?}", '{');
+    };
+    let mut vectors = Vec::<Vec<f32>>::new();
+    loop {
+        let rest = input.trim_ascii_start();
+        if let Some(rest) = rest.strip_prefix('}') {
+            input = rest;
+            break;
+        }
+        let rest = if vectors.is_empty() {
+            rest
+        } else if let Some(rest) = rest.strip_prefix(',') {
+            rest
+        } else {
+            pgrx::error!("incorrect vector array: expected {:?}", ',');
+        };
+        let (vector, rest) = list::<f32>(rest, '[', ']');
+        vectors.push(vector);
+        input = rest;
+    }
+    let input = input.trim_ascii();
+    let suffix = if input.is_empty() {
+        None
+    } else if let Some(dims) = input.strip_prefix('/') {
+        match dims.trim_ascii().parse::<u32>() {
+            Ok(dims @ 1..=65535) => Some(dims),
+            _ => pgrx::error!("incorrect vector array: invalid dimensions {:?}", dims),
+        }
+    } else {
+        pgrx::error!(
+            "incorrect vector array: unexpected trailing characters {:?}",
+            input
+        );
+    };
+    let typmod_dims = Typmod::parse_from_i32(typmod)
+        .and_then(Typmod::dims)
+        .map(|x| x.get());
+    let Some(dims) = suffix
+        .or(vectors.first().map(|x| x.len() as u32))
+        .or(typmod_dims)
+    else {
+        pgrx::error!("dimensions of an empty vector array must be given, such as {{}}/3");
+    };
+    if dims == 0 {
+        pgrx::error!("vector must have at least 1 dimension");
+    }
+    for (i, vector) in vectors.iter().enumerate() {
+        if vector.len() as u32 != dims {
+            pgrx::error!(
+                "vector {} has {} dimensions, but the vector array has {} dimensions",
+                i + 1,
+                vector.len(),
+                dims
+            );
+        }
+        if vector.iter().any(|x| !x.is_finite()) {
+            pgrx::error!("vector {} has a non-finite element", i + 1);
+        }
+    }
+    check_typmod(typmod, dims);
+    VectorArrayOutput::new(dims, &vectors.concat())
+}
+
+#[pgrx::pg_extern(immutable, strict, parallel_safe)]
+fn _vchord_vector_array_out(array: VectorArrayInput<'_>) -> CString {
+    let mut buffer = String::new();
+    buffer.push('{');
+    for (i, vector) in array.iter().enumerate() {
+        if i != 0 {
+            buffer.push(',');
+        }
+        buffer.push('[');
+        for (j, x) in vector.slice().iter().enumerate() {
+            if j != 0 {
+                buffer.push(',');
+            }
+            buffer.push_str(format!("{x}").as_str());
+        }
+        buffer.push(']');
+    }
+    buffer.push('}');
+    buffer.push_str(format!("/{}", array.dims()).as_str());
+    CString::new(buffer).unwrap()
+}
@@ -1,11 +1,14 @@
+use super::memory_scalar8::{Scalar8Input, Scalar8Output};
+use super::memory_vector_array::{VectorArrayInput, VectorArrayOutput};
 use serde::{Deserialize, Serialize};
 use std::ffi::{CStr, CString};
 use std::num::NonZero;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Typmod {
     Any,
     Dims(NonZero<u32>),
+    Range(NonZero<u32>, NonZero<u32>),
 }
 
 impl Typmod {
@@ -13,17 +16,49 @@ impl Typmod {
         use Typmod::*;
         if x == -1 {
             Some(Any)
-        } else if x >= 1 {
+        } else if (1..=65535).contains(&x) {
             Some(Dims(NonZero::new(x as u32).unwrap()))
+        } else if x > 65535 {
+            let (min, max) = ((x as u32) >> 16, (x as u32) & 0xffff);
+            if min < max {
+                Some(Range(
+                    NonZero::new(min).unwrap(),
+                    NonZero::new(max).unwrap(),
+                ))
+            } else {
+                None
+            }
         } else {
             None
         }
     }
+    // The inverse of `into_option_string`, so that a rendered modifier is parsed
+    // back to the same typmod.
+    pub fn parse_from_strs(list: &[&str]) -> Option<Self> {
+        use Typmod::*;
+        let parse = |s: &str| s.trim().parse::<u32>().ok();
+        match *list {
+            [] => Some(Any),
+            [dims] => match parse(dims)? {
+                dims @ 1..=65535 => Some(Dims(NonZero::new(dims)?)),
+                _ => None,
+            },
+            // the lower bound is stored in the high 15 bits, so that the typmod stays positive
+            [min, max] => match (parse(min)?, parse(max)?) {
+                (min @ 1..=32767, max @ 1..=65535) if min < max => {
+                    Some(Range(NonZero::new(min)?, NonZero::new(max)?))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
     pub fn into_option_string(self) -> Option<String> {
         use Typmod::*;
         match self {
             Any => None,
             Dims(x) => Some(x.get().to_string()),
+            Range(min, max) => Some(format!("{},{}", min.get(), max.get())),
         }
     }
     pub fn into_i32(self) -> i32 {
@@ -31,6 +66,7 @@ impl Typmod {
         match self {
             Any => -1,
             Dims(x) => x.get() as i32,
+            Range(min, max) => ((min.get() << 16) | max.get()) as i32,
         }
     }
     pub fn dims(self) -> Option<NonZero<u32>> {
@@ -38,33 +74,77 @@ impl Typmod {
         match self {
             Any => None,
             Dims(dims) => Some(dims),
+            Range(..) => None,
+        }
+    }
+    pub fn check(self, dims: u32) -> bool {
+        use Typmod::*;
+        match self {
+            Any => true,
+            Dims(x) => x.get() == dims,
+            Range(min, max) => (min.get()..=max.get()).contains(&dims),
         }
     }
 }
 
 #[pgrx::pg_extern(immutable, strict, parallel_safe)]
 fn _vchord_typmod_in_65535(list: pgrx::datum::Array<&CStr>) -> i32 {
-    if list.is_empty() {
-        -1
-    } else if list.len() == 1 {
-        let s = list.get(0).unwrap().unwrap().to_str().unwrap();
-        let d = s.parse::<u32>().ok();
-        if let Some(d @ 1..=65535) = d {
-            let typmod = Typmod::Dims(NonZero::new(d).unwrap());
-            typmod.into_i32()
-        } else {
-            pgrx::error!("Modifier of the type is invalid.")
-        }
-    } else {
-        pgrx::error!("Modifier of the type is invalid.")
+    let list = list
+        .iter()
+        .map(|x| x.and_then(|x| x.to_str().ok()))
+        .collect::<Option<Vec<_>>>();
+    match list.as_deref().and_then(Typmod::parse_from_strs) {
+        Some(typmod) => typmod.into_i32(),
+        None => pgrx::error!("Modifier of the type is invalid."),
     }
 }
 
 #[pgrx::pg_extern(immutable, strict, parallel_safe)]
 fn _vchord_typmod_out(typmod: i32) -> CString {
-    let typmod = Typmod::parse_from_i32(typmod).unwrap();
+    let Some(typmod) = Typmod::parse_from_i32(typmod) else {
+        pgrx::error!("Modifier of the type is invalid.");
+    };
+    // the type name is printed without parentheses if there is no modifier
     match typmod.into_option_string() {
         Some(s) => CString::new(format!("({s})")).unwrap(),
-        None => CString::new("()").unwrap(),
+        None => CString::new("").unwrap(),
     }
 }
+
+pub fn check_typmod(typmod: i32, dims: u32) {
+    if let Some(typmod) = Typmod::parse_from_i32(typmod) {
+        if !typmod.check(dims) {
+            if let Some(expected) = typmod.dims() {
+                pgrx::error!("expected vector of {expected} dimensions, got {dims}");
+            }
+            pgrx::error!(
+                "vector has {} dimensions, which does not match the type modifier {}",
+                dims,
+                typmod.into_option_string().unwrap_or_default()
+            );
+        }
+    }
+}
+
+// Values that are not parsed with the type modifier, such as results of functions,
+// are checked by these length coercion casts when they're stored in a column.
+#[pgrx::pg_extern(immutable, strict, parallel_safe)]
+fn _vchord_scalar8_typmod(x: Scalar8Input<'_>, typmod: i32, _explicit: bool) -> Scalar8Output {
+    let x = x.as_borrowed();
+    check_typmod(typmod, x.code().len() as u32);
+    Scalar8Output::new(x)
+}
+
+#[pgrx::pg_extern(immutable, strict, parallel_safe)]
+fn _vchord_vector_array_typmod(
+    x: VectorArrayInput<'_>,
+    typmod: i32,
+    _explicit: bool,
+) -> VectorArrayOutput {
+    check_typmod(typmod, x.dims());
+    let elements = x
+        .iter()
+        .flat_map(|x| x.slice().to_vec())
+        .collect::<Vec<_>>();
+    VectorArrayOutput::new(x.dims(), &elements)
+}
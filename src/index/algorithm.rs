@@ -236,6 +236,519 @@ pub fn bulkdelete(
     }
 }
 
+pub fn compact(
+    opfamily: Opfamily,
+    index: impl RelationRead + RelationWrite,
+    check: impl Fn(),
+) -> u64 {
+    match (opfamily.vector_kind(), opfamily.distance_kind()) {
+        (VectorKind::Vecf32, DistanceKind::L2) => {
+            algorithm::compact::<Op<VectOwned<f32>, L2>>(index, check)
+        }
+        (VectorKind::Vecf32, DistanceKind::Dot) => {
+            algorithm::compact::<Op<VectOwned<f32>, Dot>>(index, check)
+        }
+        (VectorKind::Vecf16, DistanceKind::L2) => {
+            algorithm::compact::<Op<VectOwned<f16>, L2>>(index, check)
+        }
+        (VectorKind::Vecf16, DistanceKind::Dot) => {
+            algorithm::compact::<Op<VectOwned<f16>, Dot>>(index, check)
+        }
+    }
+}
+
+pub fn dump(
+    opfamily: Opfamily,
+    index: impl RelationRead,
+    check: impl Fn(),
+    callback: impl FnMut(algorithm::Dumped<Vec<f32>>),
+) {
+    match (opfamily.vector_kind(), opfamily.distance_kind()) {
+        (VectorKind::Vecf32, DistanceKind::L2) => {
+            algorithm::dump::<_, Op<VectOwned<f32>, L2>>(index, check, callback)
+        }
+        (VectorKind::Vecf32, DistanceKind::Dot) => {
+            algorithm::dump::<_, Op<VectOwned<f32>, Dot>>(index, check, callback)
+        }
+        (VectorKind::Vecf16, DistanceKind::L2) => {
+            algorithm::dump::<_, Op<VectOwned<f16>, L2>>(index, check, callback)
+        }
+        (VectorKind::Vecf16, DistanceKind::Dot) => {
+            algorithm::dump::<_, Op<VectOwned<f16>, Dot>>(index, check, callback)
+        }
+    }
+}
+
+pub fn restore(
+    opfamily: Opfamily,
+    index: impl RelationRead + RelationWrite,
+    check: impl Fn(),
+    dumped: impl Iterator<Item = algorithm::Dumped<Vec<f32>>>,
+) {
+    match (opfamily.vector_kind(), opfamily.distance_kind()) {
+        (VectorKind::Vecf32, DistanceKind::L2) => {
+            algorithm::restore::<_, Op<VectOwned<f32>, L2>>(index, check, map_dumped(dumped))
+        }
+        (VectorKind::Vecf32, DistanceKind::Dot) => {
+            algorithm::restore::<_, Op<VectOwned<f32>, Dot>>(index, check, map_dumped(dumped))
+        }
+        (VectorKind::Vecf16, DistanceKind::L2) => {
+            algorithm::restore::<_, Op<VectOwned<f16>, L2>>(index, check, map_dumped(dumped))
+        }
+        (VectorKind::Vecf16, DistanceKind::Dot) => {
+            algorithm::restore::<_, Op<VectOwned<f16>, Dot>>(index, check, map_dumped(dumped))
+        }
+    }
+}
+
+pub fn reconstruct(
+    opfamily: Opfamily,
+    index: impl RelationRead,
+    payloads: &[NonZero<u64>],
+) -> Vec<Option<Vec<f32>>> {
+    use crate::index::projection::unproject;
+    let results = match (opfamily.vector_kind(), opfamily.distance_kind()) {
+        (VectorKind::Vecf32, DistanceKind::L2) => {
+            algorithm::reconstruct::<_, Op<VectOwned<f32>, L2>>(index, payloads)
+        }
+        (VectorKind::Vecf32, DistanceKind::Dot) => {
+            algorithm::reconstruct::<_, Op<VectOwned<f32>, Dot>>(index, payloads)
+        }
+        (VectorKind::Vecf16, DistanceKind::L2) => {
+            algorithm::reconstruct::<_, Op<VectOwned<f16>, L2>>(index, payloads)
+        }
+        (VectorKind::Vecf16, DistanceKind::Dot) => {
+            algorithm::reconstruct::<_, Op<VectOwned<f16>, Dot>>(index, payloads)
+        }
+    };
+    results
+        .into_iter()
+        .map(|x| x.map(|x| unproject(&x)))
+        .collect()
+}
+
+pub fn structures(opfamily: Opfamily, index: impl RelationRead) -> Vec<Structure<Vec<f32>>> {
+    match (opfamily.vector_kind(), opfamily.distance_kind()) {
+        (VectorKind::Vecf32, DistanceKind::L2) => {
+            algorithm::structures::<_, Op<VectOwned<f32>, L2>>(index)
+        }
+        (VectorKind::Vecf32, DistanceKind::Dot) => {
+            algorithm::structures::<_, Op<VectOwned<f32>, Dot>>(index)
+        }
+        (VectorKind::Vecf16, DistanceKind::L2) => {
+            algorithm::structures::<_, Op<VectOwned<f16>, L2>>(index)
+        }
+        (VectorKind::Vecf16, DistanceKind::Dot) => {
+            algorithm::structures::<_, Op<VectOwned<f16>, Dot>>(index)
+        }
+    }
+}
+
+pub fn lists<R: RelationRead + 'static>(
+    opfamily: Opfamily,
+    index: R,
+) -> Box<dyn Iterator<Item = (u32, Vec<f32>, algorithm::Codes<R>)>> {
+    match (opfamily.vector_kind(), opfamily.distance_kind()) {
+        (VectorKind::Vecf32, DistanceKind::L2) => {
+            Box::new(algorithm::lists::<_, Op<VectOwned<f32>, L2>>(index))
+        }
+        (VectorKind::Vecf32, DistanceKind::Dot) => {
+            Box::new(algorithm::lists::<_, Op<VectOwned<f32>, Dot>>(index))
+        }
+        (VectorKind::Vecf16, DistanceKind::L2) => {
+            Box::new(algorithm::lists::<_, Op<VectOwned<f16>, L2>>(index))
+        }
+        (VectorKind::Vecf16, DistanceKind::Dot) => {
+            Box::new(algorithm::lists::<_, Op<VectOwned<f16>, Dot>>(index))
+        }
+    }
+}
+
+pub fn nearest_centroid(
+    opfamily: Opfamily,
+    index: impl RelationRead,
+    vector: OwnedVector,
+    probes: Vec<u32>,
+) -> (u32, Vec<f32>, f32) {
+    use crate::index::projection::unproject;
+    let (list, centroid, distance) = match (vector, opfamily.distance_kind()) {
+        (OwnedVector::Vecf32(vector), DistanceKind::L2) => {
+            algorithm::nearest_centroid::<_, Op<VectOwned<f32>, L2>>(
+                index,
+                RandomProject::project(vector.as_borrowed()),
+                probes,
+            )
+        }
+        (OwnedVector::Vecf32(vector), DistanceKind::Dot) => {
+            algorithm::nearest_centroid::<_, Op<VectOwned<f32>, Dot>>(
+                index,
+                RandomProject::project(vector.as_borrowed()),
+                probes,
+            )
+        }
+        (OwnedVector::Vecf16(vector), DistanceKind::L2) => {
+            algorithm::nearest_centroid::<_, Op<VectOwned<f16>, L2>>(
+                index,
+                RandomProject::project(vector.as_borrowed()),
+                probes,
+            )
+        }
+        (OwnedVector::Vecf16(vector), DistanceKind::Dot) => {
+            algorithm::nearest_centroid::<_, Op<VectOwned<f16>, Dot>>(
+                index,
+                RandomProject::project(vector.as_borrowed()),
+                probes,
+            )
+        }
+    };
+    (list, unproject(&centroid), opfamily.output(distance))
+}
+
+pub fn probed_lists(
+    opfamily: Opfamily,
+    index: impl RelationRead,
+    vector: OwnedVector,
+    upper: Vec<u32>,
+    probes: u32,
+) -> Vec<(u32, f32, u32)> {
+    let lists = match (vector, opfamily.distance_kind()) {
+        (OwnedVector::Vecf32(vector), DistanceKind::L2) => {
+            algorithm::probed_lists::<_, Op<VectOwned<f32>, L2>>(
+                index,
+                RandomProject::project(vector.as_borrowed()),
+                upper,
+                probes,
+            )
+        }
+        (OwnedVector::Vecf32(vector), DistanceKind::Dot) => {
+            algorithm::probed_lists::<_, Op<VectOwned<f32>, Dot>>(
+                index,
+                RandomProject::project(vector.as_borrowed()),
+                upper,
+                probes,
+            )
+        }
+        (OwnedVector::Vecf16(vector), DistanceKind::L2) => {
+            algorithm::probed_lists::<_, Op<VectOwned<f16>, L2>>(
+                index,
+                RandomProject::project(vector.as_borrowed()),
+                upper,
+                probes,
+            )
+        }
+        (OwnedVector::Vecf16(vector), DistanceKind::Dot) => {
+            algorithm::probed_lists::<_, Op<VectOwned<f16>, Dot>>(
+                index,
+                RandomProject::project(vector.as_borrowed()),
+                upper,
+                probes,
+            )
+        }
+    };
+    lists
+        .into_iter()
+        .map(|(list, distance, size)| (list, opfamily.output(distance), size))
+        .collect()
+}
+
+pub fn distances(
+    opfamily: Opfamily,
+    index: impl RelationRead,
+    vector: OwnedVector,
+    payloads: &[NonZero<u64>],
+) -> Vec<Option<f32>> {
+    use algorithm::RerankMethod;
+    use distance::Distance;
+    use simd::Floating;
+    let results = match algorithm::how(index.clone()) {
+        RerankMethod::Index => match (vector, opfamily.distance_kind()) {
+            (OwnedVector::Vecf32(vector), DistanceKind::L2) => {
+                algorithm::distances::<_, Op<VectOwned<f32>, L2>>(
+                    index,
+                    RandomProject::project(vector.as_borrowed()),
+                    payloads,
+                )
+            }
+            (OwnedVector::Vecf32(vector), DistanceKind::Dot) => {
+                algorithm::distances::<_, Op<VectOwned<f32>, Dot>>(
+                    index,
+                    RandomProject::project(vector.as_borrowed()),
+                    payloads,
+                )
+            }
+            (OwnedVector::Vecf16(vector), DistanceKind::L2) => {
+                algorithm::distances::<_, Op<VectOwned<f16>, L2>>(
+                    index,
+                    RandomProject::project(vector.as_borrowed()),
+                    payloads,
+                )
+            }
+            (OwnedVector::Vecf16(vector), DistanceKind::Dot) => {
+                algorithm::distances::<_, Op<VectOwned<f16>, Dot>>(
+                    index,
+                    RandomProject::project(vector.as_borrowed()),
+                    payloads,
+                )
+            }
+        },
+        RerankMethod::Heap => {
+            // original vectors are not stored in the index, so estimate distances
+            // with the vectors decoded from the codes
+            let vector = match vector {
+                OwnedVector::Vecf32(vector) => vector.slice().to_vec(),
+                OwnedVector::Vecf16(vector) => f16::vector_to_f32(vector.slice()),
+            };
+            let distance_kind = opfamily.distance_kind();
+            reconstruct(opfamily, index, payloads)
+                .into_iter()
+                .map(|x| {
+                    let x = x?;
+                    Some(match distance_kind {
+                        DistanceKind::L2 => Distance::from(f32::reduce_sum_of_d2(&vector, &x)),
+                        DistanceKind::Dot => Distance::from(-f32::reduce_sum_of_xy(&vector, &x)),
+                    })
+                })
+                .collect()
+        }
+    };
+    results
+        .into_iter()
+        .map(|x| x.map(|x| opfamily.output(x)))
+        .collect()
+}
+
+pub fn multi_distances(
+    opfamily: Opfamily,
+    index: impl RelationRead,
+    vectors: Vec<OwnedVector>,
+    payloads: &[NonZero<u64>],
+) -> Vec<Option<Vec<f32>>> {
+    use algorithm::RerankMethod;
+    use distance::Distance;
+    use simd::Floating;
+    let results = match algorithm::how(index.clone()) {
+        RerankMethod::Index => match (opfamily.vector_kind(), opfamily.distance_kind()) {
+            (VectorKind::Vecf32, DistanceKind::L2) => algorithm::multi_distances::<
+                _,
+                Op<VectOwned<f32>, L2>,
+            >(
+                index, &vecf32(vectors), payloads
+            ),
+            (VectorKind::Vecf32, DistanceKind::Dot) => algorithm::multi_distances::<
+                _,
+                Op<VectOwned<f32>, Dot>,
+            >(
+                index, &vecf32(vectors), payloads
+            ),
+            (VectorKind::Vecf16, DistanceKind::L2) => algorithm::multi_distances::<
+                _,
+                Op<VectOwned<f16>, L2>,
+            >(
+                index, &vecf16(vectors), payloads
+            ),
+            (VectorKind::Vecf16, DistanceKind::Dot) => algorithm::multi_distances::<
+                _,
+                Op<VectOwned<f16>, Dot>,
+            >(
+                index, &vecf16(vectors), payloads
+            ),
+        },
+        RerankMethod::Heap => {
+            // original vectors are not stored in the index, so estimate distances
+            // with the vectors decoded from the codes
+            let vectors = vectors
+                .into_iter()
+                .map(|vector| match vector {
+                    OwnedVector::Vecf32(vector) => vector.slice().to_vec(),
+                    OwnedVector::Vecf16(vector) => f16::vector_to_f32(vector.slice()),
+                })
+                .collect::<Vec<_>>();
+            let distance_kind = opfamily.distance_kind();
+            reconstruct(opfamily, index, payloads)
+                .into_iter()
+                .map(|x| {
+                    let x = x?;
+                    Some(
+                        vectors
+                            .iter()
+                            .map(|vector| match distance_kind {
+                                DistanceKind::L2 => {
+                                    Distance::from(f32::reduce_sum_of_d2(vector, &x))
+                                }
+                                DistanceKind::Dot => {
+                                    Distance::from(-f32::reduce_sum_of_xy(vector, &x))
+                                }
+                            })
+                            .collect(),
+                    )
+                })
+                .collect()
+        }
+    };
+    results
+        .into_iter()
+        .map(|x| Some(x?.into_iter().map(|x| opfamily.output(x)).collect()))
+        .collect()
+}
+
+pub fn row_distance(
+    opfamily: Opfamily,
+    index: impl RelationRead,
+    lhs: NonZero<u64>,
+    rhs: NonZero<u64>,
+) -> Result<f32, NonZero<u64>> {
+    use algorithm::RerankMethod;
+    use distance::Distance;
+    use simd::Floating;
+    let result = match algorithm::how(index.clone()) {
+        RerankMethod::Index => match (opfamily.vector_kind(), opfamily.distance_kind()) {
+            (VectorKind::Vecf32, DistanceKind::L2) => {
+                algorithm::row_distance::<_, Op<VectOwned<f32>, L2>>(index, lhs, rhs)
+            }
+            (VectorKind::Vecf32, DistanceKind::Dot) => {
+                algorithm::row_distance::<_, Op<VectOwned<f32>, Dot>>(index, lhs, rhs)
+            }
+            (VectorKind::Vecf16, DistanceKind::L2) => {
+                algorithm::row_distance::<_, Op<VectOwned<f16>, L2>>(index, lhs, rhs)
+            }
+            (VectorKind::Vecf16, DistanceKind::Dot) => {
+                algorithm::row_distance::<_, Op<VectOwned<f16>, Dot>>(index, lhs, rhs)
+            }
+        },
+        RerankMethod::Heap => {
+            // original vectors are not stored in the index, so estimate the distance
+            // with the vectors decoded from the codes
+            let distance_kind = opfamily.distance_kind();
+            let mut vectors = reconstruct(opfamily, index, &[lhs, rhs]).into_iter();
+            let l = vectors.next().flatten().ok_or(lhs)?;
+            let r = vectors.next().flatten().ok_or(rhs)?;
+            Ok(match distance_kind {
+                DistanceKind::L2 => Distance::from(f32::reduce_sum_of_d2(&l, &r)),
+                DistanceKind::Dot => Distance::from(-f32::reduce_sum_of_xy(&l, &r)),
+            })
+        }
+    };
+    result.map(|x| opfamily.output(x))
+}
+
+fn vecf32(vectors: Vec<OwnedVector>) -> Vec<VectOwned<f32>> {
+    vectors
+        .into_iter()
+        .map(|vector| match vector {
+            OwnedVector::Vecf32(vector) => RandomProject::project(vector.as_borrowed()),
+            OwnedVector::Vecf16(_) => unreachable!(),
+        })
+        .collect()
+}
+
+fn vecf16(vectors: Vec<OwnedVector>) -> Vec<VectOwned<f16>> {
+    vectors
+        .into_iter()
+        .map(|vector| match vector {
+            OwnedVector::Vecf16(vector) => RandomProject::project(vector.as_borrowed()),
+            OwnedVector::Vecf32(_) => unreachable!(),
+        })
+        .collect()
+}
+
+// estimated distances and lower bounds of all vectors in probed lists
+pub fn bounds(
+    opfamily: Opfamily,
+    index: impl RelationRead,
+    vector: OwnedVector,
+    probes: Vec<u32>,
+    epsilon: f32,
+) -> Vec<(NonZero<u64>, distance::Distance, distance::Distance)> {
+    use algorithm::PlainPrefetcher;
+    use always_equal::AlwaysEqual;
+    use std::cmp::Reverse;
+    use std::collections::BinaryHeap;
+    let bump = BumpAlloc::new();
+    let prefetch = |results| PlainPrefetcher::<_, BinaryHeap<_>>::new(index.clone(), results);
+    let results = match (vector, opfamily.distance_kind()) {
+        (OwnedVector::Vecf32(vector), DistanceKind::L2) => {
+            algorithm::default_search::<_, Op<VectOwned<f32>, L2>, _>(
+                index.clone(),
+                RandomProject::project(vector.as_borrowed()),
+                probes,
+                epsilon,
+                &bump,
+                prefetch,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                &std::cell::Cell::new(0),
+                &mut None,
+            )
+        }
+        (OwnedVector::Vecf32(vector), DistanceKind::Dot) => {
+            algorithm::default_search::<_, Op<VectOwned<f32>, Dot>, _>(
+                index.clone(),
+                RandomProject::project(vector.as_borrowed()),
+                probes,
+                epsilon,
+                &bump,
+                prefetch,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                &std::cell::Cell::new(0),
+                &mut None,
+            )
+        }
+        (OwnedVector::Vecf16(vector), DistanceKind::L2) => {
+            algorithm::default_search::<_, Op<VectOwned<f16>, L2>, _>(
+                index.clone(),
+                RandomProject::project(vector.as_borrowed()),
+                probes,
+                epsilon,
+                &bump,
+                prefetch,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                &std::cell::Cell::new(0),
+                &mut None,
+            )
+        }
+        (OwnedVector::Vecf16(vector), DistanceKind::Dot) => {
+            algorithm::default_search::<_, Op<VectOwned<f16>, Dot>, _>(
+                index.clone(),
+                RandomProject::project(vector.as_borrowed()),
+                probes,
+                epsilon,
+                &bump,
+                prefetch,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                &std::cell::Cell::new(0),
+                &mut None,
+            )
+        }
+    };
+    results
+        .into_iter()
+        .map(
+            |((Reverse(lowerbound), AlwaysEqual(rough)), AlwaysEqual(&mut (payload, ..)))| {
+                (payload, rough, lowerbound)
+            },
+        )
+        .collect()
+}
+
 pub fn maintain(opfamily: Opfamily, index: impl RelationRead + RelationWrite, check: impl Fn()) {
     match (opfamily.vector_kind(), opfamily.distance_kind()) {
         (VectorKind::Vecf32, DistanceKind::L2) => {
@@ -352,6 +865,24 @@ fn map_structures<T, U>(x: Vec<Structure<T>>, f: impl Fn(T) -> U + Copy) -> Vec<
         .collect()
 }
 
+fn map_dumped<V: InternalBuild>(
+    x: impl Iterator<Item = algorithm::Dumped<Vec<f32>>>,
+) -> impl Iterator<Item = algorithm::Dumped<V>> {
+    use algorithm::Dumped;
+    x.map(|x| match x {
+        Dumped::List { radius } => Dumped::List { radius },
+        Dumped::Code {
+            payload,
+            code,
+            vector,
+        } => Dumped::Code {
+            payload,
+            code,
+            vector: vector.map(|x| V::build_from_vecf32(&x)),
+        },
+    })
+}
+
 pub trait RandomProject {
     type Output;
     fn project(self) -> Self::Output;
@@ -1,5 +1,5 @@
 use crate::datatype::typmod::Typmod;
-use crate::index::am::{Reloption, ctid_to_key, kv_to_pointer};
+use crate::index::am::{ALIAS, Reloption, ctid_to_key, kv_to_pointer, pointer_to_kv};
 use crate::index::opclass::{Opfamily, opfamily};
 use crate::index::storage::{PostgresPage, PostgresRelation};
 use crate::index::types::*;
@@ -9,7 +9,9 @@ use half::f16;
 use pgrx::pg_sys::{Datum, ItemPointerData};
 use rand::Rng;
 use simd::Floating;
+use std::collections::HashMap;
 use std::ffi::CStr;
+use std::num::NonZero;
 use std::ops::Deref;
 use vector::VectorOwned;
 use vector::vect::VectOwned;
@@ -147,6 +149,7 @@ struct Heap {
     index_info: *mut pgrx::pg_sys::IndexInfo,
     opfamily: Opfamily,
     scan: *mut pgrx::pg_sys::TableScanDescData,
+    sample_percent: f64,
 }
 
 impl Heap {
@@ -174,6 +177,9 @@ impl Heap {
             let opfamily = state.this.opfamily;
             let datum = unsafe { (!is_null.add(0).read()).then_some(values.add(0).read()) };
             let ctid = unsafe { ctid.read() };
+            if !sampled(ctid, state.this.sample_percent) {
+                return;
+            }
             if let Some(store) = unsafe { datum.and_then(|x| opfamily.store(x)) } {
                 (state.callback)((ctid, store));
             }
@@ -201,6 +207,16 @@ impl Heap {
     }
 }
 
+// Rows are sampled by hashes of their ctids, so all traversals and all
+// participants agree on which rows are sampled.
+fn sampled(ctid: ItemPointerData, percent: f64) -> bool {
+    if percent >= 100.0 {
+        return true;
+    }
+    let hash = crate::index::scanners::mix(0, ctid_to_key(ctid));
+    (hash as f64) < percent / 100.0 * (u64::MAX as f64)
+}
+
 #[derive(Debug, Clone)]
 struct PostgresReporter {}
 
@@ -251,15 +267,50 @@ pub unsafe extern "C-unwind" fn ambuild(
         );
     }
     let opfamily = unsafe { opfamily(index_relation) };
+    let dedup = vchordrq_options.build.dedup;
+    if vchordrq_options.build.verify
+        && matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim)
+    {
+        pgrx::error!("error while validating options: verify is not supported for maxsim");
+    }
+    if dedup == VchordrqDedup::Codes {
+        if matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim) {
+            pgrx::error!(
+                "error while validating options: dedup = \"codes\" is not supported for maxsim"
+            );
+        }
+        if vchordrq_options.index.rerank_in_table {
+            pgrx::error!(
+                "error while validating options: dedup = \"codes\" cannot be enabled if rerank_in_table is enabled"
+            );
+        }
+    }
     let index = unsafe { PostgresRelation::new(index_relation) };
+    if vchordrq_options.build.empty {
+        let structures = vec![Structure {
+            means: vec![vec![0.0f32; vector_options.dims as _]],
+            children: vec![Vec::new()],
+        }];
+        crate::index::algorithm::build(vector_options, vchordrq_options.index, index, structures);
+        return unsafe { pgrx::pgbox::PgBox::<pgrx::pg_sys::IndexBuildResult>::alloc0().into_pg() };
+    }
     let heap = Heap {
         heap_relation,
         index_relation,
         index_info,
         opfamily,
         scan: std::ptr::null_mut(),
+        sample_percent: vchordrq_options.build.index_sample_percent,
     };
     let mut reporter = PostgresReporter {};
+    let checkpoint = match &vchordrq_options.build.source {
+        VchordrqBuildSourceOptions::Internal(internal_build) if internal_build.checkpoint => {
+            Some(unsafe {
+                checkpoint_key(heap_relation, index_info, &vector_options, internal_build)
+            })
+        }
+        _ => None,
+    };
     let structures = match vchordrq_options.build.source.clone() {
         VchordrqBuildSourceOptions::External(external_build) => {
             reporter.phase(BuildPhase::from_code(BuildPhaseCode::ExternalBuild));
@@ -267,8 +318,33 @@ pub unsafe extern "C-unwind" fn ambuild(
             reporter.tuples_total(reltuples as u64);
             make_external_build(vector_options.clone(), opfamily, external_build.clone())
         }
-        VchordrqBuildSourceOptions::Internal(internal_build) => {
+        VchordrqBuildSourceOptions::Model(model_build) => {
+            reporter.phase(BuildPhase::from_code(BuildPhaseCode::ExternalBuild));
+            make_model_build(&vector_options, model_build)
+        }
+        VchordrqBuildSourceOptions::CentroidsFrom(name) => {
+            reporter.phase(BuildPhase::from_code(BuildPhaseCode::ExternalBuild));
+            unsafe { make_centroids_from_build(index_relation, &vector_options, &name) }
+        }
+        VchordrqBuildSourceOptions::Internal(mut internal_build) => 'internal: {
             reporter.phase(BuildPhase::from_code(BuildPhaseCode::InternalBuild));
+            if let Some(structures) = checkpoint
+                .as_ref()
+                .and_then(|key| crate::index::checkpoint::load(&vector_options, key))
+            {
+                pgrx::info!("checkpoint: clustering is skipped, since a checkpoint is found");
+                break 'internal structures;
+            }
+            if internal_build.auto_lists {
+                let rows = unsafe { estimate_rows(heap_relation) };
+                let lists = internal_build.auto_lists(rows);
+                pgrx::info!(
+                    "auto_lists: using {lists} lists for {rows} estimated rows, clamped to [{}, {}]",
+                    internal_build.min_lists,
+                    internal_build.max_lists
+                );
+                internal_build.lists = vec![lists];
+            }
             let mut tuples_total = 0_u64;
             let samples = 'a: {
                 let mut rand = rand::rng();
@@ -277,9 +353,20 @@ pub unsafe extern "C-unwind" fn ambuild(
                     .last()
                     .map(|x| x.saturating_mul(internal_build.sampling_factor))
                 else {
-                    break 'a Vec::new();
+                    break 'a Samples::Memory(Vec::new());
+                };
+                let size = max_number_of_samples as usize
+                    * vector_options.dims as usize
+                    * size_of::<f32>();
+                let limit = unsafe { pgrx::pg_sys::maintenance_work_mem } as usize * 1024;
+                let mut samples = if internal_build.build_spill && size > limit {
+                    pgrx::info!(
+                        "build_spill: samples need {size} bytes, which exceeds maintenance_work_mem, so they are spilled to a temporary file"
+                    );
+                    Samples::Spilled(SpilledSamples::new(vector_options.dims as _))
+                } else {
+                    Samples::Memory(Vec::new())
                 };
-                let mut samples = Vec::new();
                 let mut number_of_samples = 0_u32;
                 heap.traverse(false, |(_, store)| {
                     for (vector, _) in store {
@@ -293,11 +380,11 @@ pub unsafe extern "C-unwind" fn ambuild(
                             "invalid vector dimensions"
                         );
                         if number_of_samples < max_number_of_samples {
-                            samples.push(x);
+                            samples.set(number_of_samples as usize, x);
                             number_of_samples += 1;
                         } else {
                             let index = rand.random_range(0..max_number_of_samples) as usize;
-                            samples[index] = x;
+                            samples.set(index, x);
                         }
                     }
                     tuples_total += 1;
@@ -305,12 +392,16 @@ pub unsafe extern "C-unwind" fn ambuild(
                 samples
             };
             reporter.tuples_total(tuples_total);
-            make_internal_build(
+            let structures = make_internal_build(
                 vector_options.clone(),
                 internal_build.clone(),
                 samples,
                 &mut reporter,
-            )
+            );
+            if let Some(key) = checkpoint.as_ref() {
+                crate::index::checkpoint::save(&vector_options, key, &structures);
+            }
+            structures
         }
     };
     reporter.phase(BuildPhase::from_code(BuildPhaseCode::Build));
@@ -339,14 +430,21 @@ pub unsafe extern "C-unwind" fn ambuild(
     } else {
         vchordrq_cached::VchordrqCached::_0 {}
     };
-    if let Some(leader) = unsafe {
-        VchordrqLeader::enter(
-            heap_relation,
-            index_relation,
-            (*index_info).ii_Concurrent,
-            cache,
-        )
-    } {
+    // duplicates are detected across the whole table, so it's never parallel, and
+    // workers would interleave pages of lists, so a sorted table is read serially
+    let leader = if dedup == VchordrqDedup::Off && !vchordrq_options.build.input_sorted {
+        unsafe {
+            VchordrqLeader::enter(
+                heap_relation,
+                index_relation,
+                (*index_info).ii_Concurrent,
+                cache,
+            )
+        }
+    } else {
+        None
+    };
+    if let Some(leader) = leader {
         unsafe {
             parallel_build(
                 index_relation,
@@ -380,25 +478,238 @@ pub unsafe extern "C-unwind" fn ambuild(
     } else {
         let mut indtuples = 0;
         reporter.tuples_done(indtuples);
+        // Groups are kept in memory until they exceed maintenance_work_mem, and then
+        // they are indexed and forgotten, so duplicates across batches are not merged.
+        let limit = unsafe { pgrx::pg_sys::maintenance_work_mem } as usize * 1024;
+        let flush = |groups: &mut Vec<(OwnedVector, Vec<NonZero<u64>>)>| {
+            let mut aliases = Vec::new();
+            for (vector, members) in groups.drain(..) {
+                let payload = if let [payload] = members[..] {
+                    payload
+                } else {
+                    let (key, _) = pointer_to_kv(members[0]);
+                    let payload = kv_to_pointer((key, ALIAS));
+                    aliases.push((payload, members));
+                    payload
+                };
+                crate::index::algorithm::insert(opfamily, index.clone(), payload, vector);
+            }
+            algorithm::alias(index.clone(), aliases);
+        };
+        let mut positions = HashMap::<Vec<u32>, usize>::new();
+        let mut groups = Vec::<(OwnedVector, Vec<NonZero<u64>>)>::new();
+        let mut used = 0_usize;
+        let mut batches = 0_u64;
+        let mut unique = 0_u64;
+        let mut vectors = 0_u64;
         heap.traverse(true, |(ctid, store)| {
             for (vector, extra) in store {
                 let key = ctid_to_key(ctid);
                 let payload = kv_to_pointer((key, extra));
-                crate::index::algorithm::insert(opfamily, index.clone(), payload, vector);
+                vectors += 1;
+                if dedup == VchordrqDedup::Off {
+                    crate::index::algorithm::insert(opfamily, index.clone(), payload, vector);
+                    continue;
+                }
+                let bits: Vec<u32> = match &vector {
+                    OwnedVector::Vecf32(x) => x.slice().iter().map(|x| x.to_bits()).collect(),
+                    OwnedVector::Vecf16(x) => {
+                        x.slice().iter().map(|x| x.to_bits() as u32).collect()
+                    }
+                };
+                let size = bits.len() * size_of::<u32>() + size_of::<(Vec<u32>, usize)>();
+                let next = positions.len();
+                let position = *positions.entry(bits).or_insert_with(|| {
+                    used += size;
+                    unique += 1;
+                    next
+                });
+                if dedup == VchordrqDedup::Codes {
+                    if position == groups.len() {
+                        used += size + size_of::<NonZero<u64>>();
+                        groups.push((vector, vec![payload]));
+                    } else {
+                        used += size_of::<NonZero<u64>>();
+                        groups[position].1.push(payload);
+                    }
+                } else {
+                    crate::index::algorithm::insert(opfamily, index.clone(), payload, vector);
+                }
+                if used > limit {
+                    flush(&mut groups);
+                    positions.clear();
+                    used = 0;
+                    batches += 1;
+                }
             }
             indtuples += 1;
             reporter.tuples_done(indtuples);
         });
         reporter.tuples_total(indtuples);
+        if dedup == VchordrqDedup::Codes {
+            flush(&mut groups);
+        }
+        if dedup != VchordrqDedup::Off && vectors != 0 {
+            pgrx::info!(
+                "dedup: {} unique vectors in {} vectors, ratio {:.2}",
+                unique,
+                vectors,
+                vectors as f64 / unique as f64
+            );
+            if batches != 0 {
+                pgrx::info!(
+                    "dedup: vectors exceed maintenance_work_mem, so they are deduplicated in {} batches",
+                    batches + 1
+                );
+            }
+        }
     }
     let check = || {
         pgrx::check_for_interrupts!();
     };
     reporter.phase(BuildPhase::from_code(BuildPhaseCode::Compacting));
-    crate::index::algorithm::maintain(opfamily, index, check);
+    crate::index::algorithm::maintain(opfamily, index.clone(), check);
+    if vchordrq_options.build.verify {
+        verify(&heap, index, vector_options.d);
+    }
+    if let Some(key) = checkpoint.as_ref() {
+        crate::index::checkpoint::remove(key);
+    }
     unsafe { pgrx::pgbox::PgBox::<pgrx::pg_sys::IndexBuildResult>::alloc0().into_pg() }
 }
 
+// The table, the indexed column or expression and the options of clustering. The
+// number of lists is the one in options, even if `auto_lists` is enabled.
+unsafe fn checkpoint_key(
+    heap_relation: pgrx::pg_sys::Relation,
+    index_info: *mut pgrx::pg_sys::IndexInfo,
+    vector_options: &VectorOptions,
+    internal_build: &VchordrqInternalBuildOptions,
+) -> crate::index::checkpoint::Key {
+    let (database, table, column) = unsafe {
+        let expressions = (*index_info).ii_Expressions;
+        let column = if expressions.is_null() {
+            (*index_info).ii_IndexAttrNumbers[0].to_string()
+        } else {
+            let node = pgrx::pg_sys::nodeToString(expressions.cast());
+            CStr::from_ptr(node).to_string_lossy().into_owned()
+        };
+        (
+            pgrx::pg_sys::MyDatabaseId.to_u32(),
+            (*heap_relation).rd_id.to_u32(),
+            column,
+        )
+    };
+    let options = toml::to_string(internal_build).expect("failed to serialize options");
+    crate::index::checkpoint::Key {
+        database,
+        table,
+        fingerprint: format!(
+            "{column}/{}/{:?}/{:?}/{options}",
+            vector_options.dims, vector_options.v, vector_options.d
+        ),
+    }
+}
+
+// Measures recall@10 of sampled vectors against an exact scan over the table.
+fn verify(heap: &Heap, index: PostgresRelation, d: DistanceKind) {
+    use distance::Distance;
+    use std::collections::BinaryHeap;
+    const QUERIES: usize = 200;
+    const K: usize = 10;
+    let to_vecf32 = |vector: &OwnedVector| match vector {
+        OwnedVector::Vecf32(x) => x.slice().to_vec(),
+        OwnedVector::Vecf16(x) => f16::vector_to_f32(x.slice()),
+    };
+    let distance = |lhs: &[f32], rhs: &[f32]| match d {
+        DistanceKind::L2 => Distance::from_f32(f32::reduce_sum_of_d2(lhs, rhs)),
+        DistanceKind::Dot => Distance::from_f32(-f32::reduce_sum_of_xy(lhs, rhs)),
+    };
+    let mut rand = rand::rng();
+    let mut queries = Vec::<OwnedVector>::new();
+    let mut number_of_vectors = 0_usize;
+    heap.traverse(false, |(_, store)| {
+        for (vector, _) in store {
+            if queries.len() < QUERIES {
+                queries.push(vector);
+            } else {
+                let index = rand.random_range(0..=number_of_vectors);
+                if index < QUERIES {
+                    queries[index] = vector;
+                }
+            }
+            number_of_vectors += 1;
+        }
+    });
+    if queries.is_empty() {
+        return;
+    }
+    let k = K.min(number_of_vectors);
+    let targets = queries.iter().map(to_vecf32).collect::<Vec<_>>();
+    let mut exact = vec![BinaryHeap::<Distance>::with_capacity(k + 1); queries.len()];
+    heap.traverse(false, |(_, store)| {
+        for (vector, _) in store {
+            let vector = to_vecf32(&vector);
+            for (target, results) in targets.iter().zip(exact.iter_mut()) {
+                results.push(distance(target, &vector));
+                if results.len() > k {
+                    results.pop();
+                }
+            }
+        }
+    });
+    let cells = algorithm::cost(index).cells;
+    let probes = cells[..cells.len() - 1]
+        .iter()
+        .map(|&x| x.div_ceil(10))
+        .collect::<Vec<_>>();
+    let snapshot = unsafe {
+        if pgrx::pg_sys::ActiveSnapshotSet() {
+            pgrx::pg_sys::GetActiveSnapshot()
+        } else {
+            pgrx::pg_sys::GetTransactionSnapshot()
+        }
+    };
+    let mut hits = 0_usize;
+    for (query, results) in queries.into_iter().zip(exact) {
+        pgrx::check_for_interrupts!();
+        let Some(&threshold) = results.peek() else {
+            continue;
+        };
+        let threshold = heap.opfamily.output(threshold);
+        let mut options = crate::index::am::search_options();
+        options.probes = probes.clone();
+        options.max_scan_tuples = None;
+        let approximate = unsafe {
+            crate::index::am::search(
+                heap.index_relation,
+                heap.heap_relation,
+                snapshot,
+                options,
+                query,
+                |iter| {
+                    iter.take(k)
+                        .map(|(distance, _)| distance)
+                        .collect::<Vec<_>>()
+                },
+            )
+        };
+        // distances are compared instead of rows, so that ties are not counted as misses
+        let tolerance = 1e-4 * threshold.abs().max(1.0);
+        hits += approximate
+            .into_iter()
+            .filter(|&distance| distance <= threshold + tolerance)
+            .count();
+    }
+    pgrx::notice!(
+        "verify: recall@{} is {:.4} with probes = {:?} over {} sampled vectors",
+        k,
+        hits as f64 / (k * targets.len()) as f64,
+        probes,
+        targets.len()
+    );
+}
+
 struct VchordrqShared {
     /* Immutable state */
     heaprelid: pgrx::pg_sys::Oid,
@@ -853,12 +1164,14 @@ unsafe fn parallel_build(
 
     let scan = unsafe { pgrx::pg_sys::table_beginscan_parallel(heap_relation, tablescandesc) };
     let opfamily = unsafe { opfamily(index_relation) };
+    let (_, vchordrq_options) = unsafe { options(index_relation) };
     let heap = Heap {
         heap_relation,
         index_relation,
         index_info,
         opfamily,
         scan,
+        sample_percent: vchordrq_options.build.index_sample_percent,
     };
     match cached {
         VchordrqCachedReader::_0(_) => {
@@ -932,6 +1245,10 @@ unsafe fn options(
     let typmod = Typmod::parse_from_i32(atts[0].type_mod()).unwrap();
     let dims = if let Some(dims) = typmod.dims() {
         dims.get()
+    } else if !unsafe { pgrx::pg_sys::RelationGetIndexExpressions(index_relation) }.is_null() {
+        pgrx::error!(
+            "Dimensions type modifier of an indexed expression is needed for building the index. Cast the expression to a vector type with dimensions, such as `(l2_normalize(embedding)::vector(768))`."
+        );
     } else {
         pgrx::error!(
             "Dimensions type modifier of a vector column is needed for building the index."
@@ -951,33 +1268,143 @@ unsafe fn options(
             break 'rabitq Default::default();
         }
         let s = unsafe { Reloption::options(reloption) }.to_string_lossy();
-        match toml::from_str::<VchordrqIndexingOptions>(&s) {
+        let p = match toml::from_str::<VchordrqIndexingOptions>(&s) {
             Ok(p) => p,
             Err(e) => pgrx::error!("failed to parse options: {}", e),
+        };
+        if let Err(e) = validator::Validate::validate(&p) {
+            pgrx::error!("failed to validate options: {}", e);
         }
+        p
     };
     (vector, rabitq)
 }
 
+unsafe fn estimate_rows(heap_relation: pgrx::pg_sys::Relation) -> f64 {
+    let mut pages = 0;
+    let mut tuples = 0.0;
+    let mut allvisfrac = 0.0;
+    unsafe {
+        pgrx::pg_sys::estimate_rel_size(
+            heap_relation,
+            std::ptr::null_mut(),
+            &mut pages,
+            &mut tuples,
+            &mut allvisfrac,
+        );
+    }
+    tuples
+}
+
+enum Samples {
+    Memory(Vec<Vec<f32>>),
+    Spilled(SpilledSamples),
+}
+
+impl Samples {
+    fn set(&mut self, i: usize, sample: Vec<f32>) {
+        match self {
+            Samples::Memory(samples) if i == samples.len() => samples.push(sample),
+            Samples::Memory(samples) => samples[i] = sample,
+            // the file is read in passes, so samples are projected before they are written
+            Samples::Spilled(samples) => {
+                samples.write(i, &crate::index::projection::project(&sample));
+            }
+        }
+    }
+}
+
+// Samples are fixed-size records in a temporary file, which is deleted at the end
+// of the transaction if it's not closed.
+struct SpilledSamples {
+    file: *mut pgrx::pg_sys::BufFile,
+    dims: usize,
+    len: usize,
+}
+
+impl SpilledSamples {
+    fn new(dims: usize) -> Self {
+        let file = unsafe { pgrx::pg_sys::BufFileCreateTemp(false) };
+        Self { file, dims, len: 0 }
+    }
+    fn len(&self) -> usize {
+        self.len
+    }
+    fn seek(&self, i: usize) {
+        // the same as `MAX_PHYSICAL_FILESIZE`, the size of segments of a `BufFile`
+        const SEGMENT: usize = 1 << 30;
+        let position = i * self.dims * size_of::<f32>();
+        let fileno = (position / SEGMENT) as _;
+        let offset = (position % SEGMENT) as _;
+        let whence = pgrx::pg_sys::SEEK_SET as _;
+        if unsafe { pgrx::pg_sys::BufFileSeek(self.file, fileno, offset, whence) } != 0 {
+            pgrx::error!("could not seek in temporary file of samples");
+        }
+    }
+    fn write(&mut self, i: usize, sample: &[f32]) {
+        assert_eq!(sample.len(), self.dims);
+        self.seek(i);
+        let size = size_of_val(sample);
+        #[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15"))]
+        if unsafe { pgrx::pg_sys::BufFileWrite(self.file, sample.as_ptr().cast_mut().cast(), size) }
+            != size
+        {
+            pgrx::error!("could not write to temporary file of samples");
+        }
+        #[cfg(any(feature = "pg16", feature = "pg17"))]
+        unsafe {
+            pgrx::pg_sys::BufFileWrite(self.file, sample.as_ptr().cast(), size);
+        }
+        self.len = self.len.max(i + 1);
+    }
+    fn pass(&self, f: &mut dyn FnMut(usize, &[f32])) {
+        self.seek(0);
+        let mut sample = vec![0.0f32; self.dims];
+        let size = size_of_val(sample.as_slice());
+        for i in 0..self.len {
+            pgrx::check_for_interrupts!();
+            if unsafe { pgrx::pg_sys::BufFileRead(self.file, sample.as_mut_ptr().cast(), size) }
+                != size
+            {
+                pgrx::error!("could not read from temporary file of samples");
+            }
+            f(i, &sample);
+        }
+    }
+}
+
+impl Drop for SpilledSamples {
+    fn drop(&mut self) {
+        unsafe {
+            pgrx::pg_sys::BufFileClose(self.file);
+        }
+    }
+}
+
 fn make_internal_build(
     vector_options: VectorOptions,
     internal_build: VchordrqInternalBuildOptions,
-    mut samples: Vec<Vec<f32>>,
+    mut samples: Samples,
     reporter: &mut PostgresReporter,
 ) -> Vec<Structure<Vec<f32>>> {
     use std::iter::once;
-    k_means::preprocess(internal_build.build_threads as _, &mut samples, |sample| {
-        *sample = crate::index::projection::project(sample)
-    });
+    if let Samples::Memory(samples) = &mut samples {
+        k_means::preprocess(internal_build.build_threads as _, samples, |sample| {
+            *sample = crate::index::projection::project(sample)
+        });
+    }
     let mut result = Vec::<Structure<Vec<f32>>>::new();
     for w in internal_build.lists.iter().rev().copied().chain(once(1)) {
-        let input = if let Some(structure) = result.last() {
-            &structure.means
-        } else {
-            &samples
+        let input = match (result.last(), &samples) {
+            (Some(structure), _) => Ok(&structure.means),
+            (None, Samples::Memory(samples)) => Ok(samples),
+            (None, Samples::Spilled(samples)) => Err(samples),
         };
         let num_threads = internal_build.build_threads as _;
-        let num_points = input.len();
+        let num_points = match input {
+            Ok(input) => input.len(),
+            Err(input) => input.len(),
+        };
         let num_dims = vector_options.dims as usize;
         let num_lists = w as usize;
         let num_iterations = internal_build.kmeans_iterations as _;
@@ -993,28 +1420,41 @@ fn make_internal_build(
                 "clustering: starting, using {num_threads} threads, clustering {num_points} vectors of {num_dims} dimension into {num_lists} clusters, in {num_iterations} iterations"
             );
         }
-        let means = k_means::k_means(
-            num_threads,
-            |i| {
-                pgrx::check_for_interrupts!();
-                if result.is_empty() {
-                    let percentage =
-                        ((i as f64 / num_iterations as f64) * 100.0).clamp(0.0, 100.0) as u16;
-                    let default = BuildPhase::from_code(BuildPhaseCode::InternalBuild);
-                    let phase = BuildPhase::new(BuildPhaseCode::InternalBuild, 1 + percentage)
-                        .unwrap_or(default);
-                    reporter.phase(phase);
-                }
-                if num_lists > 1 {
-                    pgrx::info!("clustering: iteration {}", i + 1);
-                }
-            },
-            num_lists,
-            num_dims,
-            input,
-            internal_build.spherical_centroids,
-            num_iterations,
-        );
+        let check = |i| {
+            pgrx::check_for_interrupts!();
+            if result.is_empty() {
+                let percentage =
+                    ((i as f64 / num_iterations as f64) * 100.0).clamp(0.0, 100.0) as u16;
+                let default = BuildPhase::from_code(BuildPhaseCode::InternalBuild);
+                let phase = BuildPhase::new(BuildPhaseCode::InternalBuild, 1 + percentage)
+                    .unwrap_or(default);
+                reporter.phase(phase);
+            }
+            if num_lists > 1 {
+                pgrx::info!("clustering: iteration {}", i + 1);
+            }
+        };
+        let means = match input {
+            Ok(input) => k_means::k_means(
+                num_threads,
+                check,
+                num_lists,
+                num_dims,
+                input,
+                internal_build.spherical_centroids,
+                num_iterations,
+            ),
+            Err(input) => k_means::k_means_by_passes(
+                num_threads,
+                check,
+                num_lists,
+                num_dims,
+                input.len(),
+                |f| input.pass(f),
+                internal_build.spherical_centroids,
+                num_iterations,
+            ),
+        };
         if result.is_empty() {
             let percentage = 100;
             let default = BuildPhase::from_code(BuildPhaseCode::InternalBuild);
@@ -1043,6 +1483,84 @@ fn make_internal_build(
     result
 }
 
+fn make_model_build(
+    vector_options: &VectorOptions,
+    model_build: VchordrqModelBuildOptions,
+) -> Vec<Structure<Vec<f32>>> {
+    let hex = model_build.hex.trim_ascii();
+    let hex = hex.strip_prefix("\\x").unwrap_or(hex);
+    if hex.len() % 2 != 0 {
+        pgrx::error!("model build: the model is not valid hex");
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|x| u8::from_str_radix(x, 16).ok())
+        })
+        .collect::<Option<Vec<_>>>();
+    let Some(bytes) = bytes else {
+        pgrx::error!("model build: the model is not valid hex");
+    };
+    match crate::index::model::deserialize(vector_options, &bytes) {
+        Ok(structures) => structures,
+        Err(e) => pgrx::error!("model build: {e}"),
+    }
+}
+
+// Centroids are copied from the tree of the source index, so only vectors are
+// assigned and quantized.
+unsafe fn make_centroids_from_build(
+    index_relation: pgrx::pg_sys::Relation,
+    vector_options: &VectorOptions,
+    name: &str,
+) -> Vec<Structure<Vec<f32>>> {
+    use pgrx::pg_sys::panic::ErrorReportable;
+    let indexrelid = pgrx::spi::Spi::connect(|client| {
+        let name = std::ffi::CString::new(name)
+            .unwrap_or_else(|_| pgrx::error!("centroids_from: the name must not contain NUL"));
+        let name = unsafe { CStr::from_ptr(pgrx::pg_sys::quote_literal_cstr(name.as_ptr())) };
+        let query = format!("SELECT {}::regclass::oid", name.to_string_lossy());
+        client
+            .select(&query, None, &[])
+            .unwrap_or_report()
+            .first()
+            .get_one::<pgrx::pg_sys::Oid>()
+            .unwrap_or_report()
+            .expect("centroids_from: cannot get the index")
+    });
+    if indexrelid == unsafe { (*index_relation).rd_id } {
+        pgrx::error!("centroids_from: the index cannot reuse its own centroids");
+    }
+    let source =
+        unsafe { pgrx::pg_sys::index_open(indexrelid, pgrx::pg_sys::AccessShareLock as _) };
+    if unsafe { (*(*source).rd_rel).relam != (*(*index_relation).rd_rel).relam } {
+        pgrx::error!("centroids_from: the index {name:?} is not a vchordrq index");
+    }
+    let opfamily = unsafe { opfamily(source) };
+    let (v, d) = (opfamily.vector_kind(), opfamily.distance_kind());
+    if (v, d) != (vector_options.v, vector_options.d) {
+        pgrx::error!(
+            "centroids_from: the source index is for {v:?} and {d:?}, but the index is for {:?} and {:?}",
+            vector_options.v,
+            vector_options.d
+        );
+    }
+    let index = unsafe { PostgresRelation::new(source) };
+    let dims = algorithm::cost(index.clone()).dims;
+    if dims != vector_options.dims {
+        pgrx::error!(
+            "centroids_from: the source index has {dims} dimensions, but the index has {} dimensions",
+            vector_options.dims
+        );
+    }
+    let structures = crate::index::algorithm::structures(opfamily, index);
+    unsafe {
+        pgrx::pg_sys::index_close(source, pgrx::pg_sys::AccessShareLock as _);
+    }
+    structures
+}
+
 #[allow(clippy::collapsible_else_if)]
 fn make_external_build(
     vector_options: VectorOptions,
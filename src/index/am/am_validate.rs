@@ -0,0 +1,193 @@
+use pgrx::pg_sys::{Datum, Oid};
+use std::ffi::CStr;
+
+fn check_operator(operator: Oid, strategy: i32, ordering: bool) -> Result<(), String> {
+    let name = unsafe { format_operator(operator) };
+    match (strategy, ordering) {
+        (1 | 3, true) | (2, false) => Ok(()),
+        (1 | 3, false) => Err(format!(
+            "operator {name} with strategy number {strategy} must be an ordering operator"
+        )),
+        (2, true) => Err(format!(
+            "operator {name} with strategy number {strategy} must be a search operator"
+        )),
+        _ => Err(format!(
+            "operator {name} has invalid strategy number {strategy}, which must be 1, 2 or 3"
+        )),
+    }
+}
+
+fn check_function(function: Oid, number: i32) -> Result<(), String> {
+    let name = unsafe { format_procedure(function) };
+    if number != 1 {
+        return Err(format!(
+            "function {name} has invalid support function number {number}, which must be 1"
+        ));
+    }
+    let signature = unsafe {
+        let tuple = pgrx::pg_sys::SearchSysCache1(
+            pgrx::pg_sys::SysCacheIdentifier::PROCOID as _,
+            Datum::from(function),
+        );
+        if tuple.is_null() {
+            pgrx::error!("cache lookup failed for function {}", function.to_u32());
+        }
+        let form = pgrx::pg_sys::heap_tuple_get_struct::<pgrx::pg_sys::FormData_pg_proc>(tuple);
+        let signature = ((*form).pronargs, (*form).prorettype);
+        pgrx::pg_sys::ReleaseSysCache(tuple);
+        signature
+    };
+    if signature != (0, pgrx::pg_sys::TEXTOID) {
+        return Err(format!(
+            "support function 1 {name} must take no arguments and return text"
+        ));
+    }
+    Ok(())
+}
+
+unsafe fn format_operator(operator: Oid) -> String {
+    unsafe { CStr::from_ptr(pgrx::pg_sys::format_operator(operator)) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+unsafe fn format_procedure(function: Oid) -> String {
+    unsafe { CStr::from_ptr(pgrx::pg_sys::format_procedure(function)) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[pgrx::pg_guard]
+pub unsafe extern "C-unwind" fn amvalidate(opclass_oid: Oid) -> bool {
+    use pgrx::pg_sys::{
+        FormData_pg_amop, FormData_pg_amproc, FormData_pg_opclass, SysCacheIdentifier,
+        heap_tuple_get_struct,
+    };
+
+    let (opfamily, input_type, name) = unsafe {
+        let tuple = pgrx::pg_sys::SearchSysCache1(
+            SysCacheIdentifier::CLAOID as _,
+            Datum::from(opclass_oid),
+        );
+        if tuple.is_null() {
+            pgrx::error!(
+                "cache lookup failed for operator class {}",
+                opclass_oid.to_u32()
+            );
+        }
+        let form = heap_tuple_get_struct::<FormData_pg_opclass>(tuple);
+        let name = CStr::from_ptr((*form).opcname.data.as_ptr())
+            .to_string_lossy()
+            .into_owned();
+        let result = ((*form).opcfamily, (*form).opcintype, name);
+        pgrx::pg_sys::ReleaseSysCache(tuple);
+        result
+    };
+
+    let mut errors = Vec::new();
+    let mut has_ordering_operator = false;
+    let mut has_support_function = false;
+
+    unsafe {
+        let list = pgrx::pg_sys::SearchSysCacheList(
+            SysCacheIdentifier::AMOPSTRATEGY as _,
+            1,
+            Datum::from(opfamily),
+            Datum::null(),
+            Datum::null(),
+        );
+        for &member in (*list).members.as_slice((*list).n_members as usize) {
+            let form = heap_tuple_get_struct::<FormData_pg_amop>(&raw mut (*member).tuple);
+            let strategy = (*form).amopstrategy as i32;
+            let ordering = (*form).amoppurpose as u8 == pgrx::pg_sys::AMOP_ORDER;
+            if let Err(e) = check_operator((*form).amopopr, strategy, ordering) {
+                errors.push(e);
+            }
+            if ordering && (*form).amoplefttype == input_type {
+                has_ordering_operator = true;
+            }
+        }
+        pgrx::pg_sys::ReleaseCatCacheList(list);
+    }
+
+    unsafe {
+        let list = pgrx::pg_sys::SearchSysCacheList(
+            SysCacheIdentifier::AMPROCNUM as _,
+            1,
+            Datum::from(opfamily),
+            Datum::null(),
+            Datum::null(),
+        );
+        for &member in (*list).members.as_slice((*list).n_members as usize) {
+            let form = heap_tuple_get_struct::<FormData_pg_amproc>(&raw mut (*member).tuple);
+            if let Err(e) = check_function((*form).amproc, (*form).amprocnum as i32) {
+                errors.push(e);
+            }
+            if (*form).amprocnum == 1
+                && (*form).amproclefttype == input_type
+                && (*form).amprocrighttype == input_type
+            {
+                has_support_function = true;
+            }
+        }
+        pgrx::pg_sys::ReleaseCatCacheList(list);
+    }
+
+    if !has_support_function {
+        errors.push("support function 1 is missing".to_string());
+    }
+    if !has_ordering_operator {
+        errors.push("ordering operator is missing".to_string());
+    }
+    for e in errors.iter() {
+        pgrx::info!("operator class \"{name}\" of access method vchordrq is invalid: {e}");
+    }
+    errors.is_empty()
+}
+
+#[cfg(any(feature = "pg14", feature = "pg15", feature = "pg16", feature = "pg17"))]
+#[pgrx::pg_guard]
+pub unsafe extern "C-unwind" fn amadjustmembers(
+    _opfamily_oid: Oid,
+    opclass_oid: Oid,
+    operators: *mut pgrx::pg_sys::List,
+    functions: *mut pgrx::pg_sys::List,
+) {
+    unsafe fn members<'a>(
+        list: *mut pgrx::pg_sys::List,
+    ) -> impl Iterator<Item = &'a pgrx::pg_sys::OpFamilyMember> {
+        let n = if list.is_null() {
+            0
+        } else {
+            unsafe { (*list).length as usize }
+        };
+        (0..n).map(move |i| unsafe {
+            &*((*(*list).elements.add(i)).ptr_value as *const pgrx::pg_sys::OpFamilyMember)
+        })
+    }
+
+    let mut has_ordering_operator = false;
+    let mut has_support_function = false;
+    for member in unsafe { members(operators) } {
+        let ordering = member.sortfamily != Oid::INVALID;
+        if let Err(e) = check_operator(member.object, member.number, ordering) {
+            pgrx::error!("{e}");
+        }
+        has_ordering_operator |= ordering;
+    }
+    for member in unsafe { members(functions) } {
+        if let Err(e) = check_function(member.object, member.number) {
+            pgrx::error!("{e}");
+        }
+        has_support_function = true;
+    }
+    // `opclass_oid` is invalid for `ALTER OPERATOR FAMILY`, which may add members one by one.
+    if opclass_oid != Oid::INVALID {
+        if !has_support_function {
+            pgrx::error!("support function 1 is missing");
+        }
+        if !has_ordering_operator {
+            pgrx::error!("ordering operator is missing");
+        }
+    }
+}
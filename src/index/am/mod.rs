@@ -1,4 +1,5 @@
 pub mod am_build;
+pub mod am_validate;
 
 use super::algorithm::BumpAlloc;
 use super::gucs::prererank_filtering;
@@ -6,7 +7,7 @@ use crate::index::gucs;
 use crate::index::lazy_cell::LazyCell;
 use crate::index::opclass::{Opfamily, opfamily};
 use crate::index::scanners::*;
-use crate::index::storage::PostgresRelation;
+use crate::index::storage::{PostgresRelation, pages_read};
 use algorithm::Bump;
 use pgrx::datum::Internal;
 use pgrx::pg_sys::{BlockIdData, Datum, ItemPointerData};
@@ -14,6 +15,7 @@ use std::ffi::CStr;
 use std::num::NonZero;
 use std::ptr::NonNull;
 use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 #[repr(C)]
 struct Reloption {
@@ -75,6 +77,7 @@ const AM_HANDLER: pgrx::pg_sys::IndexAmRoutine = const {
 
     am_routine.amsupport = 1;
     am_routine.amcanorderbyop = true;
+    am_routine.amcanparallel = true;
 
     #[cfg(feature = "pg17")]
     {
@@ -90,7 +93,11 @@ const AM_HANDLER: pgrx::pg_sys::IndexAmRoutine = const {
     // and throw errors if someone really wants such a path.
     am_routine.amoptionalkey = true;
 
-    am_routine.amvalidate = Some(amvalidate);
+    am_routine.amvalidate = Some(am_validate::amvalidate);
+    #[cfg(any(feature = "pg14", feature = "pg15", feature = "pg16", feature = "pg17"))]
+    {
+        am_routine.amadjustmembers = Some(am_validate::amadjustmembers);
+    }
     am_routine.amoptions = Some(amoptions);
     am_routine.amcostestimate = Some(amcostestimate);
 
@@ -104,16 +111,16 @@ const AM_HANDLER: pgrx::pg_sys::IndexAmRoutine = const {
     am_routine.ambeginscan = Some(ambeginscan);
     am_routine.amrescan = Some(amrescan);
     am_routine.amgettuple = Some(amgettuple);
+    am_routine.amgetbitmap = Some(amgetbitmap);
     am_routine.amendscan = Some(amendscan);
 
+    am_routine.amestimateparallelscan = Some(amestimateparallelscan);
+    am_routine.aminitparallelscan = Some(aminitparallelscan);
+    am_routine.amparallelrescan = Some(amparallelrescan);
+
     am_routine
 };
 
-#[pgrx::pg_guard]
-pub unsafe extern "C-unwind" fn amvalidate(_opclass_oid: pgrx::pg_sys::Oid) -> bool {
-    true
-}
-
 #[pgrx::pg_guard]
 pub unsafe extern "C-unwind" fn amoptions(
     reloptions: Datum,
@@ -182,9 +189,14 @@ pub unsafe extern "C-unwind" fn amcostestimate(
                     | Opfamily::HalfvecIp
                     | Opfamily::HalfvecL2
                     | Opfamily::VectorCosine
+                    | Opfamily::VectorCosineNormalized
                     | Opfamily::VectorIp
                     | Opfamily::VectorL2
             ) {
+                // maxsim sums similarities of a row over all query vectors, so it can't be
+                // split among participants; the partial path is considered after this one,
+                // so it's not built
+                (*index_opt_info).amcanparallel = false;
                 *index_startup_cost = 0.0;
                 *index_total_cost = 0.0;
                 *index_selectivity = 1.0;
@@ -281,6 +293,7 @@ unsafe fn aminsertinner(
 ) -> bool {
     let opfamily = unsafe { opfamily(index_relation) };
     let index = unsafe { PostgresRelation::new(index_relation) };
+    probe_cache_invalidate(unsafe { (*index_relation).rd_id });
     let datum = unsafe { (!is_null.add(0).read()).then_some(values.add(0).read()) };
     let ctid = unsafe { ctid.read() };
     if let Some(store) = unsafe { datum.and_then(|x| opfamily.store(x)) } {
@@ -308,6 +321,7 @@ pub unsafe extern "C-unwind" fn ambulkdelete(
     }
     let opfamily = unsafe { opfamily((*info).index) };
     let index = unsafe { PostgresRelation::new((*info).index) };
+    probe_cache_invalidate(unsafe { (*(*info).index).rd_id });
     let check = || unsafe {
         pgrx::pg_sys::vacuum_delay_point();
     };
@@ -354,9 +368,19 @@ pub unsafe extern "C-unwind" fn ambeginscan(
         hack: None,
         scanning: LazyCell::new(Box::new(|| Box::new(std::iter::empty()))),
         bump: Box::new(BumpAlloc::new()),
+        stats: Box::new(ScanStats::default()),
+        pages: None,
+        reorder: false,
     };
     unsafe {
         (*scan).opaque = CurrentMemoryContext.leak_and_drop_on_delete(scanner).cast();
+        if n_orderbys > 0 {
+            (*scan).xs_orderbyvals =
+                pgrx::pg_sys::palloc0(size_of::<pgrx::pg_sys::Datum>() * n_orderbys as usize)
+                    .cast();
+            (*scan).xs_orderbynulls =
+                pgrx::pg_sys::palloc0(size_of::<bool>() * n_orderbys as usize).cast();
+        }
     }
     scan
 }
@@ -385,16 +409,26 @@ pub unsafe extern "C-unwind" fn amrescan(
         let scanner = &mut *(*scan).opaque.cast::<Scanner>();
         scanner.scanning = LazyCell::new(Box::new(|| Box::new(std::iter::empty())));
         scanner.bump.reset();
+        *scanner.stats = ScanStats::default();
+        scanner.pages = Some(pages_read());
         let opfamily = opfamily((*scan).indexRelation);
         let index = PostgresRelation::new((*scan).indexRelation);
         let options = SearchOptions {
-            epsilon: gucs::epsilon(),
-            probes: gucs::probes(),
-            max_scan_tuples: gucs::max_scan_tuples(),
-            maxsim_refine: gucs::maxsim_refine(),
-            maxsim_threshold: gucs::maxsim_threshold(),
-            io_rerank: gucs::io_rerank(),
+            parallel: parallel(scan),
+            cache: gucs::probe_cache().then(|| {
+                let relation = (*scan).indexRelation;
+                ((*relation).rd_id, (*(*relation).rd_rel).relfilenode)
+            }),
+            ..search_options()
+        };
+        // estimates are not lower bounds of distances, so results are not reordered
+        // without reranking, and neither are those of maxsim
+        let ctid_reorder = if options.rerank && (*scan).numberOfOrderBys > 0 {
+            gucs::ctid_reorder()
+        } else {
+            0
         };
+        let tie_seed = gucs::tie_seed();
         let fetcher = {
             let hack = scanner.hack;
             LazyCell::new(move || {
@@ -412,18 +446,27 @@ pub unsafe extern "C-unwind" fn amrescan(
         };
         // PAY ATTENTATION: `scanning` references `bump`, so `scanning` must be dropped before `bump`.
         let bump = scanner.bump.as_ref();
+        let stats = scanner.stats.as_ref();
         scanner.scanning = match opfamily {
             Opfamily::VectorL2
             | Opfamily::VectorIp
             | Opfamily::VectorCosine
+            | Opfamily::VectorCosineNormalized
             | Opfamily::HalfvecL2
             | Opfamily::HalfvecIp
             | Opfamily::HalfvecCosine => {
+                scanner.reorder = ctid_reorder != 0;
                 let mut builder = DefaultBuilder::new(opfamily);
+                let opcintype = *(*(*scan).indexRelation).rd_opcintype;
                 for i in 0..(*scan).numberOfOrderBys {
                     let data = (*scan).orderByData.add(i as usize);
-                    let value = (*data).sk_argument;
+                    let mut value = (*data).sk_argument;
                     let is_null = ((*data).sk_flags & pgrx::pg_sys::SK_ISNULL as i32) != 0;
+                    // mixed-type operators, such as `halfvec <=> vector`
+                    let subtype = (*data).sk_subtype;
+                    if !is_null && subtype != pgrx::pg_sys::Oid::INVALID && subtype != opcintype {
+                        value = opfamily.cast_vector(value);
+                    }
                     builder.add((*data).sk_strategy, (!is_null).then_some(value));
                 }
                 for i in 0..(*scan).numberOfKeys {
@@ -435,10 +478,17 @@ pub unsafe extern "C-unwind" fn amrescan(
                 LazyCell::new(Box::new(move || {
                     // only do this since `PostgresRelation` has no destructor
                     let index = bump.alloc(index.clone());
-                    builder.build(index, options, fetcher, bump)
+                    reorder_by_key(
+                        order_ties(
+                            builder.build(index, options, fetcher, bump, stats),
+                            tie_seed,
+                        ),
+                        ctid_reorder,
+                    )
                 }))
             }
             Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim => {
+                scanner.reorder = false;
                 let mut builder = MaxsimBuilder::new(opfamily);
                 for i in 0..(*scan).numberOfOrderBys {
                     let data = (*scan).orderByData.add(i as usize);
@@ -455,13 +505,114 @@ pub unsafe extern "C-unwind" fn amrescan(
                 LazyCell::new(Box::new(move || {
                     // only do this since `PostgresRelation` has no destructor
                     let index = bump.alloc(index.clone());
-                    builder.build(index, options, fetcher, bump)
+                    reorder_by_key(
+                        order_ties(
+                            builder.build(index, options, fetcher, bump, stats),
+                            tie_seed,
+                        ),
+                        0,
+                    )
                 }))
             }
         };
     }
 }
 
+pub fn search_options() -> SearchOptions {
+    let adaptive_epsilon = gucs::adaptive_epsilon();
+    let mut probes = gucs::probes();
+    if let (Some(_), Some(max_probes)) = (adaptive_epsilon, gucs::adaptive_max_probes()) {
+        if let Some(bottom) = probes.first_mut() {
+            *bottom = max_probes;
+        }
+    }
+    SearchOptions {
+        epsilon: gucs::epsilon(),
+        probes,
+        max_scan_tuples: gucs::max_scan_tuples(),
+        adaptive_epsilon,
+        max_per_list: gucs::max_per_list(),
+        scan_work_mem: gucs::scan_work_mem(),
+        maxsim_refine: gucs::maxsim_refine(),
+        maxsim_threshold: gucs::maxsim_threshold(),
+        io_rerank: gucs::io_rerank(),
+        heap_strategy: gucs::heap_strategy(),
+        incremental: gucs::incremental(),
+        rerank: gucs::rerank(),
+        rerank_precision: gucs::rerank_precision(),
+        scan_timeout: gucs::scan_timeout(),
+        cache: None,
+        parallel: None,
+    }
+}
+
+// Runs the same search as `ORDER BY` with `vector`, but outside of an index scan.
+pub unsafe fn search<T>(
+    index_relation: pgrx::pg_sys::Relation,
+    heap_relation: pgrx::pg_sys::Relation,
+    snapshot: pgrx::pg_sys::Snapshot,
+    options: SearchOptions,
+    vector: algorithm::types::OwnedVector,
+    f: impl FnOnce(&mut dyn Iterator<Item = (f32, [u16; 3])>) -> T,
+) -> T {
+    let opfamily = unsafe { opfamily(index_relation) };
+    let index = unsafe { PostgresRelation::new(index_relation) };
+    let fetcher = LazyCell::new(move || unsafe {
+        HeapFetcher::new(
+            index_relation,
+            heap_relation,
+            snapshot,
+            std::ptr::null_mut(),
+        )
+    });
+    let bump = BumpAlloc::new();
+    let stats = ScanStats::default();
+    let mut builder = DefaultBuilder::new(opfamily);
+    builder.push(vector);
+    let iter = builder.build(&index, options, fetcher, &bump, &stats);
+    f(&mut iter.map(|(distance, key, _)| (distance, key)))
+}
+
+// Calls `f` with the indexed value of each row of `keys` that is visible to `snapshot`.
+pub unsafe fn fetch(
+    index_relation: pgrx::pg_sys::Relation,
+    heap_relation: pgrx::pg_sys::Relation,
+    snapshot: pgrx::pg_sys::Snapshot,
+    keys: impl IntoIterator<Item = [u16; 3]>,
+    mut f: impl FnMut([u16; 3], Option<Datum>),
+) {
+    let mut fetcher = unsafe {
+        HeapFetcher::new(
+            index_relation,
+            heap_relation,
+            snapshot,
+            std::ptr::null_mut(),
+        )
+    };
+    for key in keys {
+        if let Some((datums, is_nulls)) = fetcher.fetch(key) {
+            f(key, (!is_nulls[0]).then_some(datums[0]));
+        }
+    }
+}
+
+pub unsafe fn is_visible(
+    heap_relation: pgrx::pg_sys::Relation,
+    snapshot: pgrx::pg_sys::Snapshot,
+    key: [u16; 3],
+) -> bool {
+    unsafe {
+        let mut ctid = key_to_ctid(key);
+        let fetch_row_version = (*(*heap_relation).rd_tableam)
+            .tuple_fetch_row_version
+            .expect("unsupported heap access method");
+        let slot = pgrx::pg_sys::table_slot_create(heap_relation, std::ptr::null_mut());
+        let result = fetch_row_version(heap_relation, &mut ctid, snapshot, slot);
+        pgrx::pg_sys::ExecDropSingleTupleTableSlot(slot);
+        result
+    }
+}
+
 #[pgrx::pg_guard]
 pub unsafe extern "C-unwind" fn amgettuple(
     scan: pgrx::pg_sys::IndexScanDesc,
@@ -479,11 +630,19 @@ pub unsafe extern "C-unwind" fn amgettuple(
         pgrx::error!("scanning with a non-MVCC-compliant snapshot is not supported");
     }
     let scanner = unsafe { (*scan).opaque.cast::<Scanner>().as_mut().unwrap_unchecked() };
-    if let Some((_, key, recheck)) = LazyCell::force_mut(&mut scanner.scanning).next() {
+    if let Some((distance, key, recheck)) = LazyCell::force_mut(&mut scanner.scanning).next() {
         unsafe {
             (*scan).xs_heaptid = key_to_ctid(key);
             (*scan).xs_recheck = recheck;
-            (*scan).xs_recheckorderby = false;
+            // the distance is a lower bound, so the executor computes the exact one and
+            // returns a row only if no row after it can be nearer
+            if scanner.reorder {
+                *(*scan).xs_orderbyvals = pgrx::IntoDatum::into_datum(distance as f64).unwrap();
+                *(*scan).xs_orderbynulls = false;
+                (*scan).xs_recheckorderby = true;
+            } else {
+                (*scan).xs_recheckorderby = false;
+            }
         }
         true
     } else {
@@ -491,11 +650,86 @@ pub unsafe extern "C-unwind" fn amgettuple(
     }
 }
 
+// A bitmap is for candidate generation: rows within the radius are added, but
+// their order is lost, so `ORDER BY` still needs an index scan.
+#[pgrx::pg_guard]
+pub unsafe extern "C-unwind" fn amgetbitmap(
+    scan: pgrx::pg_sys::IndexScanDesc,
+    tbm: *mut pgrx::pg_sys::TIDBitmap,
+) -> i64 {
+    if unsafe { (*(*scan).xs_snapshot).snapshot_type } != pgrx::pg_sys::SnapshotType::SNAPSHOT_MVCC
+    {
+        pgrx::error!("scanning with a non-MVCC-compliant snapshot is not supported");
+    }
+    let scanner = unsafe { (*scan).opaque.cast::<Scanner>().as_mut().unwrap_unchecked() };
+    let mut count = 0_i64;
+    for (_, key, recheck) in LazyCell::force_mut(&mut scanner.scanning) {
+        let mut ctid = key_to_ctid(key);
+        unsafe {
+            pgrx::pg_sys::tbm_add_tuples(tbm, &mut ctid, 1, recheck);
+        }
+        count += 1;
+    }
+    count
+}
+
 #[pgrx::pg_guard]
 pub unsafe extern "C-unwind" fn amendscan(scan: pgrx::pg_sys::IndexScanDesc) {
     let scanner = unsafe { &mut *(*scan).opaque.cast::<Scanner>() };
     scanner.scanning = LazyCell::new(Box::new(|| Box::new(std::iter::empty())));
     scanner.bump.reset();
+    if let Some(pages) = scanner.pages.take() {
+        let report = ScanReport {
+            lists: scanner.stats.lists.get(),
+            candidates: scanner.stats.candidates.get(),
+            reranked: scanner.stats.reranked.get(),
+            pages: pages_read() - pages,
+            centroids: scanner.stats.centroids.get(),
+        };
+        record_global_report(report);
+        set_last_scan_report(report);
+    }
+}
+
+// Participants of a parallel scan share a counter to claim lists.
+#[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15", feature = "pg16"))]
+#[pgrx::pg_guard]
+pub unsafe extern "C-unwind" fn amestimateparallelscan() -> pgrx::pg_sys::Size {
+    size_of::<AtomicU32>()
+}
+
+#[cfg(feature = "pg17")]
+#[pgrx::pg_guard]
+pub unsafe extern "C-unwind" fn amestimateparallelscan(
+    _n_keys: std::os::raw::c_int,
+    _n_orderbys: std::os::raw::c_int,
+) -> pgrx::pg_sys::Size {
+    size_of::<AtomicU32>()
+}
+
+#[pgrx::pg_guard]
+pub unsafe extern "C-unwind" fn aminitparallelscan(target: *mut std::os::raw::c_void) {
+    unsafe {
+        target.cast::<AtomicU32>().write(AtomicU32::new(0));
+    }
+}
+
+#[pgrx::pg_guard]
+pub unsafe extern "C-unwind" fn amparallelrescan(scan: pgrx::pg_sys::IndexScanDesc) {
+    if let Some(next) = unsafe { parallel(scan) } {
+        next.store(0, Ordering::Relaxed);
+    }
+}
+
+unsafe fn parallel(scan: pgrx::pg_sys::IndexScanDesc) -> Option<&'static AtomicU32> {
+    unsafe {
+        let parallel_scan = (*scan).parallel_scan;
+        if parallel_scan.is_null() {
+            return None;
+        }
+        let target = parallel_scan.cast::<u8>().add((*parallel_scan).ps_offset);
+        Some(&*target.cast::<AtomicU32>())
+    }
 }
 
 type Iter = Box<dyn Iterator<Item = (f32, [u16; 3], bool)>>;
@@ -504,6 +738,10 @@ pub struct Scanner {
     pub hack: Option<NonNull<pgrx::pg_sys::IndexScanState>>,
     scanning: LazyCell<Iter, Box<dyn FnOnce() -> Iter>>,
     bump: Box<BumpAlloc>,
+    stats: Box<ScanStats>,
+    pages: Option<u64>,
+    // results are reordered by ctids, so the executor restores the order by distances
+    reorder: bool,
 }
 
 struct HeapFetcher {
@@ -643,6 +881,9 @@ pub const fn key_to_ctid([bi_hi, bi_lo, ip_posid]: [u16; 3]) -> ItemPointerData
     }
 }
 
+// `extra` of a code that stands for all rows with the same vector
+pub const ALIAS: u16 = u16::MAX;
+
 pub const fn pointer_to_kv(pointer: NonZero<u64>) -> ([u16; 3], u16) {
     let value = pointer.get();
     let bi_hi = ((value >> 48) & 0xffff) as u16;
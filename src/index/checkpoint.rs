@@ -0,0 +1,106 @@
+use algorithm::types::{Structure, VectorOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+// A checkpoint is the model trained by an internal build, written in the format
+// of `vchord_export_model`. An index is built into a new relation file that is
+// discarded if the build fails, so only the trained model survives a crash, and a
+// restarted build inserts all rows again; progress of insertion is not persisted.
+// The checkpoint is found by the table, the indexed column and the options, so a
+// build that is restarted with them unchanged resumes after clustering.
+//
+// The working directory of a backend is the data directory.
+const DIRECTORY: &str = "pg_vchord";
+
+pub struct Key {
+    pub database: u32,
+    pub table: u32,
+    pub fingerprint: String,
+}
+
+// the database and the table are in the name, so checkpoints of dropped tables are found
+fn path(key: &Key) -> PathBuf {
+    let mut hash = 0xcbf29ce484222325_u64;
+    for byte in key.fingerprint.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    PathBuf::from(DIRECTORY).join(format!("{}_{}_{hash:016x}.model", key.database, key.table))
+}
+
+pub fn load(vector_options: &VectorOptions, key: &Key) -> Option<Vec<Structure<Vec<f32>>>> {
+    let bytes = std::fs::read(path(key)).ok()?;
+    match crate::index::model::deserialize(vector_options, &bytes) {
+        Ok(structures) => Some(structures),
+        Err(e) => {
+            pgrx::warning!("checkpoint: the checkpoint is ignored, since {e}");
+            None
+        }
+    }
+}
+
+pub fn save(vector_options: &VectorOptions, key: &Key, structures: &[Structure<Vec<f32>>]) {
+    let path = path(key);
+    let temporary = path.with_extension("tmp");
+    let bytes = crate::index::model::serialize(vector_options, structures);
+    // the file is renamed only after it's synced, so a checkpoint is never partial
+    let result = (|| {
+        std::fs::create_dir_all(DIRECTORY)?;
+        let mut file = std::fs::File::create(&temporary)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+        std::fs::rename(&temporary, &path)?;
+        std::fs::File::open(DIRECTORY)?.sync_all()
+    })();
+    if let Err(e) = result {
+        pgrx::warning!("checkpoint: failed to write the checkpoint: {e}");
+    }
+}
+
+pub fn remove(key: &Key) {
+    let _ = std::fs::remove_file(path(key));
+}
+
+// A checkpoint is left behind if its build is never retried, so checkpoints of
+// dropped tables are removed here. Those of other databases are kept, since their
+// tables are not visible.
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_checkpoint_cleanup() -> i64 {
+    if !unsafe { pgrx::pg_sys::superuser() } {
+        pgrx::error!("must be superuser to remove checkpoints");
+    }
+    let database = unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32();
+    let entries = match std::fs::read_dir(DIRECTORY) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return 0,
+        Err(e) => pgrx::error!("could not read the directory {DIRECTORY:?}: {e}"),
+    };
+    let mut removed = 0_i64;
+    for entry in entries {
+        let entry = entry
+            .unwrap_or_else(|e| pgrx::error!("could not read the directory {DIRECTORY:?}: {e}"));
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        let mut parts = name.splitn(3, '_');
+        let (Some(a), Some(b)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        let (Ok(a), Ok(b)) = (a.parse::<u32>(), b.parse::<u32>()) else {
+            continue;
+        };
+        if a != database {
+            continue;
+        }
+        let relname = unsafe { pgrx::pg_sys::get_rel_name(pgrx::pg_sys::Oid::from(b)) };
+        if !relname.is_null() {
+            continue;
+        }
+        if let Err(e) = std::fs::remove_file(entry.path()) {
+            pgrx::error!("could not remove the checkpoint {:?}: {e}", entry.path());
+        }
+        removed += 1;
+    }
+    removed
+}
@@ -0,0 +1,281 @@
+use algorithm::Dumped;
+use algorithm::types::{DistanceKind, Structure, VectorKind, VectorOptions};
+use std::io::{Read, Write};
+use std::num::NonZero;
+
+// A dump is the configuration of an index, its tree of centroids, its lists with
+// their codes and then groups of rows sharing a code. None of them depends on the
+// layout of pages, so a dump is read by versions with other layouts. As in a model,
+// centroids, codes and vectors are in the projected space, since the projection is
+// fixed. Codes refer to rows by ctids, so a dump is only valid for the table it's
+// taken from, which is identified by its oid and relfilenode.
+//
+// The layout is little-endian: the magic, the version, dims, vector kind, distance
+// kind, `is_residual`, `rerank_in_heap`, `sort_lists`, `code_alignment`, the oid and
+// the relfilenode of the table, the tree as a model with its length, and then
+// records, each as its tag followed by its fields, till the end tag.
+const MAGIC: &[u8; 8] = b"vcrqdump";
+const VERSION: u32 = 1;
+
+const TAG_END: u8 = 0;
+const TAG_LIST: u8 = 1;
+const TAG_CODE: u8 = 2;
+const TAG_ALIAS: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub dims: u32,
+    pub v: VectorKind,
+    pub d: DistanceKind,
+    pub is_residual: bool,
+    pub rerank_in_heap: bool,
+    pub sort_lists: bool,
+    pub code_alignment: u16,
+    pub table: (u32, u32),
+}
+
+pub enum Record {
+    Dumped(Dumped<Vec<f32>>),
+    Alias(NonZero<u64>, Vec<NonZero<u64>>),
+}
+
+pub struct Writer<W> {
+    inner: W,
+    header: Header,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(
+        mut inner: W,
+        header: Header,
+        structures: &[Structure<Vec<f32>>],
+    ) -> std::io::Result<Self> {
+        inner.write_all(MAGIC)?;
+        inner.write_all(&VERSION.to_le_bytes())?;
+        inner.write_all(&header.dims.to_le_bytes())?;
+        inner.write_all(&[
+            header.v as u8,
+            header.d as u8,
+            header.is_residual as u8,
+            header.rerank_in_heap as u8,
+            header.sort_lists as u8,
+        ])?;
+        inner.write_all(&header.code_alignment.to_le_bytes())?;
+        inner.write_all(&header.table.0.to_le_bytes())?;
+        inner.write_all(&header.table.1.to_le_bytes())?;
+        let vector_options = VectorOptions {
+            dims: header.dims,
+            v: header.v,
+            d: header.d,
+        };
+        let model = crate::index::model::serialize(&vector_options, structures);
+        inner.write_all(&(model.len() as u64).to_le_bytes())?;
+        inner.write_all(&model)?;
+        Ok(Self { inner, header })
+    }
+    pub fn push(&mut self, record: &Record) -> std::io::Result<()> {
+        let inner = &mut self.inner;
+        match record {
+            Record::Dumped(Dumped::List { radius }) => {
+                inner.write_all(&[TAG_LIST])?;
+                inner.write_all(&radius.to_le_bytes())?;
+            }
+            Record::Dumped(Dumped::Code {
+                payload,
+                code,
+                vector,
+            }) => {
+                assert_eq!(code.signs.len(), self.header.dims as usize);
+                assert_eq!(vector.is_none(), self.header.rerank_in_heap);
+                inner.write_all(&[TAG_CODE])?;
+                inner.write_all(&payload.get().to_le_bytes())?;
+                for x in [
+                    code.dis_u_2,
+                    code.factor_ppc,
+                    code.factor_ip,
+                    code.factor_err,
+                ] {
+                    inner.write_all(&x.to_le_bytes())?;
+                }
+                let mut signs = vec![0_u8; code.signs.len().div_ceil(8)];
+                for (i, &sign) in code.signs.iter().enumerate() {
+                    signs[i / 8] |= (sign as u8) << (i % 8);
+                }
+                inner.write_all(&signs)?;
+                for x in vector.iter().flatten() {
+                    inner.write_all(&x.to_le_bytes())?;
+                }
+            }
+            Record::Alias(payload, members) => {
+                inner.write_all(&[TAG_ALIAS])?;
+                inner.write_all(&payload.get().to_le_bytes())?;
+                inner.write_all(&(members.len() as u32).to_le_bytes())?;
+                for member in members {
+                    inner.write_all(&member.get().to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.inner.write_all(&[TAG_END])?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+pub struct Reader<R> {
+    inner: R,
+    header: Header,
+    ended: bool,
+}
+
+impl<R: Read> Reader<R> {
+    #[allow(clippy::type_complexity)]
+    pub fn new(mut inner: R) -> Result<(Self, Vec<Structure<Vec<f32>>>), String> {
+        let mut magic = [0_u8; MAGIC.len()];
+        take(&mut inner, &mut magic)?;
+        if &magic != MAGIC {
+            return Err("the file is not a dump".to_string());
+        }
+        if u32::from_le_bytes(array(&mut inner)?) != VERSION {
+            return Err("the version of the dump is not supported".to_string());
+        }
+        let dims = u32::from_le_bytes(array(&mut inner)?);
+        let flags: [u8; 5] = array(&mut inner)?;
+        let v = match flags[0] {
+            0 => VectorKind::Vecf32,
+            1 => VectorKind::Vecf16,
+            _ => return Err("the dump is corrupted".to_string()),
+        };
+        let d = match flags[1] {
+            0 => DistanceKind::L2,
+            1 => DistanceKind::Dot,
+            _ => return Err("the dump is corrupted".to_string()),
+        };
+        let [is_residual, rerank_in_heap, sort_lists] = match [flags[2], flags[3], flags[4]] {
+            x if x.iter().all(|&x| x <= 1) => x.map(|x| x != 0),
+            _ => return Err("the dump is corrupted".to_string()),
+        };
+        let code_alignment = u16::from_le_bytes(array(&mut inner)?);
+        let table = (
+            u32::from_le_bytes(array(&mut inner)?),
+            u32::from_le_bytes(array(&mut inner)?),
+        );
+        let header = Header {
+            dims,
+            v,
+            d,
+            is_residual,
+            rerank_in_heap,
+            sort_lists,
+            code_alignment,
+            table,
+        };
+        let n = u64::from_le_bytes(array(&mut inner)?);
+        let mut model = Vec::new();
+        (&mut inner).take(n).read_to_end(&mut model).map_err(io)?;
+        if model.len() as u64 != n {
+            return Err("the dump is truncated".to_string());
+        }
+        let vector_options = VectorOptions { dims, v, d };
+        let structures = crate::index::model::deserialize(&vector_options, &model)
+            .map_err(|e| format!("the dump is corrupted: {e}"))?;
+        let reader = Self {
+            inner,
+            header,
+            ended: false,
+        };
+        Ok((reader, structures))
+    }
+    pub fn header(&self) -> Header {
+        self.header
+    }
+    pub fn next(&mut self) -> Result<Option<Record>, String> {
+        if self.ended {
+            return Ok(None);
+        }
+        let inner = &mut self.inner;
+        let [tag] = array(inner)?;
+        let payload = |inner: &mut R| {
+            NonZero::new(u64::from_le_bytes(array(inner)?))
+                .ok_or_else(|| "the dump is corrupted".to_string())
+        };
+        match tag {
+            TAG_END => {
+                let mut trailing = [0_u8; 1];
+                if inner.read(&mut trailing).map_err(io)? != 0 {
+                    return Err("the dump has trailing bytes".to_string());
+                }
+                self.ended = true;
+                Ok(None)
+            }
+            TAG_LIST => {
+                let radius = f32::from_le_bytes(array(inner)?);
+                Ok(Some(Record::Dumped(Dumped::List { radius })))
+            }
+            TAG_CODE => {
+                let payload = payload(inner)?;
+                let dis_u_2 = f32::from_le_bytes(array(inner)?);
+                let factor_ppc = f32::from_le_bytes(array(inner)?);
+                let factor_ip = f32::from_le_bytes(array(inner)?);
+                let factor_err = f32::from_le_bytes(array(inner)?);
+                let dims = self.header.dims as usize;
+                let mut signs = vec![0_u8; dims.div_ceil(8)];
+                take(inner, &mut signs)?;
+                let signs = (0..dims)
+                    .map(|i| signs[i / 8] & (1 << (i % 8)) != 0)
+                    .collect();
+                let vector = if !self.header.rerank_in_heap {
+                    let mut vector = Vec::with_capacity(dims);
+                    for _ in 0..dims {
+                        vector.push(f32::from_le_bytes(array(inner)?));
+                    }
+                    Some(vector)
+                } else {
+                    None
+                };
+                let code = rabitq::Code {
+                    dis_u_2,
+                    factor_ppc,
+                    factor_ip,
+                    factor_err,
+                    signs,
+                };
+                Ok(Some(Record::Dumped(Dumped::Code {
+                    payload,
+                    code,
+                    vector,
+                })))
+            }
+            TAG_ALIAS => {
+                let group = payload(inner)?;
+                let n = u32::from_le_bytes(array(inner)?);
+                let mut members = Vec::new();
+                for _ in 0..n {
+                    members.push(payload(inner)?);
+                }
+                Ok(Some(Record::Alias(group, members)))
+            }
+            _ => Err("the dump is corrupted".to_string()),
+        }
+    }
+}
+
+fn io(e: std::io::Error) -> String {
+    if e.kind() == std::io::ErrorKind::UnexpectedEof {
+        "the dump is truncated".to_string()
+    } else {
+        format!("could not read the dump: {e}")
+    }
+}
+
+fn take(inner: &mut impl Read, buffer: &mut [u8]) -> Result<(), String> {
+    inner.read_exact(buffer).map_err(io)
+}
+
+fn array<const N: usize>(inner: &mut impl Read) -> Result<[u8; N], String> {
+    let mut buffer = [0_u8; N];
+    take(inner, &mut buffer)?;
+    Ok(buffer)
+}
@@ -4,6 +4,1611 @@ use pgrx_catalog::{PgAm, PgClass, PgClassRelkind};
 
 #[pgrx::pg_extern(sql = "")]
 fn _vchordrq_prewarm(indexrelid: Oid, height: i32) -> String {
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    crate::index::algorithm::prewarm(opfamily, index, height, || {
+        pgrx::check_for_interrupts!();
+    })
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_compact(indexrelid: Oid) -> i64 {
+    // a scan that has chosen to rerank by original vectors would miss rows whose
+    // vectors are freed, so it conflicts with scans as well
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessExclusiveLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    if matches!(algorithm::how(index.clone()), algorithm::RerankMethod::Heap) {
+        pgrx::error!(
+            "the index {:?} does not store original vectors, or it is already compacted",
+            relation.relname()
+        );
+    }
+    let reclaimed = crate::index::algorithm::compact(opfamily, index, || {
+        pgrx::check_for_interrupts!();
+    });
+    reclaimed as i64
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_reconstruct(
+    indexrelid: Oid,
+    ctid: pgrx::pg_sys::ItemPointerData,
+) -> crate::datatype::memory_vector::VectorOutput {
+    use crate::index::am::{ctid_to_key, kv_to_pointer};
+    use crate::index::opclass::Opfamily;
+    use vector::vect::VectBorrowed;
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    if matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim) {
+        pgrx::error!("reconstructing vectors of a maxsim index is not supported");
+    }
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    let payloads = algorithm::resolve(index.clone(), &[kv_to_pointer((ctid_to_key(ctid), 0))]);
+    let [result] = crate::index::algorithm::reconstruct(opfamily, index, &payloads)
+        .try_into()
+        .unwrap();
+    let Some(result) = result else {
+        pgrx::error!(
+            "the row {:?} is not indexed by {:?}",
+            ctid_to_key(ctid),
+            relation.relname()
+        );
+    };
+    crate::datatype::memory_vector::VectorOutput::new(VectBorrowed::new(&result))
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_distances_for(
+    indexrelid: Oid,
+    query: crate::datatype::memory_vector::VectorInput<'_>,
+    ctids: pgrx::Array<'_, pgrx::pg_sys::ItemPointerData>,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(ctid, pgrx::pg_sys::ItemPointerData),
+        pgrx::name!(distance, f64),
+    ),
+> {
+    use crate::index::am::{ctid_to_key, kv_to_pointer};
+    use crate::index::opclass::Opfamily;
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    if matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim) {
+        pgrx::error!("computing distances of a maxsim index is not supported");
+    }
+    let vector = input(&relation, opfamily, query.as_borrowed());
+    let ctids = ctids
+        .iter()
+        .map(|ctid| ctid.unwrap_or_else(|| pgrx::error!("the row id must not be null")))
+        .collect::<Vec<_>>();
+    let payloads = ctids
+        .iter()
+        .map(|&ctid| kv_to_pointer((ctid_to_key(ctid), 0)))
+        .collect::<Vec<_>>();
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    let payloads = algorithm::resolve(index.clone(), &payloads);
+    let distances = crate::index::algorithm::distances(opfamily, index, vector, &payloads);
+    let mut results = Vec::with_capacity(ctids.len());
+    for (ctid, distance) in ctids.into_iter().zip(distances) {
+        let Some(distance) = distance else {
+            pgrx::error!(
+                "the row {:?} is not indexed by {:?}",
+                ctid_to_key(ctid),
+                relation.relname()
+            );
+        };
+        results.push((ctid, distance as f64));
+    }
+    pgrx::iter::TableIterator::new(results)
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_multi_query_distances(
+    indexrelid: Oid,
+    queries: pgrx::Array<'_, crate::datatype::memory_vector::VectorInput<'_>>,
+    ctids: pgrx::Array<'_, pgrx::pg_sys::ItemPointerData>,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(query_idx, i32),
+        pgrx::name!(ctid, pgrx::pg_sys::ItemPointerData),
+        pgrx::name!(distance, f64),
+    ),
+> {
+    use crate::index::am::{ctid_to_key, kv_to_pointer};
+    use crate::index::opclass::Opfamily;
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    if matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim) {
+        pgrx::error!("computing distances of a maxsim index is not supported");
+    }
+    let vectors = queries
+        .iter()
+        .map(|query| {
+            let query = query.unwrap_or_else(|| pgrx::error!("the query must not be null"));
+            input(&relation, opfamily, query.as_borrowed())
+        })
+        .collect::<Vec<_>>();
+    let ctids = ctids
+        .iter()
+        .map(|ctid| ctid.unwrap_or_else(|| pgrx::error!("the row id must not be null")))
+        .collect::<Vec<_>>();
+    let payloads = ctids
+        .iter()
+        .map(|&ctid| kv_to_pointer((ctid_to_key(ctid), 0)))
+        .collect::<Vec<_>>();
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    let payloads = algorithm::resolve(index.clone(), &payloads);
+    let n = vectors.len();
+    let distances = crate::index::algorithm::multi_distances(opfamily, index, vectors, &payloads);
+    let mut results = Vec::with_capacity(n * ctids.len());
+    for (ctid, distances) in ctids.into_iter().zip(distances) {
+        let Some(distances) = distances else {
+            pgrx::error!(
+                "the row {:?} is not indexed by {:?}",
+                ctid_to_key(ctid),
+                relation.relname()
+            );
+        };
+        for (i, distance) in distances.into_iter().enumerate() {
+            results.push((i as i32 + 1, ctid, distance as f64));
+        }
+    }
+    pgrx::iter::TableIterator::new(results)
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_row_distance(
+    indexrelid: Oid,
+    a: pgrx::pg_sys::ItemPointerData,
+    b: pgrx::pg_sys::ItemPointerData,
+) -> f64 {
+    use crate::index::am::{ctid_to_key, kv_to_pointer};
+    use crate::index::opclass::Opfamily;
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    if matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim) {
+        pgrx::error!("computing distances of a maxsim index is not supported");
+    }
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    let [lhs, rhs] = algorithm::resolve(
+        index.clone(),
+        &[
+            kv_to_pointer((ctid_to_key(a), 0)),
+            kv_to_pointer((ctid_to_key(b), 0)),
+        ],
+    )
+    .try_into()
+    .unwrap();
+    match crate::index::algorithm::row_distance(opfamily, index, lhs, rhs) {
+        Ok(distance) => distance as f64,
+        Err(payload) => pgrx::error!(
+            "the row {:?} is not indexed by {:?}",
+            ctid_to_key(if payload == lhs { a } else { b }),
+            relation.relname()
+        ),
+    }
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_knn_after(
+    indexrelid: Oid,
+    query: crate::datatype::memory_vector::VectorInput<'_>,
+    k: i32,
+    after_distance: f64,
+    after_ctid: pgrx::pg_sys::ItemPointerData,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(ctid, pgrx::pg_sys::ItemPointerData),
+        pgrx::name!(distance, f64),
+    ),
+> {
+    use crate::index::am::{ctid_to_key, key_to_ctid};
+    use crate::index::opclass::Opfamily;
+    if k <= 0 {
+        pgrx::error!("k must be positive");
+    }
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    if matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim) {
+        pgrx::error!("pagination over a maxsim index is not supported");
+    }
+    let vector = input(&relation, opfamily, query.as_borrowed());
+    let heap = Table::open(
+        unsafe { (*(*relation.raw()).rd_index).indrelid },
+        pgrx::pg_sys::AccessShareLock as _,
+    );
+    let snapshot = unsafe { pgrx::pg_sys::GetActiveSnapshot() };
+    // rows are totally ordered by (distance, ctid), and the cursor is the last row of
+    // the previous page
+    let after = (after_distance, ctid_to_key(after_ctid));
+    let options = crate::index::am::search_options();
+    let mut results = unsafe {
+        crate::index::am::search(
+            relation.raw(),
+            heap.raw(),
+            snapshot,
+            options,
+            vector,
+            |iter| {
+                let mut results = Vec::<(f64, [u16; 3])>::new();
+                for (distance, key) in iter {
+                    let x = (distance as f64, key);
+                    if x <= after {
+                        continue;
+                    }
+                    // distances come in order, so the scan stops once all rows tied
+                    // with the `k`-th are found
+                    if results.len() >= k as usize && results[results.len() - 1].0 < x.0 {
+                        break;
+                    }
+                    if crate::index::am::is_visible(heap.raw(), snapshot, key) {
+                        results.push(x);
+                    }
+                }
+                results
+            },
+        )
+    };
+    results.sort_by(|x, y| x.0.total_cmp(&y.0).then(x.1.cmp(&y.1)));
+    let results = results
+        .into_iter()
+        .take(k as usize)
+        .map(|(distance, key)| (key_to_ctid(key), distance))
+        .collect::<Vec<_>>();
+    pgrx::iter::TableIterator::new(results)
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_knn_distinct(
+    indexrelid: Oid,
+    query: crate::datatype::memory_vector::VectorInput<'_>,
+    k: i32,
+    group_attr: &str,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(ctid, pgrx::pg_sys::ItemPointerData),
+        pgrx::name!(distance, f64),
+    ),
+> {
+    use crate::index::am::key_to_ctid;
+    use crate::index::opclass::Opfamily;
+    use std::collections::HashSet;
+    use std::ffi::{CStr, CString};
+    if k <= 0 {
+        pgrx::error!("k must be positive");
+    }
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    if matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim) {
+        pgrx::error!("distinct search over a maxsim index is not supported");
+    }
+    let vector = input(&relation, opfamily, query.as_borrowed());
+    let heaprelid = unsafe { (*(*relation.raw()).rd_index).indrelid };
+    let Ok(name) = CString::new(group_attr) else {
+        pgrx::error!("column {:?} does not exist", group_attr);
+    };
+    let attnum = unsafe { pgrx::pg_sys::get_attnum(heaprelid, name.as_ptr()) };
+    if attnum <= 0 {
+        pgrx::error!("column {:?} does not exist", group_attr);
+    }
+    let (mut typoutput, mut typisvarlena) = (Oid::INVALID, false);
+    unsafe {
+        let atttype = pgrx::pg_sys::get_atttype(heaprelid, attnum);
+        pgrx::pg_sys::getTypeOutputInfo(atttype, &mut typoutput, &mut typisvarlena);
+    }
+    let heap = Table::open(heaprelid, pgrx::pg_sys::AccessShareLock as _);
+    let snapshot = unsafe { pgrx::pg_sys::GetActiveSnapshot() };
+    // groups are compared by the text representations of their keys, and NULL is a group
+    let group = |key: [u16; 3]| unsafe {
+        let heap = heap.raw();
+        let mut ctid = key_to_ctid(key);
+        let fetch_row_version = (*(*heap).rd_tableam)
+            .tuple_fetch_row_version
+            .expect("unsupported heap access method");
+        let slot = pgrx::pg_sys::table_slot_create(heap, std::ptr::null_mut());
+        let result = if fetch_row_version(heap, &mut ctid, snapshot, slot) {
+            pgrx::pg_sys::slot_getsomeattrs_int(slot, attnum as _);
+            let i = attnum as usize - 1;
+            if *(*slot).tts_isnull.add(i) {
+                Some(None)
+            } else {
+                let value = *(*slot).tts_values.add(i);
+                let text = pgrx::pg_sys::OidOutputFunctionCall(typoutput, value);
+                let owned = CStr::from_ptr(text).to_bytes().to_vec();
+                pgrx::pg_sys::pfree(text.cast());
+                Some(Some(owned))
+            }
+        } else {
+            None
+        };
+        pgrx::pg_sys::ExecDropSingleTupleTableSlot(slot);
+        result
+    };
+    let options = crate::index::am::search_options();
+    let results = unsafe {
+        crate::index::am::search(
+            relation.raw(),
+            heap.raw(),
+            snapshot,
+            options,
+            vector,
+            |iter| {
+                let mut seen = HashSet::new();
+                iter.filter(|&(_, key)| group(key).is_some_and(|group| seen.insert(group)))
+                    .take(k as usize)
+                    .map(|(distance, key)| (key_to_ctid(key), distance as f64))
+                    .collect::<Vec<_>>()
+            },
+        )
+    };
+    pgrx::iter::TableIterator::new(results)
+}
+
+// Results are picked greedily from the top `4k` results of the search. A pick
+// maximizes `lambda * -d(query, x) + (1 - lambda) * min d(x, picked)`, where `d`
+// is the distance of the operator class, and its score is returned.
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_knn_mmr(
+    indexrelid: Oid,
+    query: crate::datatype::memory_vector::VectorInput<'_>,
+    k: i32,
+    lambda: f64,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(ctid, pgrx::pg_sys::ItemPointerData),
+        pgrx::name!(score, f64),
+    ),
+> {
+    use crate::index::am::key_to_ctid;
+    use crate::index::opclass::Opfamily;
+    use algorithm::types::{DistanceKind, OwnedVector};
+    use distance::Distance;
+    use half::f16;
+    use simd::Floating;
+    use std::collections::HashMap;
+    const POOL: usize = 4;
+    if k <= 0 {
+        pgrx::error!("k must be positive");
+    }
+    if !(0.0..=1.0).contains(&lambda) {
+        pgrx::error!("lambda must be between 0 and 1");
+    }
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    if matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim) {
+        pgrx::error!("diverse search over a maxsim index is not supported");
+    }
+    let vector = input(&relation, opfamily, query.as_borrowed());
+    let heap = Table::open(
+        unsafe { (*(*relation.raw()).rd_index).indrelid },
+        pgrx::pg_sys::AccessShareLock as _,
+    );
+    let snapshot = unsafe { pgrx::pg_sys::GetActiveSnapshot() };
+    let options = crate::index::am::search_options();
+    let pool = unsafe {
+        crate::index::am::search(
+            relation.raw(),
+            heap.raw(),
+            snapshot,
+            options,
+            vector,
+            |iter| {
+                iter.filter(|&(_, key)| crate::index::am::is_visible(heap.raw(), snapshot, key))
+                    .take(POOL * k as usize)
+                    .map(|(distance, key)| (key, distance as f64))
+                    .collect::<Vec<_>>()
+            },
+        )
+    };
+    let to_f32 = |x: OwnedVector| match x {
+        OwnedVector::Vecf32(x) => x.slice().to_vec(),
+        OwnedVector::Vecf16(x) => f16::vector_to_f32(x.slice()),
+    };
+    let mut vectors = HashMap::new();
+    unsafe {
+        crate::index::am::fetch(
+            relation.raw(),
+            heap.raw(),
+            snapshot,
+            pool.iter().map(|&(key, _)| key),
+            |key, datum| {
+                if let Some(vector) = datum.and_then(|datum| opfamily.input_vector(datum)) {
+                    vectors.insert(key, to_f32(vector));
+                }
+            },
+        );
+    }
+    let distance = |x: &[f32], y: &[f32]| {
+        let d = match opfamily.distance_kind() {
+            DistanceKind::L2 => f32::reduce_sum_of_d2(x, y),
+            DistanceKind::Dot => -f32::reduce_sum_of_xy(x, y),
+        };
+        opfamily.output(Distance::from_f32(d)) as f64
+    };
+    // the nearest distance of each candidate to picked results
+    let mut remaining = pool
+        .into_iter()
+        .filter_map(|(key, d)| Some((key, d, vectors.remove(&key)?, None::<f64>)))
+        .collect::<Vec<_>>();
+    let mut results = Vec::new();
+    while results.len() < k as usize && !remaining.is_empty() {
+        pgrx::check_for_interrupts!();
+        let score = |&(_, d, _, nearest): &(_, f64, _, Option<f64>)| {
+            lambda * -d + (1.0 - lambda) * nearest.unwrap_or(0.0)
+        };
+        let mut best = 0;
+        for i in 1..remaining.len() {
+            if score(&remaining[i]) > score(&remaining[best]) {
+                best = i;
+            }
+        }
+        let picked = remaining.remove(best);
+        for (_, _, vector, nearest) in remaining.iter_mut() {
+            let d = distance(vector, &picked.2);
+            *nearest = Some(nearest.map_or(d, |x| x.min(d)));
+        }
+        results.push((key_to_ctid(picked.0), score(&picked)));
+    }
+    pgrx::iter::TableIterator::new(results)
+}
+
+// Bounds are the ones used for pruning, so they depend on `vchordrq.epsilon`.
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_knn_bounds(
+    indexrelid: Oid,
+    query: crate::datatype::memory_vector::VectorInput<'_>,
+    k: i32,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(ctid, pgrx::pg_sys::ItemPointerData),
+        pgrx::name!(est_distance, f64),
+        pgrx::name!(lower_bound, f64),
+        pgrx::name!(upper_bound, f64),
+    ),
+> {
+    use crate::index::am::{ALIAS, key_to_ctid, pointer_to_kv};
+    use crate::index::opclass::Opfamily;
+    use algorithm::types::DistanceKind;
+    use distance::Distance;
+    if k <= 0 {
+        pgrx::error!("k must be positive");
+    }
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    if matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim) {
+        pgrx::error!("bounds over a maxsim index are not supported");
+    }
+    let vector = input(&relation, opfamily, query.as_borrowed());
+    let heap = Table::open(
+        unsafe { (*(*relation.raw()).rd_index).indrelid },
+        pgrx::pg_sys::AccessShareLock as _,
+    );
+    let snapshot = unsafe { pgrx::pg_sys::GetActiveSnapshot() };
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    let options = crate::index::am::search_options();
+    let mut results = crate::index::algorithm::bounds(
+        opfamily,
+        index.clone(),
+        vector,
+        options.probes,
+        options.epsilon,
+    );
+    results.sort_by_key(|&(_, rough, _)| rough);
+    let mut aliases = None;
+    // squared Euclidean distances are never negative
+    let output = |x: f32| {
+        let x = match opfamily.distance_kind() {
+            DistanceKind::L2 => x.max(0.0),
+            DistanceKind::Dot => x,
+        };
+        opfamily.output(Distance::from_f32(x)) as f64
+    };
+    let results = results
+        .into_iter()
+        .flat_map(|(payload, rough, lowerbound)| {
+            let members = if pointer_to_kv(payload).1 == ALIAS {
+                let aliases = aliases.get_or_insert_with(|| algorithm::Aliases::new(index.clone()));
+                aliases.get(payload)
+            } else {
+                vec![payload]
+            };
+            let (rough, lowerbound) = (rough.to_f32(), lowerbound.to_f32());
+            let upperbound = rough + (rough - lowerbound);
+            members
+                .into_iter()
+                .map(move |member| (pointer_to_kv(member).0, rough, lowerbound, upperbound))
+        })
+        .filter(|&(key, ..)| unsafe { crate::index::am::is_visible(heap.raw(), snapshot, key) })
+        .take(k as usize)
+        .map(|(key, rough, lowerbound, upperbound)| {
+            (
+                key_to_ctid(key),
+                output(rough),
+                output(lowerbound),
+                output(upperbound),
+            )
+        })
+        .collect::<Vec<_>>();
+    pgrx::iter::TableIterator::new(results)
+}
+
+// Estimates are the ones of `vchord_knn_bounds` and exact distances are computed
+// from the table. Errors are grouped into ten buckets of equal width over the
+// range of exact distances, and a bucket is named by its lower end.
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_error_profile(
+    indexrelid: Oid,
+    queries: pgrx::Array<'_, crate::datatype::memory_vector::VectorInput<'_>>,
+    k: i32,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(distance_bucket, f64),
+        pgrx::name!(mean_signed_error, f64),
+        pgrx::name!(count, i64),
+    ),
+> {
+    use crate::index::am::{ALIAS, pointer_to_kv};
+    use crate::index::opclass::Opfamily;
+    use algorithm::types::{DistanceKind, OwnedVector};
+    use distance::Distance;
+    use half::f16;
+    use simd::Floating;
+    use std::collections::HashMap;
+    const BUCKETS: usize = 10;
+    if k <= 0 {
+        pgrx::error!("k must be positive");
+    }
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    if matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim) {
+        pgrx::error!("error profiles of a maxsim index are not supported");
+    }
+    let heap = Table::open(
+        unsafe { (*(*relation.raw()).rd_index).indrelid },
+        pgrx::pg_sys::AccessShareLock as _,
+    );
+    let snapshot = unsafe { pgrx::pg_sys::GetActiveSnapshot() };
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    let to_f32 = |x: OwnedVector| match x {
+        OwnedVector::Vecf32(x) => x.slice().to_vec(),
+        OwnedVector::Vecf16(x) => f16::vector_to_f32(x.slice()),
+    };
+    // squared Euclidean distances are never negative
+    let output = |x: f32| {
+        let x = match opfamily.distance_kind() {
+            DistanceKind::L2 => x.max(0.0),
+            DistanceKind::Dot => x,
+        };
+        opfamily.output(Distance::from_f32(x)) as f64
+    };
+    let aliases = algorithm::Aliases::new(index.clone());
+    let mut samples = Vec::<(f64, f64)>::new();
+    let mut n = 0_u64;
+    for query in queries.iter_deny_null() {
+        pgrx::check_for_interrupts!();
+        let vector = input(&relation, opfamily, query.as_borrowed());
+        let options = crate::index::am::search_options();
+        let mut results = crate::index::algorithm::bounds(
+            opfamily,
+            index.clone(),
+            vector.clone(),
+            options.probes,
+            options.epsilon,
+        );
+        results.sort_by_key(|&(_, rough, _)| rough);
+        let estimates = results
+            .into_iter()
+            .flat_map(|(payload, rough, _)| {
+                let members = if pointer_to_kv(payload).1 == ALIAS {
+                    aliases.get(payload)
+                } else {
+                    vec![payload]
+                };
+                members
+                    .into_iter()
+                    .map(move |member| (pointer_to_kv(member).0, rough.to_f32()))
+            })
+            .take(k as usize)
+            .collect::<HashMap<_, _>>();
+        let query = to_f32(vector);
+        unsafe {
+            crate::index::am::fetch(
+                relation.raw(),
+                heap.raw(),
+                snapshot,
+                estimates.keys().copied(),
+                |key, datum| {
+                    let Some(vector) = datum.and_then(|datum| opfamily.input_vector(datum)) else {
+                        return;
+                    };
+                    let vector = to_f32(vector);
+                    let exact = match opfamily.distance_kind() {
+                        DistanceKind::L2 => f32::reduce_sum_of_d2(&query, &vector),
+                        DistanceKind::Dot => -f32::reduce_sum_of_xy(&query, &vector),
+                    };
+                    let exact = output(exact);
+                    samples.push((exact, output(estimates[&key]) - exact));
+                },
+            );
+        }
+        n += 1;
+    }
+    if n == 0 {
+        pgrx::error!("queries must not be empty");
+    }
+    let min = samples
+        .iter()
+        .map(|&(x, _)| x)
+        .fold(f64::INFINITY, f64::min);
+    let max = samples
+        .iter()
+        .map(|&(x, _)| x)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let width = (max - min) / BUCKETS as f64;
+    let mut buckets = [(0.0_f64, 0_i64); BUCKETS];
+    for (exact, error) in samples {
+        let i = if width > 0.0 {
+            (((exact - min) / width) as usize).min(BUCKETS - 1)
+        } else {
+            0
+        };
+        buckets[i].0 += error;
+        buckets[i].1 += 1;
+    }
+    let results = buckets
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, (_, count))| count != 0)
+        .map(|(i, (sum, count))| (min + width * i as f64, sum / count as f64, count))
+        .collect::<Vec<_>>();
+    pgrx::iter::TableIterator::new(results)
+}
+
+// It returns one row per indexed vector, so the number of rows equals
+// the number of indexed rows, including dead rows not vacuumed yet.
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_assignments(
+    indexrelid: Oid,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(ctid, pgrx::pg_sys::ItemPointerData),
+        pgrx::name!(list_id, i32),
+    ),
+> {
+    use crate::index::am::{ALIAS, key_to_ctid, pointer_to_kv};
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    let aliases = algorithm::Aliases::new(index.clone());
+    let mut results = Vec::new();
+    algorithm::assignments(
+        index,
+        || pgrx::check_for_interrupts!(),
+        |list, payload| {
+            let members = if pointer_to_kv(payload).1 == ALIAS {
+                aliases.get(payload)
+            } else {
+                vec![payload]
+            };
+            for member in members {
+                let (key, _) = pointer_to_kv(member);
+                results.push((key_to_ctid(key), list as i32));
+            }
+        },
+    );
+    pgrx::iter::TableIterator::new(results)
+}
+
+// Codes of rows that are not visible to the snapshot, such as rows inserted after
+// the statement began, are not counted.
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_lists(
+    indexrelid: Oid,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(list_id, i32),
+        pgrx::name!(centroid, crate::datatype::memory_vector::VectorOutput),
+        pgrx::name!(count, i64),
+    ),
+> {
+    use crate::index::am::{ALIAS, pointer_to_kv};
+    use crate::index::opclass::Opfamily;
+    use crate::index::projection::unproject;
+    use vector::vect::VectBorrowed;
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    if matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim) {
+        pgrx::error!("lists of a maxsim index are not supported");
+    }
+    let heap = Table::open(
+        unsafe { (*(*relation.raw()).rd_index).indrelid },
+        pgrx::pg_sys::AccessShareLock as _,
+    );
+    let snapshot = unsafe { pgrx::pg_sys::GetActiveSnapshot() };
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    let aliases = algorithm::Aliases::new(index.clone());
+    let mut results = Vec::new();
+    for (list, centroid, codes) in crate::index::algorithm::lists(opfamily, index) {
+        let mut count = 0_i64;
+        for code in codes {
+            pgrx::check_for_interrupts!();
+            let members = if pointer_to_kv(code.payload).1 == ALIAS {
+                aliases.get(code.payload)
+            } else {
+                vec![code.payload]
+            };
+            for member in members {
+                let (key, _) = pointer_to_kv(member);
+                count += unsafe { crate::index::am::is_visible(heap.raw(), snapshot, key) } as i64;
+            }
+        }
+        let centroid = unproject(&centroid);
+        let centroid =
+            crate::datatype::memory_vector::VectorOutput::new(VectBorrowed::new(&centroid));
+        results.push((list as i32, centroid, count));
+    }
+    pgrx::iter::TableIterator::new(results)
+}
+
+// Original values are read from the table, since codes of broken vectors can't be decoded.
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_audit(
+    indexrelid: Oid,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(ctid, pgrx::pg_sys::ItemPointerData),
+        pgrx::name!(issue, String),
+    ),
+> {
+    use crate::index::am::{ALIAS, key_to_ctid, pointer_to_kv};
+    use std::collections::BTreeSet;
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    let heap = Table::open(
+        unsafe { (*(*relation.raw()).rd_index).indrelid },
+        pgrx::pg_sys::AccessShareLock as _,
+    );
+    let snapshot = unsafe { pgrx::pg_sys::GetActiveSnapshot() };
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    let dims = algorithm::cost(index.clone()).dims;
+    let aliases = algorithm::Aliases::new(index.clone());
+    let mut keys = BTreeSet::new();
+    algorithm::assignments(
+        index,
+        || pgrx::check_for_interrupts!(),
+        |_, payload| {
+            let members = if pointer_to_kv(payload).1 == ALIAS {
+                aliases.get(payload)
+            } else {
+                vec![payload]
+            };
+            for member in members {
+                keys.insert(pointer_to_kv(member).0);
+            }
+        },
+    );
+    let mut results = Vec::new();
+    unsafe {
+        crate::index::am::fetch(relation.raw(), heap.raw(), snapshot, keys, |key, datum| {
+            pgrx::check_for_interrupts!();
+            if let Some(datum) = datum {
+                for issue in opfamily.audit(datum, dims) {
+                    results.push((key_to_ctid(key), issue));
+                }
+            }
+        });
+    }
+    pgrx::iter::TableIterator::new(results)
+}
+
+// Codes of lists whose offsets are not multiples of `code_alignment` are counted.
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_misaligned_codes(indexrelid: Oid) -> i64 {
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    algorithm::misaligned(index, || pgrx::check_for_interrupts!()) as i64
+}
+
+// Overlaps are the Jaccard indexes of top-k results, which are averaged over queries.
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_compare_indexes(
+    a: Oid,
+    b: Oid,
+    queries: pgrx::Array<'_, crate::datatype::memory_vector::VectorInput<'_>>,
+    k: i32,
+) -> pgrx::iter::TableIterator<'static, (pgrx::name!(mean_overlap, f64), pgrx::name!(count, i64))> {
+    use crate::index::opclass::Opfamily;
+    use std::collections::HashSet;
+    if k <= 0 {
+        pgrx::error!("k must be positive");
+    }
+    let relations = [a, b].map(|x| open_vchordrq_index(x, pgrx::pg_sys::AccessShareLock as _));
+    let opfamilies = relations
+        .each_ref()
+        .map(|x| unsafe { crate::index::opclass::opfamily(x.raw()) });
+    if opfamilies[0] != opfamilies[1] {
+        pgrx::error!("the indexes are built with different operator classes");
+    }
+    let opfamily = opfamilies[0];
+    if matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim) {
+        pgrx::error!("comparing maxsim indexes is not supported");
+    }
+    let indrelids = relations
+        .each_ref()
+        .map(|x| unsafe { (*(*x.raw()).rd_index).indrelid });
+    if indrelids[0] != indrelids[1] {
+        pgrx::error!("the indexes are on different tables");
+    }
+    let heap = Table::open(indrelids[0], pgrx::pg_sys::AccessShareLock as _);
+    let snapshot = unsafe { pgrx::pg_sys::GetActiveSnapshot() };
+    let mut sum = 0.0_f64;
+    let mut n = 0_i64;
+    for query in queries.iter_deny_null() {
+        pgrx::check_for_interrupts!();
+        let [x, y] = relations.each_ref().map(|relation| {
+            let vector = input(relation, opfamily, query.as_borrowed());
+            let options = crate::index::am::search_options();
+            unsafe {
+                crate::index::am::search(
+                    relation.raw(),
+                    heap.raw(),
+                    snapshot,
+                    options,
+                    vector,
+                    |iter| {
+                        iter.map(|(_, key)| key)
+                            .filter(|&key| crate::index::am::is_visible(heap.raw(), snapshot, key))
+                            .take(k as usize)
+                            .collect::<HashSet<_>>()
+                    },
+                )
+            }
+        });
+        let union = x.union(&y).count();
+        sum += if union == 0 {
+            1.0
+        } else {
+            x.intersection(&y).count() as f64 / union as f64
+        };
+        n += 1;
+    }
+    if n == 0 {
+        pgrx::error!("queries must not be empty");
+    }
+    pgrx::iter::TableIterator::once((sum / n as f64, n))
+}
+
+// `truth` has a row of ids for each query, and a query scores the fraction of
+// the first `k` ids of its row that are in its top-k results.
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_recall_vs_truth(
+    indexrelid: Oid,
+    queries: pgrx::Array<'_, crate::datatype::memory_vector::VectorInput<'_>>,
+    truth: pgrx::Array<'_, pgrx::pg_sys::ItemPointerData>,
+    ndims: i32,
+    rows: i32,
+    k: i32,
+    probes: i32,
+) -> f64 {
+    use crate::index::am::ctid_to_key;
+    use crate::index::opclass::Opfamily;
+    use std::collections::HashSet;
+    if k <= 0 {
+        pgrx::error!("k must be positive");
+    }
+    if probes <= 0 {
+        pgrx::error!("probes must be positive");
+    }
+    let queries = queries.iter_deny_null().collect::<Vec<_>>();
+    if queries.is_empty() {
+        pgrx::error!("queries must not be empty");
+    }
+    if ndims != 2 {
+        pgrx::error!("truth must be a two-dimensional array");
+    }
+    if rows as usize != queries.len() {
+        pgrx::error!(
+            "truth has {rows} rows, but there are {} queries",
+            queries.len()
+        );
+    }
+    let truth = truth
+        .iter()
+        .map(|x| x.unwrap_or_else(|| pgrx::error!("truth must not contain nulls")))
+        .map(ctid_to_key)
+        .collect::<Vec<_>>();
+    let width = truth.len() / queries.len();
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    if matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim) {
+        pgrx::error!("measuring recall of a maxsim index is not supported");
+    }
+    let heap = Table::open(
+        unsafe { (*(*relation.raw()).rd_index).indrelid },
+        pgrx::pg_sys::AccessShareLock as _,
+    );
+    let snapshot = unsafe { pgrx::pg_sys::GetActiveSnapshot() };
+    let mut sum = 0.0_f64;
+    for (query, row) in queries.iter().zip(truth.chunks(width)) {
+        pgrx::check_for_interrupts!();
+        let vector = input(&relation, opfamily, query.as_borrowed());
+        let mut options = crate::index::am::search_options();
+        if let Some(bottom) = options.probes.first_mut() {
+            *bottom = probes as u32;
+        }
+        let results = unsafe {
+            crate::index::am::search(
+                relation.raw(),
+                heap.raw(),
+                snapshot,
+                options,
+                vector,
+                |iter| {
+                    iter.map(|(_, key)| key)
+                        .filter(|&key| crate::index::am::is_visible(heap.raw(), snapshot, key))
+                        .take(k as usize)
+                        .collect::<HashSet<_>>()
+                },
+            )
+        };
+        let expected = &row[..row.len().min(k as usize)];
+        let found = expected.iter().filter(|key| results.contains(*key)).count();
+        sum += found as f64 / expected.len() as f64;
+    }
+    sum / queries.len() as f64
+}
+
+// The checksum is FNV-1a of row ids of top-k results in order, so it's the same
+// for the same results.
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_bench_scan(
+    indexrelid: Oid,
+    query: crate::datatype::memory_vector::VectorInput<'_>,
+    k: i32,
+    heap_strategy: &str,
+) -> pgrx::iter::TableIterator<'static, (pgrx::name!(latency_ms, f64), pgrx::name!(checksum, i64))>
+{
+    use crate::index::opclass::Opfamily;
+    use algorithm::HeapStrategy;
+    use std::time::Instant;
+    if k <= 0 {
+        pgrx::error!("k must be positive");
+    }
+    let heap_strategy = match heap_strategy {
+        "auto" => HeapStrategy::Auto,
+        "select" => HeapStrategy::Select,
+        "binary" => HeapStrategy::Binary,
+        _ => pgrx::error!("heap_strategy must be one of \"auto\", \"select\" and \"binary\""),
+    };
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    if matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim) {
+        pgrx::error!("benchmarking a maxsim index is not supported");
+    }
+    let vector = input(&relation, opfamily, query.as_borrowed());
+    let heap = Table::open(
+        unsafe { (*(*relation.raw()).rd_index).indrelid },
+        pgrx::pg_sys::AccessShareLock as _,
+    );
+    let snapshot = unsafe { pgrx::pg_sys::GetActiveSnapshot() };
+    let mut options = crate::index::am::search_options();
+    options.heap_strategy = heap_strategy;
+    let start = Instant::now();
+    let keys = unsafe {
+        crate::index::am::search(
+            relation.raw(),
+            heap.raw(),
+            snapshot,
+            options,
+            vector,
+            |iter| {
+                iter.map(|(_, key)| key)
+                    .filter(|&key| crate::index::am::is_visible(heap.raw(), snapshot, key))
+                    .take(k as usize)
+                    .collect::<Vec<_>>()
+            },
+        )
+    };
+    let latency = start.elapsed();
+    let mut checksum = 0xcbf29ce484222325_u64;
+    for byte in keys.iter().flatten().flat_map(|x| x.to_le_bytes()) {
+        checksum ^= byte as u64;
+        checksum = checksum.wrapping_mul(0x100000001b3);
+    }
+    pgrx::iter::TableIterator::once((latency.as_secs_f64() * 1000.0, checksum as i64))
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_export_model(indexrelid: Oid) -> Vec<u8> {
+    use algorithm::types::VectorOptions;
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    let structures = crate::index::algorithm::structures(opfamily, index);
+    let vector_options = VectorOptions {
+        dims: structures.last().expect("data corruption").means[0].len() as u32,
+        v: opfamily.vector_kind(),
+        d: opfamily.distance_kind(),
+    };
+    crate::index::model::serialize(&vector_options, &structures)
+}
+
+// Inserts and vacuums are blocked while the index is read, so a dump is consistent.
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_dump(indexrelid: Oid, path: &str) -> i64 {
+    use crate::index::dump::{Header, Record, Writer};
+    if !unsafe { pgrx::pg_sys::superuser() } {
+        pgrx::error!("must be superuser to dump an index to a file");
+    }
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::ShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    let table = Table::open(
+        unsafe { (*(*relation.raw()).rd_index).indrelid },
+        pgrx::pg_sys::AccessShareLock as _,
+    );
+    let cost = algorithm::cost(index.clone());
+    let header = Header {
+        dims: cost.dims,
+        v: opfamily.vector_kind(),
+        d: opfamily.distance_kind(),
+        is_residual: cost.is_residual,
+        rerank_in_heap: cost.rerank_in_heap,
+        sort_lists: cost.sort_lists,
+        code_alignment: cost.code_alignment,
+        table: unsafe {
+            (
+                (*table.raw()).rd_id.to_u32(),
+                (*(*table.raw()).rd_rel).relfilenode.to_u32(),
+            )
+        },
+    };
+    let structures = crate::index::algorithm::structures(opfamily, index.clone());
+    let write_error = |e: std::io::Error| -> ! {
+        pgrx::error!("could not write the dump to {path:?}: {e}");
+    };
+    let file = std::fs::File::create(path).unwrap_or_else(|e| write_error(e));
+    let mut writer = Writer::new(std::io::BufWriter::new(file), header, &structures)
+        .unwrap_or_else(|e| write_error(e));
+    let mut n = 0_i64;
+    let check = || {
+        pgrx::check_for_interrupts!();
+    };
+    crate::index::algorithm::dump(opfamily, index.clone(), check, |dumped| {
+        if matches!(dumped, algorithm::Dumped::Code { .. }) {
+            n += 1;
+        }
+        writer
+            .push(&Record::Dumped(dumped))
+            .unwrap_or_else(|e| write_error(e));
+    });
+    algorithm::chunks(index, |payload, members| {
+        writer
+            .push(&Record::Alias(payload, members.to_vec()))
+            .unwrap_or_else(|e| write_error(e));
+    });
+    writer.finish().unwrap_or_else(|e| write_error(e));
+    n
+}
+
+// The index is rebuilt from the dump, so it must be created in this transaction
+// with `[build] empty = true`, and then a restore that fails midway is rolled back
+// with it. The dump must be taken from the table of the index, which has not been
+// rewritten since, so ctids in the dump refer to the same rows.
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_restore(indexrelid: Oid, path: &str) -> i64 {
+    use crate::index::dump::{Reader, Record};
+    use algorithm::types::{VchordrqIndexOptions, VectorOptions};
+    if !unsafe { pgrx::pg_sys::superuser() } {
+        pgrx::error!("must be superuser to restore an index from a file");
+    }
+    let file = std::fs::File::open(path)
+        .unwrap_or_else(|e| pgrx::error!("could not read the dump from {path:?}: {e}"));
+    let (mut reader, structures) =
+        Reader::new(std::io::BufReader::new(file)).unwrap_or_else(|e| pgrx::error!("{e}"));
+    let header = reader.header();
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessExclusiveLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    let table = Table::open(
+        unsafe { (*(*relation.raw()).rd_index).indrelid },
+        pgrx::pg_sys::AccessShareLock as _,
+    );
+    let cost = algorithm::cost(index.clone());
+    if (header.v, header.d) != (opfamily.vector_kind(), opfamily.distance_kind()) {
+        pgrx::error!(
+            "the dump is for {:?} and {:?}, but the index is for {:?} and {:?}",
+            header.v,
+            header.d,
+            opfamily.vector_kind(),
+            opfamily.distance_kind()
+        );
+    }
+    if header.dims != cost.dims {
+        pgrx::error!(
+            "the dump has {} dimensions, but the index has {} dimensions",
+            header.dims,
+            cost.dims
+        );
+    }
+    if header.is_residual != cost.is_residual {
+        pgrx::error!("residual_quantization of the dump does not match the index");
+    }
+    if header.rerank_in_heap != cost.rerank_in_heap {
+        pgrx::error!("rerank_in_table of the dump does not match the index");
+    }
+    if header.sort_lists != cost.sort_lists {
+        pgrx::error!("sort_lists of the dump does not match the index");
+    }
+    if header.code_alignment != cost.code_alignment {
+        pgrx::error!("code_alignment of the dump does not match the index");
+    }
+    let (relid, relfilenode) = unsafe {
+        (
+            (*table.raw()).rd_id.to_u32(),
+            (*(*table.raw()).rd_rel).relfilenode.to_u32(),
+        )
+    };
+    if header.table.0 != relid {
+        pgrx::error!("the dump is taken from another table");
+    }
+    if header.table.1 != relfilenode {
+        pgrx::error!("the dump is taken before the table is rewritten");
+    }
+    // `InvalidSubTransactionId` is zero
+    if unsafe { (*relation.raw()).rd_createSubid } == 0 {
+        pgrx::error!("the index must be created in the current transaction");
+    }
+    let check = || {
+        pgrx::check_for_interrupts!();
+    };
+    let mut empty = true;
+    algorithm::assignments(index.clone(), check, |_, _| empty = false);
+    if !empty {
+        pgrx::error!("the index must be created with `[build] empty = true`");
+    }
+    unsafe {
+        pgrx::pg_sys::RelationTruncate(relation.raw(), 0);
+    }
+    crate::index::algorithm::build(
+        VectorOptions {
+            dims: header.dims,
+            v: header.v,
+            d: header.d,
+        },
+        VchordrqIndexOptions {
+            residual_quantization: header.is_residual,
+            rerank_in_table: header.rerank_in_heap,
+            code_alignment: header.code_alignment,
+            sort_lists: header.sort_lists,
+        },
+        index.clone(),
+        structures,
+    );
+    let mut next = || reader.next().unwrap_or_else(|e| pgrx::error!("{e}"));
+    let mut n = 0_i64;
+    let mut pending = None;
+    let dumped = std::iter::from_fn(|| match next()? {
+        Record::Dumped(dumped) => {
+            if matches!(dumped, algorithm::Dumped::Code { .. }) {
+                n += 1;
+            }
+            Some(dumped)
+        }
+        alias => {
+            pending = Some(alias);
+            None
+        }
+    });
+    crate::index::algorithm::restore(opfamily, index.clone(), check, dumped);
+    let limit = unsafe { pgrx::pg_sys::maintenance_work_mem } as usize * 1024;
+    let mut groups = Vec::new();
+    let mut used = 0_usize;
+    for record in pending.into_iter().chain(std::iter::from_fn(next)) {
+        check();
+        let Record::Alias(payload, members) = record else {
+            pgrx::error!("the dump is corrupted");
+        };
+        used += members.len() * size_of::<u64>();
+        groups.push((payload, members));
+        if used > limit {
+            algorithm::alias(index.clone(), std::mem::take(&mut groups));
+            used = 0;
+        }
+    }
+    algorithm::alias(index.clone(), groups);
+    crate::index::algorithm::maintain(opfamily, index, check);
+    n
+}
+
+// Only centroids are read, so no member of any list is scanned.
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_nearest_centroid(
+    indexrelid: Oid,
+    query: crate::datatype::memory_vector::VectorInput<'_>,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(list_id, i32),
+        pgrx::name!(centroid, crate::datatype::memory_vector::VectorOutput),
+        pgrx::name!(distance, f64),
+    ),
+> {
+    use crate::index::opclass::Opfamily;
+    use vector::vect::VectBorrowed;
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    if matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim) {
+        pgrx::error!("centroids of a maxsim index are not supported");
+    }
+    let vector = input(&relation, opfamily, query.as_borrowed());
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    let options = crate::index::am::search_options();
+    let (list, centroid, distance) =
+        crate::index::algorithm::nearest_centroid(opfamily, index, vector, options.probes);
+    let centroid = crate::datatype::memory_vector::VectorOutput::new(VectBorrowed::new(&centroid));
+    pgrx::iter::TableIterator::once((list as i32, centroid, distance as f64))
+}
+
+// Lists are probed as `vchord_nearest_centroid` does, and `probes` is the number
+// of lists probed at the bottom level.
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_explain_scan(
+    indexrelid: Oid,
+    query: crate::datatype::memory_vector::VectorInput<'_>,
+    probes: i32,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(probe_order, i32),
+        pgrx::name!(list_id, i32),
+        pgrx::name!(centroid_distance, f64),
+        pgrx::name!(list_size, i32),
+    ),
+> {
+    use crate::index::opclass::Opfamily;
+    if probes <= 0 {
+        pgrx::error!("probes must be positive");
+    }
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    if matches!(opfamily, Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim) {
+        pgrx::error!("centroids of a maxsim index are not supported");
+    }
+    let vector = input(&relation, opfamily, query.as_borrowed());
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    // upper levels are probed as the session does
+    let mut upper = crate::index::am::search_options().probes;
+    if !upper.is_empty() {
+        upper.remove(0);
+    }
+    let lists = crate::index::algorithm::probed_lists(opfamily, index, vector, upper, probes as _);
+    pgrx::iter::TableIterator::new(lists.into_iter().enumerate().map(
+        |(i, (list, distance, size))| (1 + i as i32, list as i32, distance as f64, size as i32),
+    ))
+}
+
+// Only the meta page is read.
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_index_info(
+    indexrelid: Oid,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(dims, i32),
+        pgrx::name!(metric, String),
+        pgrx::name!(quantizer, String),
+        pgrx::name!(lists, i32),
+        pgrx::name!(residual_quantization, bool),
+        pgrx::name!(store_originals, bool),
+    ),
+> {
+    use crate::index::opclass::Opfamily;
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    let metric = match opfamily {
+        Opfamily::VectorL2 | Opfamily::HalfvecL2 => "l2",
+        Opfamily::VectorIp | Opfamily::HalfvecIp => "ip",
+        Opfamily::VectorCosine | Opfamily::VectorCosineNormalized | Opfamily::HalfvecCosine => {
+            "cosine"
+        }
+        Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim => "maxsim",
+    };
+    let cost = algorithm::cost(index);
+    pgrx::iter::TableIterator::once((
+        cost.dims as i32,
+        metric.to_string(),
+        "rabitq".to_string(),
+        cost.cells[0] as i32,
+        cost.is_residual,
+        !cost.rerank_in_heap,
+    ))
+}
+
+// Lists are given from the root to the bottom, as in `[build.internal]`.
+#[pgrx::pg_extern(sql = "")]
+fn _vchordrq_index_options(
+    indexrelid: Oid,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(dims, i32),
+        pgrx::name!(vector, String),
+        pgrx::name!(metric, String),
+        pgrx::name!(quantizer, String),
+        pgrx::name!(bits, i32),
+        pgrx::name!(lists, Vec<i32>),
+        pgrx::name!(residual_quantization, bool),
+        pgrx::name!(rerank_in_table, bool),
+        pgrx::name!(sort_lists, bool),
+        pgrx::name!(code_alignment, i32),
+    ),
+> {
+    use crate::index::opclass::Opfamily;
+    use algorithm::types::VectorKind;
+    let relation = open_vchordrq_index(indexrelid, pgrx::pg_sys::AccessShareLock as _);
+    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
+    let index = unsafe { PostgresRelation::new(relation.raw()) };
+    let vector = match opfamily.vector_kind() {
+        VectorKind::Vecf32 => "vector",
+        VectorKind::Vecf16 => "halfvec",
+    };
+    let metric = match opfamily {
+        Opfamily::VectorL2 | Opfamily::HalfvecL2 => "l2",
+        Opfamily::VectorIp | Opfamily::HalfvecIp => "ip",
+        Opfamily::VectorCosine | Opfamily::VectorCosineNormalized | Opfamily::HalfvecCosine => {
+            "cosine"
+        }
+        Opfamily::VectorMaxsim | Opfamily::HalfvecMaxsim => "maxsim",
+    };
+    let cost = algorithm::cost(index);
+    let lists = cost.cells[..cost.cells.len() - 1]
+        .iter()
+        .rev()
+        .map(|&x| x as i32)
+        .collect();
+    pgrx::iter::TableIterator::once((
+        cost.dims as i32,
+        vector.to_string(),
+        metric.to_string(),
+        "rabitq".to_string(),
+        1,
+        lists,
+        cost.is_residual,
+        cost.rerank_in_heap,
+        cost.sort_lists,
+        cost.code_alignment as i32,
+    ))
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_dimension_histogram(
+    relid: Oid,
+    column: &std::ffi::CStr,
+) -> pgrx::iter::TableIterator<'static, (pgrx::name!(dims, i32), pgrx::name!(count, i64))> {
+    use pgrx::pg_sys::panic::ErrorReportable;
+    use std::ffi::CStr;
+    let relname = unsafe { pgrx::pg_sys::get_rel_name(relid) };
+    if relname.is_null() {
+        pgrx::error!("the relation does not exist");
+    }
+    let attnum = unsafe { pgrx::pg_sys::get_attnum(relid, column.as_ptr()) };
+    if attnum <= 0 {
+        pgrx::error!("column {:?} does not exist", column);
+    }
+    let (table, column) = unsafe {
+        let namespace = pgrx::pg_sys::get_namespace_name(pgrx::pg_sys::get_rel_namespace(relid));
+        let table = pgrx::pg_sys::quote_qualified_identifier(namespace, relname);
+        let column = pgrx::pg_sys::quote_identifier(column.as_ptr());
+        (
+            CStr::from_ptr(table).to_string_lossy().into_owned(),
+            CStr::from_ptr(column).to_string_lossy().into_owned(),
+        )
+    };
+    let mut results = Vec::new();
+    pgrx::spi::Spi::connect(|client| {
+        let schema_query = "SELECT n.nspname::TEXT
+            FROM pg_catalog.pg_extension e
+            LEFT JOIN pg_catalog.pg_namespace n ON n.oid = e.extnamespace
+            WHERE e.extname = 'vector';";
+        let pgvector_schema: String = client
+            .select(schema_query, None, &[])
+            .unwrap_or_report()
+            .first()
+            .get_by_name("nspname")
+            .expect("cannot get schema of pgvector")
+            .expect("cannot get schema of pgvector");
+        let histogram_query = format!(
+            "SELECT {pgvector_schema}.vector_dims({column}) AS dims, COUNT(1) AS count \
+            FROM {table} WHERE {column} IS NOT NULL GROUP BY 1 ORDER BY 1;"
+        );
+        for row in client
+            .select(&histogram_query, None, &[])
+            .unwrap_or_report()
+        {
+            let dims: Option<i32> = row.get_by_name("dims").unwrap();
+            let count: Option<i64> = row.get_by_name("count").unwrap();
+            results.push((dims.unwrap(), count.unwrap()));
+        }
+    });
+    pgrx::iter::TableIterator::new(results)
+}
+
+// A heuristic: the number of lists is doubled, until the within-cluster variance
+// of the sample stops dropping quickly.
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_suggest_lists(
+    sample: pgrx::Array<'_, crate::datatype::memory_vector::VectorInput<'_>>,
+    metric: &str,
+) -> i32 {
+    use simd::Floating;
+    let (normalized, spherical) = match metric {
+        "l2" => (false, false),
+        "ip" => (false, true),
+        "cosine" => (true, true),
+        _ => pgrx::error!("unknown metric {:?}", metric),
+    };
+    let mut samples = Vec::new();
+    for vector in sample.iter().flatten() {
+        let mut vector = vector.as_borrowed().slice().to_vec();
+        if normalized {
+            let norm = f32::reduce_sum_of_x2(&vector).sqrt();
+            if norm != 0.0 {
+                f32::vector_mul_scalar_inplace(&mut vector, 1.0 / norm);
+            }
+        }
+        if samples
+            .first()
+            .is_some_and(|x: &Vec<f32>| x.len() != vector.len())
+        {
+            pgrx::error!("dimension is not matched");
+        }
+        samples.push(vector);
+    }
+    if samples.is_empty() {
+        pgrx::error!("sample must not be empty");
+    }
+    let n = samples.len();
+    let dims = samples[0].len();
+    let variance = |c: usize| {
+        let check = |_| pgrx::check_for_interrupts!();
+        let means = k_means::k_means(1, check, c, dims, &samples, spherical, 10);
+        samples
+            .iter()
+            .map(|x| {
+                let i = k_means::k_means_lookup(x, &means);
+                f32::reduce_sum_of_d2(x, &means[i]) as f64
+            })
+            .sum::<f64>()
+    };
+    let mut c = 1;
+    let mut current = variance(c);
+    while 2 * c <= n && current > 0.0 {
+        let next = variance(2 * c);
+        if next > 0.7 * current {
+            break;
+        }
+        (c, current) = (2 * c, next);
+    }
+    c as i32
+}
+
+// An estimate of the size of a `vector` index right after a build, from the sizes of
+// tuples, see `algorithm::estimate_size` for its assumptions. It's not the size
+// after inserts and vacuums, which leave free space in pages.
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_estimate_size(
+    row_count: i64,
+    dims: i32,
+    bits: i32,
+    lists: i32,
+    store_originals: bool,
+) -> i64 {
+    if row_count < 0 {
+        pgrx::error!("row_count must not be negative");
+    }
+    if !(1..=65535).contains(&dims) {
+        pgrx::error!("dims must be between 1 and 65535");
+    }
+    if !(1..=8).contains(&bits) {
+        pgrx::error!("bits must be between 1 and 8");
+    }
+    if lists < 0 {
+        pgrx::error!("lists must not be negative");
+    }
+    let pages = algorithm::estimate_size(
+        crate::index::storage::size_of_contents(),
+        row_count as u64,
+        dims as u32,
+        bits as u32,
+        lists as u32,
+        !store_originals,
+    );
+    (pages * pgrx::pg_sys::BLCKSZ as u64) as i64
+}
+
+// Distances are those of operators of the metric, and a smaller distance is a
+// higher score:
+// * l2: `1 / (1 + d)`, with `d` the Euclidean distance
+// * ip and maxsim: `1 / (1 + exp(d))`, the sigmoid of the inner product `-d`
+// * cosine: `1 - d / 2`, with `d` the cosine distance in [0, 2]
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_similarity_score(distance: f64, metric: &str) -> f64 {
+    let score = match metric {
+        "l2" => 1.0 / (1.0 + distance.max(0.0)),
+        "ip" | "maxsim" => 1.0 / (1.0 + distance.exp()),
+        "cosine" => 1.0 - distance / 2.0,
+        _ => pgrx::error!("unknown metric {:?}", metric),
+    };
+    score.clamp(0.0, 1.0)
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_last_scan_stats() -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(lists, i64),
+        pgrx::name!(candidates, i64),
+        pgrx::name!(reranked, i64),
+        pgrx::name!(pages, i64),
+        pgrx::name!(centroids, i64),
+    ),
+> {
+    let report = crate::index::scanners::last_scan_report();
+    pgrx::iter::TableIterator::new(report.map(|report| {
+        (
+            report.lists as i64,
+            report.candidates as i64,
+            report.reranked as i64,
+            report.pages as i64,
+            report.centroids as i64,
+        )
+    }))
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_stats() -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(scans, i64),
+        pgrx::name!(lists, i64),
+        pgrx::name!(candidates, i64),
+        pgrx::name!(reranked, i64),
+        pgrx::name!(pages, i64),
+        pgrx::name!(avg_lists, Option<f64>),
+    ),
+> {
+    let (scans, report) = crate::index::scanners::global_report();
+    let avg_lists = (scans != 0).then(|| report.lists as f64 / scans as f64);
+    pgrx::iter::TableIterator::once((
+        scans as i64,
+        report.lists as i64,
+        report.candidates as i64,
+        report.reranked as i64,
+        report.pages as i64,
+        avg_lists,
+    ))
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_stats_reset() {
+    crate::index::scanners::reset_global_report();
+}
+
+// The index is checked before it's opened, so that a lock is never taken on a
+// relation that is not a vchordrq index.
+fn open_vchordrq_index(indexrelid: Oid, lockmode: pgrx::pg_sys::LOCKMASK) -> Index {
     let pg_am = PgAm::search_amname(c"vchordrq").unwrap();
     let Some(pg_am) = pg_am.get() else {
         pgrx::error!("vchord is not installed");
@@ -18,12 +1623,7 @@ fn _vchordrq_prewarm(indexrelid: Oid, height: i32) -> String {
     if pg_class.relam() != pg_am.oid() {
         pgrx::error!("the index {:?} is not a vchordrq index", pg_class.relname());
     }
-    let relation = Index::open(indexrelid, pgrx::pg_sys::AccessShareLock as _);
-    let opfamily = unsafe { crate::index::opclass::opfamily(relation.raw()) };
-    let index = unsafe { PostgresRelation::new(relation.raw()) };
-    crate::index::algorithm::prewarm(opfamily, index, height, || {
-        pgrx::check_for_interrupts!();
-    })
+    Index::open(indexrelid, lockmode)
 }
 
 struct Index {
@@ -41,6 +1641,9 @@ impl Index {
     fn raw(&self) -> *mut pgrx::pg_sys::RelationData {
         self.raw
     }
+    fn relname(&self) -> &std::ffi::CStr {
+        unsafe { std::ffi::CStr::from_ptr((*(*self.raw).rd_rel).relname.data.as_ptr()) }
+    }
 }
 
 impl Drop for Index {
@@ -50,3 +1653,58 @@ impl Drop for Index {
         }
     }
 }
+
+struct Table {
+    raw: *mut pgrx::pg_sys::RelationData,
+    lockmode: pgrx::pg_sys::LOCKMODE,
+}
+
+impl Table {
+    fn open(relid: Oid, lockmode: pgrx::pg_sys::LOCKMASK) -> Self {
+        Self {
+            raw: unsafe { pgrx::pg_sys::table_open(relid, lockmode) },
+            lockmode,
+        }
+    }
+    fn raw(&self) -> *mut pgrx::pg_sys::RelationData {
+        self.raw
+    }
+}
+
+impl Drop for Table {
+    fn drop(&mut self) {
+        unsafe {
+            pgrx::pg_sys::table_close(self.raw, self.lockmode);
+        }
+    }
+}
+
+fn input(
+    relation: &Index,
+    opfamily: crate::index::opclass::Opfamily,
+    query: vector::vect::VectBorrowed<'_, f32>,
+) -> algorithm::types::OwnedVector {
+    use crate::datatype::typmod::Typmod;
+    use algorithm::types::{BorrowedVector, VectorKind};
+    use half::f16;
+    use simd::Floating;
+    use vector::VectorBorrowed;
+    use vector::vect::VectOwned;
+    let dims = {
+        let att = unsafe { &*(*relation.raw()).rd_att };
+        let atts = unsafe { att.attrs.as_slice(att.natts as _) };
+        Typmod::parse_from_i32(atts[0].type_mod())
+            .and_then(Typmod::dims)
+            .map(|dims| dims.get())
+    };
+    if dims.is_some_and(|dims| dims != query.dims()) {
+        pgrx::error!("dimension is not matched");
+    }
+    match opfamily.vector_kind() {
+        VectorKind::Vecf32 => opfamily.input(BorrowedVector::Vecf32(query)),
+        VectorKind::Vecf16 => {
+            let query = VectOwned::new(f16::vector_from_f32(query.slice()));
+            opfamily.input(BorrowedVector::Vecf16(query.as_borrowed()))
+        }
+    }
+}
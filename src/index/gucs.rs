@@ -1,7 +1,9 @@
 use super::scanners::SearchIo;
+use algorithm::{HeapStrategy, RerankPrecision};
 use pgrx::PostgresGucEnum;
 use pgrx::guc::{GucContext, GucFlags, GucRegistry, GucSetting};
 use std::ffi::CStr;
+use std::time::Duration;
 
 #[allow(non_camel_case_types)]
 #[derive(Debug, Clone, Copy, PostgresGucEnum)]
@@ -12,18 +14,47 @@ pub enum Io {
     read_stream,
 }
 
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PostgresGucEnum)]
+pub enum Precision {
+    f32,
+    f64,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PostgresGucEnum)]
+pub enum Heap {
+    auto,
+    select,
+    binary,
+}
+
 static PREWARM_DIM: GucSetting<Option<&CStr>> =
     GucSetting::<Option<&CStr>>::new(Some(c"64,128,256,384,512,768,1024,1536"));
 
 static PROBES: GucSetting<Option<&'static CStr>> = GucSetting::<Option<&CStr>>::new(Some(c""));
 static EPSILON: GucSetting<f64> = GucSetting::<f64>::new(1.9);
 static MAX_SCAN_TUPLES: GucSetting<i32> = GucSetting::<i32>::new(-1);
+static CTID_REORDER: GucSetting<i32> = GucSetting::<i32>::new(0);
+static SCAN_TIMEOUT_MS: GucSetting<i32> = GucSetting::<i32>::new(0);
+static PROBE_CACHE: GucSetting<bool> = GucSetting::<bool>::new(false);
+static ADAPTIVE_EPSILON: GucSetting<f64> = GucSetting::<f64>::new(0.0);
+static ADAPTIVE_MAX_PROBES: GucSetting<i32> = GucSetting::<i32>::new(0);
+static MAX_PER_LIST: GucSetting<i32> = GucSetting::<i32>::new(0);
+static SCAN_WORK_MEM: GucSetting<i32> = GucSetting::<i32>::new(0);
+
+static TIE_SEED: GucSetting<Option<&CStr>> = GucSetting::<Option<&CStr>>::new(None);
 
 static MAXSIM_REFINE: GucSetting<i32> = GucSetting::<i32>::new(0);
 static MAXSIM_THRESHOLD: GucSetting<i32> = GucSetting::<i32>::new(0);
 
 static PRERERANK_FILTERING: GucSetting<bool> = GucSetting::<bool>::new(false);
 
+static INCREMENTAL: GucSetting<bool> = GucSetting::<bool>::new(false);
+
+// without reranking, results are ordered by RaBitQ estimates, which lowers recall
+static RERANK: GucSetting<bool> = GucSetting::<bool>::new(true);
+
 static IO_RERANK: GucSetting<Io> = GucSetting::<Io>::new(
     #[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15", feature = "pg16"))]
     Io::prefetch_buffer,
@@ -31,6 +62,10 @@ static IO_RERANK: GucSetting<Io> = GucSetting::<Io>::new(
     Io::read_stream,
 );
 
+static HEAP_STRATEGY: GucSetting<Heap> = GucSetting::<Heap>::new(Heap::auto);
+
+static RERANK_PRECISION: GucSetting<Precision> = GucSetting::<Precision>::new(Precision::f32);
+
 pub fn init() {
     GucRegistry::define_string_guc(
         "vchordrq.probes",
@@ -60,6 +95,98 @@ pub fn init() {
         GucContext::Userset,
         GucFlags::default(),
     );
+    GucRegistry::define_int_guc(
+        "vchordrq.ctid_reorder",
+        "Reorder the first `ctid_reorder` results of vchordrq by physical location.",
+        "Reorder the first `ctid_reorder` results of vchordrq by physical location, so that heap pages are fetched sequentially. \
+        The index reports lower bounds of distances for them, so the executor rechecks them and restores the order of distance. \
+        It's ignored if `vchordrq.rerank` is off or for maxsim, whose estimates are not lower bounds. 0 means never reorder.",
+        &CTID_REORDER,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_int_guc(
+        "vchordrq.scan_timeout_ms",
+        "Stop probing lists once a scan of vchordrq takes `scan_timeout_ms` milliseconds.",
+        "Stop probing lists once a scan of vchordrq takes `scan_timeout_ms` milliseconds, and return results in probed lists. \
+        Unlike `statement_timeout`, the query is not aborted, but results are approximate. \
+        At least one list is probed. 0 means unlimited.",
+        &SCAN_TIMEOUT_MS,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::UNIT_MS,
+    );
+    GucRegistry::define_bool_guc(
+        "vchordrq.probe_cache",
+        "Cache lists selected for a query of vchordrq in this session.",
+        "Cache lists selected by upper levels for a query of vchordrq in this session, \
+        so that repeated scans with the identical query skip computing distances to centroids. \
+        The cache of an index is dropped once it's modified in this session. \
+        It doesn't apply to incremental scans.",
+        &PROBE_CACHE,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_float_guc(
+        "vchordrq.adaptive_epsilon",
+        "Stop probing lists of vchordrq once the `max_scan_tuples`-th distance improves by less than `adaptive_epsilon`.",
+        "Stop probing lists of vchordrq once a list improves the estimated `max_scan_tuples`-th distance by less than \
+        `adaptive_epsilon` times it, so easy queries probe fewer lists than `probes`. \
+        It requires `max_scan_tuples`, and it doesn't apply to incremental or parallel scans. 0 means always probe `probes` lists.",
+        &ADAPTIVE_EPSILON,
+        0.0,
+        1.0,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_int_guc(
+        "vchordrq.adaptive_max_probes",
+        "The most lists of the bottom level that vchordrq probes if `adaptive_epsilon` is set.",
+        "The most lists of the bottom level that vchordrq probes if `adaptive_epsilon` is set, \
+        which replaces the number of `probes` for the bottom level. 0 means using `probes`.",
+        &ADAPTIVE_MAX_PROBES,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_int_guc(
+        "vchordrq.max_per_list",
+        "The most candidates that vchordrq takes from a list.",
+        "The most candidates that vchordrq takes from a list, so that a huge list doesn't dominate the scan. \
+        The candidates nearest by estimated distances are taken, so the whole list is still read. \
+        Incremental scans reject it, and it doesn't apply to maxsim scans. 0 means no limit.",
+        &MAX_PER_LIST,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_int_guc(
+        "vchordrq.scan_work_mem",
+        "The most memory that candidates of a vchordrq scan take.",
+        "The most memory that candidates of a vchordrq scan take. \
+        Once candidates would take more, the ones with the greatest lower bounds are dropped, \
+        so a scan returns no more rows than the candidates kept. \
+        Incremental scans reject it, and it doesn't apply to maxsim scans. 0 means no limit.",
+        &SCAN_WORK_MEM,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::UNIT_KB,
+    );
+    GucRegistry::define_string_guc(
+        "vchordrq.tie_seed",
+        "Shuffle results of vchordrq with equal distances by `tie_seed`.",
+        "Shuffle results of vchordrq with equal distances deterministically by `tie_seed`. \
+        If it's unset, results with equal distances are returned in order of physical location.",
+        &TIE_SEED,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
     GucRegistry::define_string_guc(
         "vchordrq.prewarm_dim",
         "prewarm_dim when the extension is loading.",
@@ -96,6 +223,22 @@ pub fn init() {
         GucContext::Userset,
         GucFlags::default(),
     );
+    GucRegistry::define_bool_guc(
+        "vchordrq.incremental",
+        "`incremental` argument of vchordrq.",
+        "`incremental` argument of vchordrq.",
+        &INCREMENTAL,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_bool_guc(
+        "vchordrq.rerank",
+        "`rerank` argument of vchordrq.",
+        "`rerank` argument of vchordrq.",
+        &RERANK,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
     GucRegistry::define_enum_guc(
         "vchordrq.io_rerank",
         "`io_rerank` argument of vchordrq.",
@@ -104,6 +247,23 @@ pub fn init() {
         GucContext::Userset,
         GucFlags::default(),
     );
+    GucRegistry::define_enum_guc(
+        "vchordrq.heap_strategy",
+        "`heap_strategy` argument of vchordrq.",
+        "`heap_strategy` argument of vchordrq.",
+        &HEAP_STRATEGY,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_enum_guc(
+        "vchordrq.rerank_precision",
+        "Precision of accumulation when vchordrq computes exact distances for reranking.",
+        "Precision of accumulation when vchordrq computes exact distances for reranking. \
+        `f64` is more stable numerically on vectors with many dimensions, but it's slower.",
+        &RERANK_PRECISION,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
     unsafe {
         #[cfg(any(feature = "pg13", feature = "pg14"))]
         pgrx::pg_sys::EmitWarningsOnPlaceholders(c"vchordrq".as_ptr());
@@ -149,6 +309,52 @@ pub fn max_scan_tuples() -> Option<u32> {
     if x < 0 { None } else { Some(x as u32) }
 }
 
+pub fn ctid_reorder() -> u32 {
+    CTID_REORDER.get() as u32
+}
+
+pub fn scan_timeout() -> Option<Duration> {
+    let x = SCAN_TIMEOUT_MS.get();
+    (x > 0).then(|| Duration::from_millis(x as u64))
+}
+
+pub fn probe_cache() -> bool {
+    PROBE_CACHE.get()
+}
+
+pub fn adaptive_epsilon() -> Option<f32> {
+    let x = ADAPTIVE_EPSILON.get();
+    (x > 0.0).then_some(x as f32)
+}
+
+pub fn adaptive_max_probes() -> Option<u32> {
+    let x = ADAPTIVE_MAX_PROBES.get();
+    (x > 0).then_some(x as u32)
+}
+
+pub fn max_per_list() -> Option<u32> {
+    let x = MAX_PER_LIST.get();
+    (x > 0).then_some(x as u32)
+}
+
+pub fn scan_work_mem() -> Option<usize> {
+    let x = SCAN_WORK_MEM.get();
+    (x > 0).then_some(x as usize * 1024)
+}
+
+pub fn tie_seed() -> Option<i64> {
+    let tie_seed = TIE_SEED.get()?;
+    let tie_seed = tie_seed
+        .to_str()
+        .ok()
+        .map(str::trim)
+        .filter(|x| !x.is_empty())?;
+    match tie_seed.parse() {
+        Ok(tie_seed) => Some(tie_seed),
+        Err(_) => pgrx::error!("`tie_seed` should be an integer, but got {tie_seed:?}"),
+    }
+}
+
 pub fn maxsim_refine() -> u32 {
     MAXSIM_REFINE.get() as u32
 }
@@ -182,6 +388,14 @@ pub fn prererank_filtering() -> bool {
     PRERERANK_FILTERING.get()
 }
 
+pub fn incremental() -> bool {
+    INCREMENTAL.get()
+}
+
+pub fn rerank() -> bool {
+    RERANK.get()
+}
+
 pub fn io_rerank() -> SearchIo {
     match IO_RERANK.get() {
         Io::read_buffer => SearchIo::ReadBuffer,
@@ -190,3 +404,18 @@ pub fn io_rerank() -> SearchIo {
         Io::read_stream => SearchIo::ReadStream,
     }
 }
+
+pub fn heap_strategy() -> HeapStrategy {
+    match HEAP_STRATEGY.get() {
+        Heap::auto => HeapStrategy::Auto,
+        Heap::select => HeapStrategy::Select,
+        Heap::binary => HeapStrategy::Binary,
+    }
+}
+
+pub fn rerank_precision() -> RerankPrecision {
+    match RERANK_PRECISION.get() {
+        Precision::f32 => RerankPrecision::F32,
+        Precision::f64 => RerankPrecision::F64,
+    }
+}
@@ -0,0 +1,258 @@
+use pgrx::bgworkers::{BackgroundWorker, BackgroundWorkerBuilder, SignalWakeFlags};
+use pgrx::lwlock::PgLwLock;
+use pgrx::pg_sys::Oid;
+use pgrx::pg_sys::panic::{CaughtError, ErrorReportable};
+use pgrx::shmem::PGRXSharedMemory;
+use std::ffi::{CStr, CString};
+
+// Jobs of `vchord_build_async` are kept in shared memory, so they're forgotten
+// after a restart, and builds are not resumed; it's out of scope to make them
+// survive one. An index is built in one transaction, so a job that doesn't finish
+// leaves nothing behind, and it can be launched again.
+const SLOTS: usize = 8;
+const STATEMENT: usize = 4096;
+const ERROR: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Empty,
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Job {
+    id: u64,
+    state: State,
+    pid: i32,
+    database: Oid,
+    user: Oid,
+    statement_len: u16,
+    statement: [u8; STATEMENT],
+    error_len: u16,
+    error: [u8; ERROR],
+}
+
+impl Default for Job {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            state: State::Empty,
+            pid: 0,
+            database: Oid::INVALID,
+            user: Oid::INVALID,
+            statement_len: 0,
+            statement: [0; STATEMENT],
+            error_len: 0,
+            error: [0; ERROR],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Jobs {
+    next: u64,
+    slots: [Job; SLOTS],
+}
+
+unsafe impl PGRXSharedMemory for Jobs {}
+
+static JOBS: PgLwLock<Jobs> = unsafe { PgLwLock::new(c"vchord_build_jobs") };
+
+pub fn init() {
+    pgrx::pg_shmem_init!(JOBS);
+}
+
+fn finish(id: u64, state: State, error: &str) {
+    let mut jobs = JOBS.exclusive();
+    if let Some(job) = jobs.slots.iter_mut().find(|job| job.id == id) {
+        // the message is truncated at a character boundary
+        let mut len = error.len().min(ERROR);
+        while !error.is_char_boundary(len) {
+            len -= 1;
+        }
+        job.state = state;
+        job.error[..len].copy_from_slice(&error.as_bytes()[..len]);
+        job.error_len = len as u16;
+    }
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_build_async(relid: Oid, column: &CStr, options: pgrx::JsonB) -> i64 {
+    let relname = unsafe { pgrx::pg_sys::get_rel_name(relid) };
+    if relname.is_null() {
+        pgrx::error!("the relation does not exist");
+    }
+    let attnum = unsafe { pgrx::pg_sys::get_attnum(relid, column.as_ptr()) };
+    if attnum <= 0 {
+        pgrx::error!("column {:?} does not exist", column);
+    }
+    let toml::Value::Table(mut options) = toml::Value::try_from(&options.0)
+        .unwrap_or_else(|e| pgrx::error!("options cannot be converted to TOML: {e}"))
+    else {
+        pgrx::error!("options must be an object");
+    };
+    let opclass = match options.remove("opclass") {
+        None => "vector_l2_ops".to_string(),
+        Some(toml::Value::String(opclass)) => opclass,
+        Some(_) => pgrx::error!("opclass must be a string"),
+    };
+    let options = toml::to_string(&options)
+        .unwrap_or_else(|e| pgrx::error!("options cannot be converted to TOML: {e}"));
+    let statement = unsafe {
+        let text = |x: *const std::ffi::c_char| CStr::from_ptr(x).to_string_lossy().into_owned();
+        let namespace = pgrx::pg_sys::get_namespace_name(pgrx::pg_sys::get_rel_namespace(relid));
+        let table = text(pgrx::pg_sys::quote_qualified_identifier(namespace, relname));
+        let column = text(pgrx::pg_sys::quote_identifier(column.as_ptr()));
+        let opclass = CString::new(opclass).expect("opclass must not contain NUL");
+        let opclass = text(pgrx::pg_sys::quote_identifier(opclass.as_ptr()));
+        let options = CString::new(options).expect("options must not contain NUL");
+        let options = text(pgrx::pg_sys::quote_literal_cstr(options.as_ptr()));
+        format!(
+            "CREATE INDEX ON {table} USING vchordrq ({column} {opclass}) WITH (options = {options})"
+        )
+    };
+    if statement.len() > STATEMENT {
+        pgrx::error!("options are too long");
+    }
+    let id = {
+        let mut jobs = JOBS.exclusive();
+        let Some(slot) = (0..SLOTS)
+            .filter(|&i| {
+                matches!(
+                    jobs.slots[i].state,
+                    State::Empty | State::Done | State::Failed
+                )
+            })
+            .min_by_key(|&i| (jobs.slots[i].state != State::Empty, jobs.slots[i].id))
+        else {
+            pgrx::error!("too many indexes are being built in the background");
+        };
+        jobs.next += 1;
+        let id = jobs.next;
+        let mut job = Job {
+            id,
+            state: State::Pending,
+            database: unsafe { pgrx::pg_sys::MyDatabaseId },
+            user: unsafe { pgrx::pg_sys::GetUserId() },
+            statement_len: statement.len() as u16,
+            ..Default::default()
+        };
+        job.statement[..statement.len()].copy_from_slice(statement.as_bytes());
+        jobs.slots[slot] = job;
+        id
+    };
+    let launched = BackgroundWorkerBuilder::new("vchord build")
+        .set_type("vchord build")
+        .set_library("vchord")
+        .set_function("_vchord_build_main")
+        .set_argument(Some(pgrx::pg_sys::Datum::from(id as i64)))
+        .enable_spi_access()
+        .set_notify_pid(unsafe { pgrx::pg_sys::MyProcPid })
+        .load_dynamic();
+    let Ok(worker) = launched else {
+        finish(id, State::Failed, "no background worker could be launched");
+        pgrx::error!(
+            "no background worker could be launched, so consider raising max_worker_processes"
+        );
+    };
+    if let Err(status) = worker.wait_for_startup() {
+        finish(id, State::Failed, "the background worker could not start");
+        pgrx::error!("the background worker could not start: {status:?}");
+    }
+    id as i64
+}
+
+#[pgrx::pg_guard]
+#[unsafe(no_mangle)]
+pub extern "C-unwind" fn _vchord_build_main(arg: pgrx::pg_sys::Datum) {
+    let id = arg.value() as u64;
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    let Some((database, user)) = ({
+        let jobs = JOBS.share();
+        jobs.slots
+            .iter()
+            .find(|job| job.id == id && job.state == State::Pending)
+            .map(|job| (job.database, job.user))
+    }) else {
+        return;
+    };
+    unsafe {
+        pgrx::pg_sys::BackgroundWorkerInitializeConnectionByOid(database, user, 0);
+    }
+    // the worker is found by its pid only after it's connected, so it's marked as
+    // running here, and then a running job whose worker is not found has failed
+    let job = {
+        let mut jobs = JOBS.exclusive();
+        let Some(job) = jobs
+            .slots
+            .iter_mut()
+            .find(|job| job.id == id && job.state == State::Pending)
+        else {
+            return;
+        };
+        job.state = State::Running;
+        job.pid = unsafe { pgrx::pg_sys::MyProcPid };
+        *job
+    };
+    let statement = String::from_utf8_lossy(&job.statement[..job.statement_len as usize]);
+    pgrx::PgTryBuilder::new(|| {
+        BackgroundWorker::transaction(|| {
+            pgrx::spi::Spi::run(&statement).unwrap_or_report();
+        });
+        finish(id, State::Done, "");
+    })
+    .catch_others(|e| {
+        let message = match &e {
+            CaughtError::PostgresError(report) | CaughtError::ErrorReport(report) => {
+                report.message().to_string()
+            }
+            CaughtError::RustPanic { ereport, .. } => ereport.message().to_string(),
+        };
+        finish(id, State::Failed, &message);
+        e.rethrow()
+    })
+    .execute();
+}
+
+// A running job whose worker has exited without finishing is reported as failed.
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_build_job(
+    id: i64,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        pgrx::name!(state, String),
+        pgrx::name!(pid, Option<i32>),
+        pgrx::name!(error, Option<String>),
+    ),
+> {
+    let job = {
+        let jobs = JOBS.share();
+        let Some(job) = jobs
+            .slots
+            .iter()
+            .find(|job| job.id == id as u64 && job.state != State::Empty)
+        else {
+            pgrx::error!("the job {id} does not exist, or it's forgotten after a restart");
+        };
+        *job
+    };
+    let error = String::from_utf8_lossy(&job.error[..job.error_len as usize]).into_owned();
+    let alive = || unsafe { !pgrx::pg_sys::BackendPidGetProc(job.pid).is_null() };
+    let result = match job.state {
+        State::Empty => unreachable!(),
+        State::Pending => ("pending".to_string(), None, None),
+        State::Running if alive() => ("running".to_string(), Some(job.pid), None),
+        State::Running => (
+            "failed".to_string(),
+            None,
+            Some("the worker exited unexpectedly".to_string()),
+        ),
+        State::Done => ("done".to_string(), None, None),
+        State::Failed => ("failed".to_string(), None, Some(error)),
+    };
+    pgrx::iter::TableIterator::once(result)
+}
@@ -1,19 +1,26 @@
 pub mod algorithm;
 pub mod am;
+pub mod checkpoint;
+pub mod dump;
 pub mod functions;
 pub mod gucs;
 pub mod hook;
+pub mod jobs;
 pub mod lazy_cell;
+pub mod model;
 pub mod opclass;
 pub mod projection;
 pub mod scanners;
 pub mod storage;
+pub mod topk;
 pub mod types;
 
 pub fn init() {
     am::init();
     hook::init();
+    jobs::init();
     gucs::init();
+    scanners::init();
     for x in gucs::prewarm_dim() {
         projection::prewarm(x as _);
     }
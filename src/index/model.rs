@@ -0,0 +1,111 @@
+use algorithm::types::{DistanceKind, Structure, VectorKind, VectorOptions};
+
+// A model is the tree of centroids in the projected space. RaBitQ has no trained
+// parameters and the projection is fixed, so the tree is the whole quantizer state.
+//
+// The layout is little-endian: the magic, dims, vector kind, distance kind and
+// then levels from the bottom to the root, each as its length followed by
+// means and children of all its nodes.
+const MAGIC: &[u8; 8] = b"vchordrq";
+const VERSION: u32 = 1;
+
+pub fn serialize(vector_options: &VectorOptions, structures: &[Structure<Vec<f32>>]) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    buffer.extend(MAGIC);
+    buffer.extend(VERSION.to_le_bytes());
+    buffer.extend(vector_options.dims.to_le_bytes());
+    buffer.push(vector_options.v as u8);
+    buffer.push(vector_options.d as u8);
+    buffer.extend((structures.len() as u32).to_le_bytes());
+    for structure in structures {
+        buffer.extend((structure.len() as u32).to_le_bytes());
+        for (mean, children) in structure.means.iter().zip(structure.children.iter()) {
+            for x in mean {
+                buffer.extend(x.to_le_bytes());
+            }
+            buffer.extend((children.len() as u32).to_le_bytes());
+            for x in children {
+                buffer.extend(x.to_le_bytes());
+            }
+        }
+    }
+    buffer
+}
+
+pub fn deserialize(
+    vector_options: &VectorOptions,
+    mut bytes: &[u8],
+) -> Result<Vec<Structure<Vec<f32>>>, String> {
+    let mut take = |n: usize| {
+        if bytes.len() < n {
+            return Err("the model is truncated".to_string());
+        }
+        let (head, tail) = bytes.split_at(n);
+        bytes = tail;
+        Ok(head)
+    };
+    if take(MAGIC.len())? != MAGIC {
+        return Err("the data is not a model".to_string());
+    }
+    let mut u32 = || take(4).map(|x| u32::from_le_bytes(x.try_into().unwrap()));
+    if u32()? != VERSION {
+        return Err("the version of the model is not supported".to_string());
+    }
+    let dims = u32()?;
+    if dims != vector_options.dims {
+        return Err(format!(
+            "the model has {dims} dimensions, but the index has {} dimensions",
+            vector_options.dims
+        ));
+    }
+    let kinds = take(2)?;
+    let v = match kinds[0] {
+        0 => VectorKind::Vecf32,
+        1 => VectorKind::Vecf16,
+        _ => return Err("the model is corrupted".to_string()),
+    };
+    let d = match kinds[1] {
+        0 => DistanceKind::L2,
+        1 => DistanceKind::Dot,
+        _ => return Err("the model is corrupted".to_string()),
+    };
+    if (v, d) != (vector_options.v, vector_options.d) {
+        return Err(format!(
+            "the model is trained for {v:?} and {d:?}, but the index is for {:?} and {:?}",
+            vector_options.v, vector_options.d
+        ));
+    }
+    let mut u32 = || take(4).map(|x| u32::from_le_bytes(x.try_into().unwrap()));
+    let height = u32()?;
+    let mut structures = Vec::new();
+    for i in 0..height {
+        let len = u32()?;
+        let mut means = Vec::new();
+        let mut children = Vec::new();
+        for _ in 0..len {
+            let mut mean = Vec::with_capacity(dims as _);
+            for _ in 0..dims {
+                mean.push(f32::from_bits(u32()?));
+            }
+            means.push(mean);
+            let n = u32()?;
+            let mut list = Vec::new();
+            for _ in 0..n {
+                list.push(u32()?);
+            }
+            children.push(list);
+        }
+        let lower = structures.last().map_or(0, |x: &Structure<_>| x.len());
+        if children.iter().flatten().any(|&x| x as usize >= lower) || (i == 0) != (lower == 0) {
+            return Err("the model is corrupted".to_string());
+        }
+        structures.push(Structure { means, children });
+    }
+    if !bytes.is_empty() {
+        return Err("the model has trailing bytes".to_string());
+    }
+    if structures.last().is_none_or(|x| x.len() != 1) {
+        return Err("the model is corrupted".to_string());
+    }
+    Ok(structures)
+}
@@ -23,6 +23,12 @@ fn _vchordrq_support_vector_cosine_ops() -> String {
     "vector_cosine_ops".to_string()
 }
 
+// Vectors are assumed to be normalized, so they are never normalized again.
+#[pgrx::pg_extern(immutable, strict, parallel_safe)]
+fn _vchordrq_support_vector_cosine_normalized_ops() -> String {
+    "vector_cosine_normalized_ops".to_string()
+}
+
 #[pgrx::pg_extern(immutable, strict, parallel_safe)]
 fn _vchordrq_support_halfvec_l2_ops() -> String {
     "halfvec_l2_ops".to_string()
@@ -53,11 +59,12 @@ pub struct Sphere<T> {
     pub radius: f32,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Opfamily {
     VectorL2,
     VectorIp,
     VectorCosine,
+    VectorCosineNormalized,
     HalfvecL2,
     HalfvecIp,
     HalfvecCosine,
@@ -66,12 +73,13 @@ pub enum Opfamily {
 }
 
 impl Opfamily {
-    fn input(self, vector: BorrowedVector<'_>) -> OwnedVector {
+    pub fn input(self, vector: BorrowedVector<'_>) -> OwnedVector {
         use {BorrowedVector as B, OwnedVector as O};
         match (vector, self) {
             (B::Vecf32(x), Self::VectorL2) => O::Vecf32(x.own()),
             (B::Vecf32(x), Self::VectorIp | Self::VectorMaxsim) => O::Vecf32(x.own()),
             (B::Vecf32(x), Self::VectorCosine) => O::Vecf32(x.function_normalize()),
+            (B::Vecf32(x), Self::VectorCosineNormalized) => O::Vecf32(x.own()),
             (B::Vecf32(_), _) => unreachable!(),
             (B::Vecf16(x), Self::HalfvecL2) => O::Vecf16(x.own()),
             (B::Vecf16(x), Self::HalfvecIp | Self::HalfvecMaxsim) => O::Vecf16(x.own()),
@@ -84,7 +92,7 @@ impl Opfamily {
             return None;
         }
         let store = match self {
-            Self::VectorL2 | Self::VectorIp | Self::VectorCosine => {
+            Self::VectorL2 | Self::VectorIp | Self::VectorCosine | Self::VectorCosineNormalized => {
                 let vector = unsafe { VectorInput::from_datum(datum, false).unwrap() };
                 vec![(self.input(BorrowedVector::Vecf32(vector.as_borrowed())), 0)]
             }
@@ -119,6 +127,70 @@ impl Opfamily {
         };
         Some(store)
     }
+    // Issues of an original value that break assumptions of this opfamily. It reads
+    // elements before `input`, since normalization turns a zero vector into NaNs.
+    pub unsafe fn audit(self, datum: Datum, dims: u32) -> Vec<String> {
+        let vectors: Vec<Vec<f32>> = match self {
+            Self::VectorL2 | Self::VectorIp | Self::VectorCosine | Self::VectorCosineNormalized => {
+                let vector = unsafe { VectorInput::from_datum(datum, false).unwrap() };
+                vec![vector.as_borrowed().slice().to_vec()]
+            }
+            Self::HalfvecL2 | Self::HalfvecIp | Self::HalfvecCosine => {
+                let vector = unsafe { HalfvecInput::from_datum(datum, false).unwrap() };
+                vec![
+                    vector
+                        .as_borrowed()
+                        .slice()
+                        .iter()
+                        .map(|x| x.to_f32())
+                        .collect(),
+                ]
+            }
+            Self::VectorMaxsim => {
+                let vectors =
+                    unsafe { pgrx::Array::<VectorInput>::from_datum(datum, false).unwrap() };
+                vectors
+                    .iter_deny_null()
+                    .map(|x| x.as_borrowed().slice().to_vec())
+                    .collect()
+            }
+            Self::HalfvecMaxsim => {
+                let vectors =
+                    unsafe { pgrx::Array::<HalfvecInput>::from_datum(datum, false).unwrap() };
+                vectors
+                    .iter_deny_null()
+                    .map(|x| x.as_borrowed().slice().iter().map(|x| x.to_f32()).collect())
+                    .collect()
+            }
+        };
+        let cosine = matches!(
+            self,
+            Self::VectorCosine | Self::VectorCosineNormalized | Self::HalfvecCosine
+        );
+        let maxsim = matches!(self, Self::VectorMaxsim | Self::HalfvecMaxsim);
+        let mut issues = Vec::new();
+        for (i, vector) in vectors.iter().enumerate() {
+            let name = if maxsim {
+                format!("vector {i}")
+            } else {
+                "the vector".to_string()
+            };
+            if vector.len() as u32 != dims {
+                issues.push(format!(
+                    "{name} has {} dimensions, but the index has {dims} dimensions",
+                    vector.len()
+                ));
+            }
+            if vector.iter().any(|x| !x.is_finite()) {
+                issues.push(format!("{name} has non-finite components"));
+            } else if cosine && vector.iter().all(|&x| x == 0.0) {
+                issues.push(format!(
+                    "{name} has zero norm, so its cosine distance is undefined"
+                ));
+            }
+        }
+        issues
+    }
     pub unsafe fn input_sphere(self, datum: Datum) -> Option<Sphere<OwnedVector>> {
         if datum.is_null() {
             return None;
@@ -127,7 +199,11 @@ impl Opfamily {
         let attno_2 = NonZero::new(2_usize).unwrap();
         let tuple = unsafe { PgHeapTuple::from_composite_datum(datum) };
         let center = match self {
-            Self::VectorL2 | Self::VectorIp | Self::VectorCosine | Self::VectorMaxsim => {
+            Self::VectorL2
+            | Self::VectorIp
+            | Self::VectorCosine
+            | Self::VectorCosineNormalized
+            | Self::VectorMaxsim => {
                 let vector = tuple.get_by_index::<VectorOutput>(attno_1).unwrap()?;
                 self.input(BorrowedVector::Vecf32(vector.as_borrowed()))
             }
@@ -144,7 +220,11 @@ impl Opfamily {
             return None;
         }
         let vector = match self {
-            Self::VectorL2 | Self::VectorIp | Self::VectorCosine | Self::VectorMaxsim => {
+            Self::VectorL2
+            | Self::VectorIp
+            | Self::VectorCosine
+            | Self::VectorCosineNormalized
+            | Self::VectorMaxsim => {
                 let vector = unsafe { VectorInput::from_datum(datum, false).unwrap() };
                 self.input(BorrowedVector::Vecf32(vector.as_borrowed()))
             }
@@ -155,12 +235,40 @@ impl Opfamily {
         };
         Some(vector)
     }
+    // A query of the other element type is converted to the element type of the index.
+    pub unsafe fn cast_vector(self, datum: Datum) -> Datum {
+        use pgrx::datum::IntoDatum;
+        use vector::vect::VectBorrowed;
+        match self {
+            Self::VectorL2 | Self::VectorIp | Self::VectorCosine | Self::VectorCosineNormalized => {
+                let vector = unsafe { HalfvecInput::from_datum(datum, false).unwrap() };
+                let slice = vector.as_borrowed().slice().iter().map(|x| x.to_f32());
+                let slice = slice.collect::<Vec<_>>();
+                VectorOutput::new(VectBorrowed::new(&slice))
+                    .into_datum()
+                    .unwrap()
+            }
+            Self::HalfvecL2 | Self::HalfvecIp | Self::HalfvecCosine => {
+                let vector = unsafe { VectorInput::from_datum(datum, false).unwrap() };
+                let slice = vector.as_borrowed().slice().iter();
+                let slice = slice.map(|&x| half::f16::from_f32(x)).collect::<Vec<_>>();
+                HalfvecOutput::new(VectBorrowed::new(&slice))
+                    .into_datum()
+                    .unwrap()
+            }
+            Self::VectorMaxsim | Self::HalfvecMaxsim => unreachable!(),
+        }
+    }
     pub unsafe fn input_vectors(self, datum: Datum) -> Option<Vec<OwnedVector>> {
         if datum.is_null() {
             return None;
         }
         let vectors = match self {
-            Self::VectorL2 | Self::VectorIp | Self::VectorCosine | Self::VectorMaxsim => {
+            Self::VectorL2
+            | Self::VectorIp
+            | Self::VectorCosine
+            | Self::VectorCosineNormalized
+            | Self::VectorMaxsim => {
                 let vectors =
                     unsafe { pgrx::Array::<VectorInput>::from_datum(datum, false).unwrap() };
                 let mut result = Vec::with_capacity(vectors.len());
@@ -183,7 +291,9 @@ impl Opfamily {
     }
     pub fn output(self, x: Distance) -> f32 {
         match self {
-            Self::VectorCosine | Self::HalfvecCosine => x.to_f32() + 1.0f32,
+            Self::VectorCosine | Self::VectorCosineNormalized | Self::HalfvecCosine => {
+                x.to_f32() + 1.0f32
+            }
             Self::VectorL2 | Self::HalfvecL2 => x.to_f32().sqrt(),
             Self::VectorIp | Self::HalfvecIp | Self::VectorMaxsim | Self::HalfvecMaxsim => {
                 x.to_f32()
@@ -196,6 +306,7 @@ impl Opfamily {
             Self::VectorIp
             | Self::HalfvecIp
             | Self::VectorCosine
+            | Self::VectorCosineNormalized
             | Self::HalfvecCosine
             | Self::VectorMaxsim
             | Self::HalfvecMaxsim => DistanceKind::Dot,
@@ -203,9 +314,11 @@ impl Opfamily {
     }
     pub const fn vector_kind(self) -> VectorKind {
         match self {
-            Self::VectorL2 | Self::VectorIp | Self::VectorCosine | Self::VectorMaxsim => {
-                VectorKind::Vecf32
-            }
+            Self::VectorL2
+            | Self::VectorIp
+            | Self::VectorCosine
+            | Self::VectorCosineNormalized
+            | Self::VectorMaxsim => VectorKind::Vecf32,
             Self::HalfvecL2 | Self::HalfvecIp | Self::HalfvecCosine | Self::HalfvecMaxsim => {
                 VectorKind::Vecf16
             }
@@ -248,6 +361,7 @@ pub unsafe fn opfamily(index_relation: pgrx::pg_sys::Relation) -> Opfamily {
         "vector_l2_ops" => Opfamily::VectorL2,
         "vector_ip_ops" => Opfamily::VectorIp,
         "vector_cosine_ops" => Opfamily::VectorCosine,
+        "vector_cosine_normalized_ops" => Opfamily::VectorCosineNormalized,
         "halfvec_l2_ops" => Opfamily::HalfvecL2,
         "halfvec_ip_ops" => Opfamily::HalfvecIp,
         "halfvec_cosine_ops" => Opfamily::HalfvecCosine,
@@ -1,6 +1,11 @@
 use random_orthogonal_matrix::random_orthogonal_matrix;
 use std::sync::OnceLock;
 
+// Every vector is rotated before quantization, and so is every query, which spreads
+// the variance of anisotropic data across dimensions. The matrix is generated
+// deterministically from the number of dimensions, so it's not stored in indexes.
+// A query costs an extra `O(n²)` multiplication, and a matrix takes `4n²` bytes
+// of memory per backend once it's used.
 fn matrix(n: usize) -> Option<&'static Vec<Vec<f32>>> {
     static MATRIXS: [OnceLock<Vec<Vec<f32>>>; 1 + 60000] = [const { OnceLock::new() }; 1 + 60000];
     MATRIXS
@@ -20,3 +25,14 @@ pub fn project(vector: &[f32]) -> Vec<f32> {
         .map(|i| f32::reduce_sum_of_xy(vector, &matrix[i]))
         .collect()
 }
+
+pub fn unproject(vector: &[f32]) -> Vec<f32> {
+    use simd::Floating;
+    let n = vector.len();
+    let matrix = matrix(n).expect("dimension too large");
+    let mut result = vec![0.0f32; n];
+    for i in 0..n {
+        f32::vector_add_inplace(&mut result, &f32::vector_mul_scalar(&matrix[i], vector[i]));
+    }
+    result
+}
@@ -1,13 +1,20 @@
-use super::{SearchBuilder, SearchFetcher, SearchIo, SearchOptions};
+use super::{
+    ProbeCacheKey, ScanStats, SearchBuilder, SearchFetcher, SearchIo, SearchOptions, observe,
+    observe_incremental, probe_cache_get, probe_cache_put,
+};
 use crate::index::algorithm::RandomProject;
-use crate::index::am::pointer_to_kv;
+use crate::index::am::{ALIAS, pointer_to_kv};
 use crate::index::opclass::{Opfamily, Sphere};
 use algorithm::operator::{Dot, L2, Op};
 use algorithm::types::{DistanceKind, OwnedVector, VectorKind};
 use algorithm::*;
+use always_equal::AlwaysEqual;
+use distance::Distance;
 use half::f16;
-use std::collections::BinaryHeap;
+use std::cell::Cell;
+use std::cmp::Reverse;
 use std::num::NonZero;
+use std::time::Instant;
 use vector::VectorOwned;
 use vector::vect::VectOwned;
 
@@ -17,6 +24,12 @@ pub struct DefaultBuilder {
     spheres: Vec<Option<Sphere<OwnedVector>>>,
 }
 
+impl DefaultBuilder {
+    pub fn push(&mut self, vector: OwnedVector) {
+        self.orderbys.push(Some(vector));
+    }
+}
+
 impl SearchBuilder for DefaultBuilder {
     fn new(opfamily: Opfamily) -> Self {
         assert!(matches!(
@@ -25,6 +38,7 @@ impl SearchBuilder for DefaultBuilder {
                 | Opfamily::HalfvecIp
                 | Opfamily::HalfvecL2
                 | Opfamily::VectorCosine
+                | Opfamily::VectorCosineNormalized
                 | Opfamily::VectorIp
                 | Opfamily::VectorL2
         ));
@@ -55,6 +69,7 @@ impl SearchBuilder for DefaultBuilder {
         options: SearchOptions,
         mut fetcher: impl SearchFetcher + 'a,
         bump: &'a impl Bump,
+        stats: &'a ScanStats,
     ) -> Box<dyn Iterator<Item = (f32, [u16; 3], bool)> + 'a> {
         let mut vector = None;
         let mut threshold = None;
@@ -73,10 +88,53 @@ impl SearchBuilder for DefaultBuilder {
                 recheck = true;
             }
         }
+        // estimates are not exact, so rows found by them are rechecked by the executor
+        if !options.rerank && threshold.is_some() {
+            recheck = true;
+        }
+        if options.adaptive_epsilon.is_some() && options.max_scan_tuples.is_none() {
+            pgrx::error!("vchordrq.adaptive_epsilon requires vchordrq.max_scan_tuples");
+        }
+        if options.incremental && options.rerank {
+            if options.max_per_list.is_some() {
+                pgrx::error!("vchordrq.max_per_list is not supported by incremental scans");
+            }
+            if options.scan_work_mem.is_some() {
+                pgrx::error!("vchordrq.scan_work_mem is not supported by incremental scans");
+            }
+        }
         let opfamily = self.opfamily;
+        let heap_strategy = options.heap_strategy;
+        let precision = options.rerank_precision;
+        let deadline = options.scan_timeout.map(|timeout| Instant::now() + timeout);
         let Some(vector) = vector else {
             return Box::new(std::iter::empty()) as Box<dyn Iterator<Item = (f32, [u16; 3], bool)>>;
         };
+        let cache = options.cache.map(|index| ProbeCacheKey {
+            index,
+            query: match &vector {
+                OwnedVector::Vecf32(x) => x.slice().iter().map(|x| x.to_bits()).collect(),
+                OwnedVector::Vecf16(x) => x.slice().iter().map(|x| x.to_f32().to_bits()).collect(),
+            },
+            probes: options.probes.clone(),
+            epsilon: options.epsilon.to_bits(),
+        });
+        // lists are counted at all levels, and those at the bottom are replaced by the
+        // ones probed if they're known
+        let cells = cost(relation.clone()).cells;
+        let selected = options
+            .probes
+            .iter()
+            .zip(&cells)
+            .map(|(&probes, &cells)| probes.min(cells) as u64)
+            .collect::<Vec<_>>();
+        let upper = selected.iter().skip(1).sum::<u64>();
+        stats.lists.set(if selected.is_empty() {
+            1
+        } else {
+            selected.iter().sum()
+        });
+        let opened = Cell::new(0_u64);
         let iter: Box<dyn Iterator<Item = (f32, NonZero<u64>)>> =
             match (opfamily.vector_kind(), opfamily.distance_kind()) {
                 (VectorKind::Vecf32, DistanceKind::L2) => {
@@ -88,19 +146,6 @@ impl SearchBuilder for DefaultBuilder {
                         }
                         .as_borrowed(),
                     );
-                    let results = default_search::<_, Op<VectOwned<f32>, L2>, _>(
-                        relation.clone(),
-                        vector.clone(),
-                        options.probes,
-                        options.epsilon,
-                        bump,
-                        {
-                            let index = relation.clone();
-                            move |results| {
-                                PlainPrefetcher::<_, BinaryHeap<_>>::new(index.clone(), results)
-                            }
-                        },
-                    );
                     let fetch = move |payload| {
                         let (key, _) = pointer_to_kv(payload);
                         let (datums, is_nulls) = fetcher.fetch(key)?;
@@ -113,47 +158,146 @@ impl SearchBuilder for DefaultBuilder {
                         };
                         Some(RandomProject::project(raw.as_borrowed()))
                     };
-                    let method = how(relation.clone());
-                    match (method, options.io_rerank) {
-                        (RerankMethod::Index, SearchIo::ReadBuffer) => {
-                            let prefetcher =
-                                PlainPrefetcher::<_, BinaryHeap<_>>::new(relation.clone(), results);
-                            Box::new(
-                                rerank_index::<Op<VectOwned<f32>, L2>, _, _>(vector, prefetcher)
-                                    .map(move |(distance, payload)| {
-                                        (opfamily.output(distance), payload)
-                                    }),
+                    if options.incremental && options.rerank {
+                        let incremental = incremental_search::<_, Op<VectOwned<f32>, L2>, _, _>(
+                            relation.clone(),
+                            vector,
+                            options.probes,
+                            options.epsilon,
+                            bump,
+                            {
+                                let index = relation.clone();
+                                move |results| {
+                                    PlainPrefetcher::with_strategy(
+                                        index.clone(),
+                                        results,
+                                        heap_strategy,
+                                    )
+                                }
+                            },
+                            how(relation.clone()),
+                            precision,
+                            fetch,
+                            deadline,
+                            options.parallel,
+                        );
+                        Box::new(
+                            observe_incremental(incremental, stats, upper).map(
+                                move |(distance, payload)| (opfamily.output(distance), payload),
+                            ),
+                        )
+                    } else {
+                        let results = cached(&cache, stats, |probed| {
+                            default_search::<_, Op<VectOwned<f32>, L2>, _>(
+                                relation.clone(),
+                                vector.clone(),
+                                options.probes,
+                                options.epsilon,
+                                bump,
+                                {
+                                    let index = relation.clone();
+                                    move |results| {
+                                        PlainPrefetcher::with_strategy(
+                                            index.clone(),
+                                            results,
+                                            heap_strategy,
+                                        )
+                                    }
+                                },
+                                options.parallel,
+                                deadline,
+                                options.max_scan_tuples,
+                                options.adaptive_epsilon,
+                                options.max_per_list,
+                                options.scan_work_mem,
+                                &opened,
+                                probed,
                             )
+                        });
+                        stats.candidates.set(results.len() as u64);
+                        if options.parallel.is_none() {
+                            stats.lists.set(upper + opened.get());
                         }
-                        (RerankMethod::Index, SearchIo::PrefetchBuffer) => {
-                            let prefetcher = SimplePrefetcher::new(relation.clone(), results);
-                            Box::new(
-                                rerank_index::<Op<VectOwned<f32>, L2>, _, _>(vector, prefetcher)
-                                    .map(move |(distance, payload)| {
-                                        (opfamily.output(distance), payload)
-                                    }),
-                            )
-                        }
-                        (RerankMethod::Index, SearchIo::ReadStream) => {
-                            let prefetcher = StreamPrefetcher::new(relation, results);
-                            Box::new(
-                                rerank_index::<Op<VectOwned<f32>, L2>, _, _>(vector, prefetcher)
-                                    .map(move |(distance, payload)| {
-                                        (opfamily.output(distance), payload)
-                                    }),
-                            )
-                        }
-                        (RerankMethod::Heap, _) => {
-                            let prefetcher =
-                                PlainPrefetcher::<_, BinaryHeap<_>>::new(relation.clone(), results);
-                            Box::new(
-                                rerank_heap::<Op<VectOwned<f32>, L2>, _, _>(
-                                    vector, prefetcher, fetch,
+                        check(options.rerank, &results);
+                        let method = how(relation.clone());
+                        match (method, options.io_rerank) {
+                            _ if !options.rerank => {
+                                Box::new(estimated(results, threshold.map(|_| Distance::ZERO)).map(
+                                    move |(distance, payload)| (opfamily.output(distance), payload),
+                                ))
+                            }
+                            (RerankMethod::Index, SearchIo::ReadBuffer) => {
+                                let prefetcher = PlainPrefetcher::with_strategy(
+                                    relation.clone(),
+                                    results,
+                                    heap_strategy,
+                                );
+                                Box::new(
+                                    observe(
+                                        rerank_index::<Op<VectOwned<f32>, L2>, _, _>(
+                                            vector, prefetcher, precision,
+                                        ),
+                                        stats,
+                                    )
+                                    .map(
+                                        move |(distance, payload)| {
+                                            (opfamily.output(distance), payload)
+                                        },
+                                    ),
                                 )
-                                .map(move |(distance, payload)| {
-                                    (opfamily.output(distance), payload)
-                                }),
-                            )
+                            }
+                            (RerankMethod::Index, SearchIo::PrefetchBuffer) => {
+                                let prefetcher = SimplePrefetcher::new(relation.clone(), results);
+                                Box::new(
+                                    observe(
+                                        rerank_index::<Op<VectOwned<f32>, L2>, _, _>(
+                                            vector, prefetcher, precision,
+                                        ),
+                                        stats,
+                                    )
+                                    .map(
+                                        move |(distance, payload)| {
+                                            (opfamily.output(distance), payload)
+                                        },
+                                    ),
+                                )
+                            }
+                            (RerankMethod::Index, SearchIo::ReadStream) => {
+                                let prefetcher = StreamPrefetcher::new(relation, results);
+                                Box::new(
+                                    observe(
+                                        rerank_index::<Op<VectOwned<f32>, L2>, _, _>(
+                                            vector, prefetcher, precision,
+                                        ),
+                                        stats,
+                                    )
+                                    .map(
+                                        move |(distance, payload)| {
+                                            (opfamily.output(distance), payload)
+                                        },
+                                    ),
+                                )
+                            }
+                            (RerankMethod::Heap, _) => {
+                                let prefetcher = PlainPrefetcher::with_strategy(
+                                    relation.clone(),
+                                    results,
+                                    heap_strategy,
+                                );
+                                Box::new(
+                                    observe(
+                                        rerank_heap::<Op<VectOwned<f32>, L2>, _, _>(
+                                            vector, prefetcher, fetch, precision,
+                                        ),
+                                        stats,
+                                    )
+                                    .map(
+                                        move |(distance, payload)| {
+                                            (opfamily.output(distance), payload)
+                                        },
+                                    ),
+                                )
+                            }
                         }
                     }
                 }
@@ -166,19 +310,6 @@ impl SearchBuilder for DefaultBuilder {
                         }
                         .as_borrowed(),
                     );
-                    let results = default_search::<_, Op<VectOwned<f32>, Dot>, _>(
-                        relation.clone(),
-                        vector.clone(),
-                        options.probes,
-                        options.epsilon,
-                        bump,
-                        {
-                            let index = relation.clone();
-                            move |results| {
-                                PlainPrefetcher::<_, BinaryHeap<_>>::new(index.clone(), results)
-                            }
-                        },
-                    );
                     let fetch = move |payload| {
                         let (key, _) = pointer_to_kv(payload);
                         let (datums, is_nulls) = fetcher.fetch(key)?;
@@ -191,47 +322,146 @@ impl SearchBuilder for DefaultBuilder {
                         };
                         Some(RandomProject::project(raw.as_borrowed()))
                     };
-                    let method = how(relation.clone());
-                    match (method, options.io_rerank) {
-                        (RerankMethod::Index, SearchIo::ReadBuffer) => {
-                            let prefetcher =
-                                PlainPrefetcher::<_, BinaryHeap<_>>::new(relation.clone(), results);
-                            Box::new(
-                                rerank_index::<Op<VectOwned<f32>, Dot>, _, _>(vector, prefetcher)
-                                    .map(move |(distance, payload)| {
-                                        (opfamily.output(distance), payload)
-                                    }),
+                    if options.incremental && options.rerank {
+                        let incremental = incremental_search::<_, Op<VectOwned<f32>, Dot>, _, _>(
+                            relation.clone(),
+                            vector,
+                            options.probes,
+                            options.epsilon,
+                            bump,
+                            {
+                                let index = relation.clone();
+                                move |results| {
+                                    PlainPrefetcher::with_strategy(
+                                        index.clone(),
+                                        results,
+                                        heap_strategy,
+                                    )
+                                }
+                            },
+                            how(relation.clone()),
+                            precision,
+                            fetch,
+                            deadline,
+                            options.parallel,
+                        );
+                        Box::new(
+                            observe_incremental(incremental, stats, upper).map(
+                                move |(distance, payload)| (opfamily.output(distance), payload),
+                            ),
+                        )
+                    } else {
+                        let results = cached(&cache, stats, |probed| {
+                            default_search::<_, Op<VectOwned<f32>, Dot>, _>(
+                                relation.clone(),
+                                vector.clone(),
+                                options.probes,
+                                options.epsilon,
+                                bump,
+                                {
+                                    let index = relation.clone();
+                                    move |results| {
+                                        PlainPrefetcher::with_strategy(
+                                            index.clone(),
+                                            results,
+                                            heap_strategy,
+                                        )
+                                    }
+                                },
+                                options.parallel,
+                                deadline,
+                                options.max_scan_tuples,
+                                options.adaptive_epsilon,
+                                options.max_per_list,
+                                options.scan_work_mem,
+                                &opened,
+                                probed,
                             )
+                        });
+                        stats.candidates.set(results.len() as u64);
+                        if options.parallel.is_none() {
+                            stats.lists.set(upper + opened.get());
                         }
-                        (RerankMethod::Index, SearchIo::PrefetchBuffer) => {
-                            let prefetcher = SimplePrefetcher::new(relation.clone(), results);
-                            Box::new(
-                                rerank_index::<Op<VectOwned<f32>, Dot>, _, _>(vector, prefetcher)
-                                    .map(move |(distance, payload)| {
-                                        (opfamily.output(distance), payload)
-                                    }),
-                            )
-                        }
-                        (RerankMethod::Index, SearchIo::ReadStream) => {
-                            let prefetcher = StreamPrefetcher::new(relation, results);
-                            Box::new(
-                                rerank_index::<Op<VectOwned<f32>, Dot>, _, _>(vector, prefetcher)
-                                    .map(move |(distance, payload)| {
-                                        (opfamily.output(distance), payload)
-                                    }),
-                            )
-                        }
-                        (RerankMethod::Heap, _) => {
-                            let prefetcher =
-                                PlainPrefetcher::<_, BinaryHeap<_>>::new(relation.clone(), results);
-                            Box::new(
-                                rerank_heap::<Op<VectOwned<f32>, Dot>, _, _>(
-                                    vector, prefetcher, fetch,
+                        check(options.rerank, &results);
+                        let method = how(relation.clone());
+                        match (method, options.io_rerank) {
+                            _ if !options.rerank => Box::new(
+                                estimated(results, threshold.map(|_| Distance::NEG_INFINITY)).map(
+                                    move |(distance, payload)| (opfamily.output(distance), payload),
+                                ),
+                            ),
+                            (RerankMethod::Index, SearchIo::ReadBuffer) => {
+                                let prefetcher = PlainPrefetcher::with_strategy(
+                                    relation.clone(),
+                                    results,
+                                    heap_strategy,
+                                );
+                                Box::new(
+                                    observe(
+                                        rerank_index::<Op<VectOwned<f32>, Dot>, _, _>(
+                                            vector, prefetcher, precision,
+                                        ),
+                                        stats,
+                                    )
+                                    .map(
+                                        move |(distance, payload)| {
+                                            (opfamily.output(distance), payload)
+                                        },
+                                    ),
                                 )
-                                .map(move |(distance, payload)| {
-                                    (opfamily.output(distance), payload)
-                                }),
-                            )
+                            }
+                            (RerankMethod::Index, SearchIo::PrefetchBuffer) => {
+                                let prefetcher = SimplePrefetcher::new(relation.clone(), results);
+                                Box::new(
+                                    observe(
+                                        rerank_index::<Op<VectOwned<f32>, Dot>, _, _>(
+                                            vector, prefetcher, precision,
+                                        ),
+                                        stats,
+                                    )
+                                    .map(
+                                        move |(distance, payload)| {
+                                            (opfamily.output(distance), payload)
+                                        },
+                                    ),
+                                )
+                            }
+                            (RerankMethod::Index, SearchIo::ReadStream) => {
+                                let prefetcher = StreamPrefetcher::new(relation, results);
+                                Box::new(
+                                    observe(
+                                        rerank_index::<Op<VectOwned<f32>, Dot>, _, _>(
+                                            vector, prefetcher, precision,
+                                        ),
+                                        stats,
+                                    )
+                                    .map(
+                                        move |(distance, payload)| {
+                                            (opfamily.output(distance), payload)
+                                        },
+                                    ),
+                                )
+                            }
+                            (RerankMethod::Heap, _) => {
+                                let prefetcher = PlainPrefetcher::with_strategy(
+                                    relation.clone(),
+                                    results,
+                                    heap_strategy,
+                                );
+                                Box::new(
+                                    observe(
+                                        rerank_heap::<Op<VectOwned<f32>, Dot>, _, _>(
+                                            vector, prefetcher, fetch, precision,
+                                        ),
+                                        stats,
+                                    )
+                                    .map(
+                                        move |(distance, payload)| {
+                                            (opfamily.output(distance), payload)
+                                        },
+                                    ),
+                                )
+                            }
                         }
                     }
                 }
@@ -244,19 +474,6 @@ impl SearchBuilder for DefaultBuilder {
                         }
                         .as_borrowed(),
                     );
-                    let results = default_search::<_, Op<VectOwned<f16>, L2>, _>(
-                        relation.clone(),
-                        vector.clone(),
-                        options.probes,
-                        options.epsilon,
-                        bump,
-                        {
-                            let index = relation.clone();
-                            move |results| {
-                                PlainPrefetcher::<_, BinaryHeap<_>>::new(index.clone(), results)
-                            }
-                        },
-                    );
                     let fetch = move |payload| {
                         let (key, _) = pointer_to_kv(payload);
                         let (datums, is_nulls) = fetcher.fetch(key)?;
@@ -269,47 +486,146 @@ impl SearchBuilder for DefaultBuilder {
                         };
                         Some(RandomProject::project(raw.as_borrowed()))
                     };
-                    let method = how(relation.clone());
-                    match (method, options.io_rerank) {
-                        (RerankMethod::Index, SearchIo::ReadBuffer) => {
-                            let prefetcher =
-                                PlainPrefetcher::<_, BinaryHeap<_>>::new(relation.clone(), results);
-                            Box::new(
-                                rerank_index::<Op<VectOwned<f16>, L2>, _, _>(vector, prefetcher)
-                                    .map(move |(distance, payload)| {
-                                        (opfamily.output(distance), payload)
-                                    }),
-                            )
-                        }
-                        (RerankMethod::Index, SearchIo::PrefetchBuffer) => {
-                            let prefetcher = SimplePrefetcher::new(relation.clone(), results);
-                            Box::new(
-                                rerank_index::<Op<VectOwned<f16>, L2>, _, _>(vector, prefetcher)
-                                    .map(move |(distance, payload)| {
-                                        (opfamily.output(distance), payload)
-                                    }),
+                    if options.incremental && options.rerank {
+                        let incremental = incremental_search::<_, Op<VectOwned<f16>, L2>, _, _>(
+                            relation.clone(),
+                            vector,
+                            options.probes,
+                            options.epsilon,
+                            bump,
+                            {
+                                let index = relation.clone();
+                                move |results| {
+                                    PlainPrefetcher::with_strategy(
+                                        index.clone(),
+                                        results,
+                                        heap_strategy,
+                                    )
+                                }
+                            },
+                            how(relation.clone()),
+                            precision,
+                            fetch,
+                            deadline,
+                            options.parallel,
+                        );
+                        Box::new(
+                            observe_incremental(incremental, stats, upper).map(
+                                move |(distance, payload)| (opfamily.output(distance), payload),
+                            ),
+                        )
+                    } else {
+                        let results = cached(&cache, stats, |probed| {
+                            default_search::<_, Op<VectOwned<f16>, L2>, _>(
+                                relation.clone(),
+                                vector.clone(),
+                                options.probes,
+                                options.epsilon,
+                                bump,
+                                {
+                                    let index = relation.clone();
+                                    move |results| {
+                                        PlainPrefetcher::with_strategy(
+                                            index.clone(),
+                                            results,
+                                            heap_strategy,
+                                        )
+                                    }
+                                },
+                                options.parallel,
+                                deadline,
+                                options.max_scan_tuples,
+                                options.adaptive_epsilon,
+                                options.max_per_list,
+                                options.scan_work_mem,
+                                &opened,
+                                probed,
                             )
+                        });
+                        stats.candidates.set(results.len() as u64);
+                        if options.parallel.is_none() {
+                            stats.lists.set(upper + opened.get());
                         }
-                        (RerankMethod::Index, SearchIo::ReadStream) => {
-                            let prefetcher = StreamPrefetcher::new(relation, results);
-                            Box::new(
-                                rerank_index::<Op<VectOwned<f16>, L2>, _, _>(vector, prefetcher)
-                                    .map(move |(distance, payload)| {
-                                        (opfamily.output(distance), payload)
-                                    }),
-                            )
-                        }
-                        (RerankMethod::Heap, _) => {
-                            let prefetcher =
-                                PlainPrefetcher::<_, BinaryHeap<_>>::new(relation.clone(), results);
-                            Box::new(
-                                rerank_heap::<Op<VectOwned<f16>, L2>, _, _>(
-                                    vector, prefetcher, fetch,
+                        check(options.rerank, &results);
+                        let method = how(relation.clone());
+                        match (method, options.io_rerank) {
+                            _ if !options.rerank => {
+                                Box::new(estimated(results, threshold.map(|_| Distance::ZERO)).map(
+                                    move |(distance, payload)| (opfamily.output(distance), payload),
+                                ))
+                            }
+                            (RerankMethod::Index, SearchIo::ReadBuffer) => {
+                                let prefetcher = PlainPrefetcher::with_strategy(
+                                    relation.clone(),
+                                    results,
+                                    heap_strategy,
+                                );
+                                Box::new(
+                                    observe(
+                                        rerank_index::<Op<VectOwned<f16>, L2>, _, _>(
+                                            vector, prefetcher, precision,
+                                        ),
+                                        stats,
+                                    )
+                                    .map(
+                                        move |(distance, payload)| {
+                                            (opfamily.output(distance), payload)
+                                        },
+                                    ),
                                 )
-                                .map(move |(distance, payload)| {
-                                    (opfamily.output(distance), payload)
-                                }),
-                            )
+                            }
+                            (RerankMethod::Index, SearchIo::PrefetchBuffer) => {
+                                let prefetcher = SimplePrefetcher::new(relation.clone(), results);
+                                Box::new(
+                                    observe(
+                                        rerank_index::<Op<VectOwned<f16>, L2>, _, _>(
+                                            vector, prefetcher, precision,
+                                        ),
+                                        stats,
+                                    )
+                                    .map(
+                                        move |(distance, payload)| {
+                                            (opfamily.output(distance), payload)
+                                        },
+                                    ),
+                                )
+                            }
+                            (RerankMethod::Index, SearchIo::ReadStream) => {
+                                let prefetcher = StreamPrefetcher::new(relation, results);
+                                Box::new(
+                                    observe(
+                                        rerank_index::<Op<VectOwned<f16>, L2>, _, _>(
+                                            vector, prefetcher, precision,
+                                        ),
+                                        stats,
+                                    )
+                                    .map(
+                                        move |(distance, payload)| {
+                                            (opfamily.output(distance), payload)
+                                        },
+                                    ),
+                                )
+                            }
+                            (RerankMethod::Heap, _) => {
+                                let prefetcher = PlainPrefetcher::with_strategy(
+                                    relation.clone(),
+                                    results,
+                                    heap_strategy,
+                                );
+                                Box::new(
+                                    observe(
+                                        rerank_heap::<Op<VectOwned<f16>, L2>, _, _>(
+                                            vector, prefetcher, fetch, precision,
+                                        ),
+                                        stats,
+                                    )
+                                    .map(
+                                        move |(distance, payload)| {
+                                            (opfamily.output(distance), payload)
+                                        },
+                                    ),
+                                )
+                            }
                         }
                     }
                 }
@@ -322,19 +638,6 @@ impl SearchBuilder for DefaultBuilder {
                         }
                         .as_borrowed(),
                     );
-                    let results = default_search::<_, Op<VectOwned<f16>, Dot>, _>(
-                        relation.clone(),
-                        vector.clone(),
-                        options.probes,
-                        options.epsilon,
-                        bump,
-                        {
-                            let index = relation.clone();
-                            move |results| {
-                                PlainPrefetcher::<_, BinaryHeap<_>>::new(index.clone(), results)
-                            }
-                        },
-                    );
                     let fetch = move |payload| {
                         let (key, _) = pointer_to_kv(payload);
                         let (datums, is_nulls) = fetcher.fetch(key)?;
@@ -347,47 +650,146 @@ impl SearchBuilder for DefaultBuilder {
                         };
                         Some(RandomProject::project(raw.as_borrowed()))
                     };
-                    let method = how(relation.clone());
-                    match (method, options.io_rerank) {
-                        (RerankMethod::Index, SearchIo::ReadBuffer) => {
-                            let prefetcher =
-                                PlainPrefetcher::<_, BinaryHeap<_>>::new(relation.clone(), results);
-                            Box::new(
-                                rerank_index::<Op<VectOwned<f16>, Dot>, _, _>(vector, prefetcher)
-                                    .map(move |(distance, payload)| {
-                                        (opfamily.output(distance), payload)
-                                    }),
-                            )
-                        }
-                        (RerankMethod::Index, SearchIo::PrefetchBuffer) => {
-                            let prefetcher = SimplePrefetcher::new(relation.clone(), results);
-                            Box::new(
-                                rerank_index::<Op<VectOwned<f16>, Dot>, _, _>(vector, prefetcher)
-                                    .map(move |(distance, payload)| {
-                                        (opfamily.output(distance), payload)
-                                    }),
-                            )
-                        }
-                        (RerankMethod::Index, SearchIo::ReadStream) => {
-                            let prefetcher = StreamPrefetcher::new(relation, results);
-                            Box::new(
-                                rerank_index::<Op<VectOwned<f16>, Dot>, _, _>(vector, prefetcher)
-                                    .map(move |(distance, payload)| {
-                                        (opfamily.output(distance), payload)
-                                    }),
+                    if options.incremental && options.rerank {
+                        let incremental = incremental_search::<_, Op<VectOwned<f16>, Dot>, _, _>(
+                            relation.clone(),
+                            vector,
+                            options.probes,
+                            options.epsilon,
+                            bump,
+                            {
+                                let index = relation.clone();
+                                move |results| {
+                                    PlainPrefetcher::with_strategy(
+                                        index.clone(),
+                                        results,
+                                        heap_strategy,
+                                    )
+                                }
+                            },
+                            how(relation.clone()),
+                            precision,
+                            fetch,
+                            deadline,
+                            options.parallel,
+                        );
+                        Box::new(
+                            observe_incremental(incremental, stats, upper).map(
+                                move |(distance, payload)| (opfamily.output(distance), payload),
+                            ),
+                        )
+                    } else {
+                        let results = cached(&cache, stats, |probed| {
+                            default_search::<_, Op<VectOwned<f16>, Dot>, _>(
+                                relation.clone(),
+                                vector.clone(),
+                                options.probes,
+                                options.epsilon,
+                                bump,
+                                {
+                                    let index = relation.clone();
+                                    move |results| {
+                                        PlainPrefetcher::with_strategy(
+                                            index.clone(),
+                                            results,
+                                            heap_strategy,
+                                        )
+                                    }
+                                },
+                                options.parallel,
+                                deadline,
+                                options.max_scan_tuples,
+                                options.adaptive_epsilon,
+                                options.max_per_list,
+                                options.scan_work_mem,
+                                &opened,
+                                probed,
                             )
+                        });
+                        stats.candidates.set(results.len() as u64);
+                        if options.parallel.is_none() {
+                            stats.lists.set(upper + opened.get());
                         }
-                        (RerankMethod::Heap, _) => {
-                            let prefetcher =
-                                PlainPrefetcher::<_, BinaryHeap<_>>::new(relation.clone(), results);
-                            Box::new(
-                                rerank_heap::<Op<VectOwned<f16>, Dot>, _, _>(
-                                    vector, prefetcher, fetch,
+                        check(options.rerank, &results);
+                        let method = how(relation.clone());
+                        match (method, options.io_rerank) {
+                            _ if !options.rerank => Box::new(
+                                estimated(results, threshold.map(|_| Distance::NEG_INFINITY)).map(
+                                    move |(distance, payload)| (opfamily.output(distance), payload),
+                                ),
+                            ),
+                            (RerankMethod::Index, SearchIo::ReadBuffer) => {
+                                let prefetcher = PlainPrefetcher::with_strategy(
+                                    relation.clone(),
+                                    results,
+                                    heap_strategy,
+                                );
+                                Box::new(
+                                    observe(
+                                        rerank_index::<Op<VectOwned<f16>, Dot>, _, _>(
+                                            vector, prefetcher, precision,
+                                        ),
+                                        stats,
+                                    )
+                                    .map(
+                                        move |(distance, payload)| {
+                                            (opfamily.output(distance), payload)
+                                        },
+                                    ),
                                 )
-                                .map(move |(distance, payload)| {
-                                    (opfamily.output(distance), payload)
-                                }),
-                            )
+                            }
+                            (RerankMethod::Index, SearchIo::PrefetchBuffer) => {
+                                let prefetcher = SimplePrefetcher::new(relation.clone(), results);
+                                Box::new(
+                                    observe(
+                                        rerank_index::<Op<VectOwned<f16>, Dot>, _, _>(
+                                            vector, prefetcher, precision,
+                                        ),
+                                        stats,
+                                    )
+                                    .map(
+                                        move |(distance, payload)| {
+                                            (opfamily.output(distance), payload)
+                                        },
+                                    ),
+                                )
+                            }
+                            (RerankMethod::Index, SearchIo::ReadStream) => {
+                                let prefetcher = StreamPrefetcher::new(relation, results);
+                                Box::new(
+                                    observe(
+                                        rerank_index::<Op<VectOwned<f16>, Dot>, _, _>(
+                                            vector, prefetcher, precision,
+                                        ),
+                                        stats,
+                                    )
+                                    .map(
+                                        move |(distance, payload)| {
+                                            (opfamily.output(distance), payload)
+                                        },
+                                    ),
+                                )
+                            }
+                            (RerankMethod::Heap, _) => {
+                                let prefetcher = PlainPrefetcher::with_strategy(
+                                    relation.clone(),
+                                    results,
+                                    heap_strategy,
+                                );
+                                Box::new(
+                                    observe(
+                                        rerank_heap::<Op<VectOwned<f16>, Dot>, _, _>(
+                                            vector, prefetcher, fetch, precision,
+                                        ),
+                                        stats,
+                                    )
+                                    .map(
+                                        move |(distance, payload)| {
+                                            (opfamily.output(distance), payload)
+                                        },
+                                    ),
+                                )
+                            }
                         }
                     }
                 }
@@ -397,14 +799,94 @@ impl SearchBuilder for DefaultBuilder {
         } else {
             iter
         };
-        let iter = if let Some(max_scan_tuples) = options.max_scan_tuples {
+        let mut aliases = None;
+        let iter = iter.flat_map(move |(distance, pointer)| {
+            let members = if pointer_to_kv(pointer).1 == ALIAS {
+                let aliases = aliases.get_or_insert_with(|| Aliases::new(relation.clone()));
+                aliases.get(pointer)
+            } else {
+                vec![pointer]
+            };
+            members.into_iter().map(move |member| {
+                let (key, _) = pointer_to_kv(member);
+                (distance, key, recheck)
+            })
+        });
+        // `max_scan_tuples` counts rows, so it is applied after groups are expanded.
+        if let Some(max_scan_tuples) = options.max_scan_tuples {
             Box::new(iter.take(max_scan_tuples as _))
         } else {
-            iter
-        };
-        Box::new(iter.map(move |(distance, pointer)| {
-            let (key, _) = pointer_to_kv(pointer);
-            (distance, key, recheck)
-        }))
+            Box::new(iter)
+        }
     }
 }
+
+// Incremental search always walks upper levels, so only default search is cached.
+fn cached<V: Clone + Send + 'static, T>(
+    cache: &Option<ProbeCacheKey>,
+    stats: &ScanStats,
+    f: impl FnOnce(&mut Option<Probed<V>>) -> T,
+) -> T {
+    let mut probed = cache.as_ref().and_then(probe_cache_get::<Probed<V>>);
+    let hit = probed.is_some();
+    let result = f(&mut probed);
+    if let Some(probed) = probed.filter(|_| !hit) {
+        stats.centroids.set(probed.centroids);
+        if let Some(key) = cache.clone() {
+            probe_cache_put(key, probed);
+        }
+    }
+    result
+}
+
+// Codes that give no bounds are reranked by vectors, so results are still exact, but
+// without reranking no vector is read, so their estimated distances are returned.
+fn check(
+    rerank: bool,
+    results: &[(
+        (Reverse<Distance>, AlwaysEqual<Distance>),
+        AlwaysEqual<&mut (NonZero<u64>, u16, &mut [u32])>,
+    )],
+) {
+    let invalid = results
+        .iter()
+        .filter(|((Reverse(lowerbound), _), _)| *lowerbound == Distance::NEG_INFINITY)
+        .count();
+    if invalid != 0 {
+        if rerank {
+            pgrx::warning!(
+                "{invalid} codes in the index are invalid, so exact distances are used for them"
+            );
+        } else {
+            pgrx::warning!(
+                "{invalid} codes in the index are invalid, so their rows are returned in order of unreliable estimated distances"
+            );
+        }
+    }
+}
+
+// Candidates are returned in order of estimated distances, so original vectors are never read.
+// Estimates may undershoot or overshoot, so a range query takes candidates by lower bounds,
+// which are not less than `floor`, and rows are rechecked.
+fn estimated(
+    results: Vec<(
+        (Reverse<Distance>, AlwaysEqual<Distance>),
+        AlwaysEqual<&mut (NonZero<u64>, u16, &mut [u32])>,
+    )>,
+    floor: Option<Distance>,
+) -> std::vec::IntoIter<(Distance, NonZero<u64>)> {
+    let mut results = results
+        .into_iter()
+        .map(
+            |((Reverse(lowerbound), AlwaysEqual(rough)), AlwaysEqual(&mut (payload, ..)))| {
+                if let Some(floor) = floor {
+                    (lowerbound.max(floor), payload)
+                } else {
+                    (rough, payload)
+                }
+            },
+        )
+        .collect::<Vec<_>>();
+    results.sort_unstable();
+    results.into_iter()
+}
@@ -1,4 +1,4 @@
-use super::{SearchBuilder, SearchFetcher, SearchOptions};
+use super::{ScanStats, SearchBuilder, SearchFetcher, SearchOptions};
 use crate::index::algorithm::RandomProject;
 use crate::index::am::pointer_to_kv;
 use crate::index::opclass::Opfamily;
@@ -12,6 +12,7 @@ use half::f16;
 use std::cmp::Reverse;
 use std::collections::BinaryHeap;
 use std::num::NonZero;
+use std::sync::atomic::Ordering;
 use vector::VectorOwned;
 use vector::vect::VectOwned;
 
@@ -48,6 +49,7 @@ impl SearchBuilder for MaxsimBuilder {
         options: SearchOptions,
         _: impl SearchFetcher + 'a,
         bump: &'a impl Bump,
+        stats: &'a ScanStats,
     ) -> Box<dyn Iterator<Item = (f32, [u16; 3], bool)> + 'a> {
         let mut vectors = None;
         for orderby_vectors in self.orderbys.into_iter().flatten() {
@@ -62,10 +64,23 @@ impl SearchBuilder for MaxsimBuilder {
         }
         let maxsim_refine = options.maxsim_refine;
         let maxsim_threshold = options.maxsim_threshold;
+        let heap_strategy = options.heap_strategy;
+        let precision = options.rerank_precision;
         let opfamily = self.opfamily;
         let Some(vectors) = vectors else {
             return Box::new(std::iter::empty()) as Box<dyn Iterator<Item = (f32, [u16; 3], bool)>>;
         };
+        // maxsim search is not split, so a participant of a parallel scan claims all of it,
+        // though the planner doesn't consider a parallel scan for it
+        if let Some(next) = options.parallel {
+            if next.fetch_add(1, Ordering::Relaxed) != 0 {
+                return Box::new(std::iter::empty());
+            }
+        }
+        let lists = match options.probes.first() {
+            Some(&probes) => probes.min(cost(relation.clone()).cells[0]) as u64,
+            None => 1,
+        };
         let method = how(relation.clone());
         if !matches!(method, RerankMethod::Index) {
             pgrx::error!("maxsim search with rerank_in_table is not supported");
@@ -104,21 +119,33 @@ impl SearchBuilder for MaxsimBuilder {
                         {
                             let index = relation.clone();
                             move |results| {
-                                PlainPrefetcher::<_, BinaryHeap<_>>::new(index.clone(), results)
+                                PlainPrefetcher::with_strategy(
+                                    index.clone(),
+                                    results,
+                                    heap_strategy,
+                                )
                             }
                         },
                     );
                     let (mut accu_set, mut rough_set) = (Vec::new(), Vec::new());
+                    stats.lists.set(stats.lists.get() + lists);
+                    stats
+                        .candidates
+                        .set(stats.candidates.get() + results.len() as u64);
                     if maxsim_refine != 0 && !results.is_empty() {
                         match options.io_rerank {
                             SearchIo::ReadBuffer => {
-                                let prefetcher = PlainPrefetcher::<_, BinaryHeap<_>>::new(
+                                let prefetcher = PlainPrefetcher::with_strategy(
                                     relation.clone(),
                                     results,
+                                    heap_strategy,
                                 );
                                 let mut reranker =
-                                    rerank_index::<Op, _, _>(vector.clone(), prefetcher);
+                                    rerank_index::<Op, _, _>(vector.clone(), prefetcher, precision);
                                 accu_set.extend(reranker.by_ref().take(maxsim_refine as _));
+                                stats
+                                    .reranked
+                                    .set(stats.reranked.get() + reranker.reranked());
                                 let (rough_iter, accu_iter) = reranker.finish();
                                 accu_set.extend(accu_iter.map(accu_map));
                                 rough_set.extend(rough_iter.into_iter().map(rough_map));
@@ -126,8 +153,11 @@ impl SearchBuilder for MaxsimBuilder {
                             SearchIo::PrefetchBuffer => {
                                 let prefetcher = SimplePrefetcher::new(relation.clone(), results);
                                 let mut reranker =
-                                    rerank_index::<Op, _, _>(vector.clone(), prefetcher);
+                                    rerank_index::<Op, _, _>(vector.clone(), prefetcher, precision);
                                 accu_set.extend(reranker.by_ref().take(maxsim_refine as _));
+                                stats
+                                    .reranked
+                                    .set(stats.reranked.get() + reranker.reranked());
                                 let (rough_iter, accu_iter) = reranker.finish();
                                 accu_set.extend(accu_iter.map(accu_map));
                                 rough_set.extend(rough_iter.into_iter().map(rough_map));
@@ -135,8 +165,11 @@ impl SearchBuilder for MaxsimBuilder {
                             SearchIo::ReadStream => {
                                 let prefetcher = StreamPrefetcher::new(relation, results);
                                 let mut reranker =
-                                    rerank_index::<Op, _, _>(vector.clone(), prefetcher);
+                                    rerank_index::<Op, _, _>(vector.clone(), prefetcher, precision);
                                 accu_set.extend(reranker.by_ref().take(maxsim_refine as _));
+                                stats
+                                    .reranked
+                                    .set(stats.reranked.get() + reranker.reranked());
                                 let (rough_iter, accu_iter) = reranker.finish();
                                 accu_set.extend(accu_iter.map(accu_map));
                                 rough_set.extend(rough_iter.into_iter().map(rough_map));
@@ -175,21 +208,33 @@ impl SearchBuilder for MaxsimBuilder {
                         {
                             let index = relation.clone();
                             move |results| {
-                                PlainPrefetcher::<_, BinaryHeap<_>>::new(index.clone(), results)
+                                PlainPrefetcher::with_strategy(
+                                    index.clone(),
+                                    results,
+                                    heap_strategy,
+                                )
                             }
                         },
                     );
                     let (mut accu_set, mut rough_set) = (Vec::new(), Vec::new());
+                    stats.lists.set(stats.lists.get() + lists);
+                    stats
+                        .candidates
+                        .set(stats.candidates.get() + results.len() as u64);
                     if maxsim_refine != 0 && !results.is_empty() {
                         match options.io_rerank {
                             SearchIo::ReadBuffer => {
-                                let prefetcher = PlainPrefetcher::<_, BinaryHeap<_>>::new(
+                                let prefetcher = PlainPrefetcher::with_strategy(
                                     relation.clone(),
                                     results,
+                                    heap_strategy,
                                 );
                                 let mut reranker =
-                                    rerank_index::<Op, _, _>(vector.clone(), prefetcher);
+                                    rerank_index::<Op, _, _>(vector.clone(), prefetcher, precision);
                                 accu_set.extend(reranker.by_ref().take(maxsim_refine as _));
+                                stats
+                                    .reranked
+                                    .set(stats.reranked.get() + reranker.reranked());
                                 let (rough_iter, accu_iter) = reranker.finish();
                                 accu_set.extend(accu_iter.map(accu_map));
                                 rough_set.extend(rough_iter.into_iter().map(rough_map));
@@ -197,8 +242,11 @@ impl SearchBuilder for MaxsimBuilder {
                             SearchIo::PrefetchBuffer => {
                                 let prefetcher = SimplePrefetcher::new(relation.clone(), results);
                                 let mut reranker =
-                                    rerank_index::<Op, _, _>(vector.clone(), prefetcher);
+                                    rerank_index::<Op, _, _>(vector.clone(), prefetcher, precision);
                                 accu_set.extend(reranker.by_ref().take(maxsim_refine as _));
+                                stats
+                                    .reranked
+                                    .set(stats.reranked.get() + reranker.reranked());
                                 let (rough_iter, accu_iter) = reranker.finish();
                                 accu_set.extend(accu_iter.map(accu_map));
                                 rough_set.extend(rough_iter.into_iter().map(rough_map));
@@ -206,8 +254,11 @@ impl SearchBuilder for MaxsimBuilder {
                             SearchIo::ReadStream => {
                                 let prefetcher = StreamPrefetcher::new(relation, results);
                                 let mut reranker =
-                                    rerank_index::<Op, _, _>(vector.clone(), prefetcher);
+                                    rerank_index::<Op, _, _>(vector.clone(), prefetcher, precision);
                                 accu_set.extend(reranker.by_ref().take(maxsim_refine as _));
+                                stats
+                                    .reranked
+                                    .set(stats.reranked.get() + reranker.reranked());
                                 let (rough_iter, accu_iter) = reranker.finish();
                                 accu_set.extend(accu_iter.map(accu_map));
                                 rough_set.extend(rough_iter.into_iter().map(rough_map));
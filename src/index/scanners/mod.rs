@@ -3,8 +3,21 @@ mod maxsim;
 
 use super::opclass::Opfamily;
 use crate::index::lazy_cell::LazyCell;
-use algorithm::{Bump, RelationPrefetch, RelationReadStream};
-use pgrx::pg_sys::Datum;
+use algorithm::operator::Operator;
+use algorithm::{
+    Bump, HeapStrategy, Incremental, RelationPrefetch, RelationReadStream, RerankPrecision,
+    Reranker,
+};
+use distance::Distance;
+use pgrx::PgAtomic;
+use pgrx::pg_sys::{Datum, Oid};
+use std::any::Any;
+use std::cell::Cell;
+use std::cmp::Reverse;
+use std::num::NonZero;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
 
 pub use default::DefaultBuilder;
 pub use maxsim::MaxsimBuilder;
@@ -25,9 +38,21 @@ pub struct SearchOptions {
     pub epsilon: f32,
     pub probes: Vec<u32>,
     pub max_scan_tuples: Option<u32>,
+    pub adaptive_epsilon: Option<f32>,
+    pub max_per_list: Option<u32>,
+    pub scan_work_mem: Option<usize>,
     pub maxsim_refine: u32,
     pub maxsim_threshold: u32,
     pub io_rerank: SearchIo,
+    pub heap_strategy: HeapStrategy,
+    pub incremental: bool,
+    pub rerank: bool,
+    pub rerank_precision: RerankPrecision,
+    pub scan_timeout: Option<Duration>,
+    // the index and its relfilenode, if lists selected for a query are cached
+    pub cache: Option<(Oid, Oid)>,
+    // the counter lives in the shared memory of a parallel scan, which outlives the scan
+    pub parallel: Option<&'static AtomicU32>,
 }
 
 pub trait SearchBuilder: 'static {
@@ -41,6 +66,7 @@ pub trait SearchBuilder: 'static {
         options: SearchOptions,
         fetcher: impl SearchFetcher + 'a,
         bump: &'a impl Bump,
+        stats: &'a ScanStats,
     ) -> Box<dyn Iterator<Item = (f32, [u16; 3], bool)> + 'a>;
 }
 
@@ -53,3 +79,215 @@ impl<T: SearchFetcher, F: FnOnce() -> T> SearchFetcher for LazyCell<T, F> {
         LazyCell::force_mut(self).fetch(key)
     }
 }
+
+#[derive(Debug, Default)]
+pub struct ScanStats {
+    pub lists: Cell<u64>,
+    pub candidates: Cell<u64>,
+    pub reranked: Cell<u64>,
+    pub centroids: Cell<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScanReport {
+    pub lists: u64,
+    pub candidates: u64,
+    pub reranked: u64,
+    pub pages: u64,
+    pub centroids: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeCacheKey {
+    pub index: (Oid, Oid),
+    // bits of elements, so that only the identical query hits
+    pub query: Vec<u32>,
+    pub probes: Vec<u32>,
+    pub epsilon: u32,
+}
+
+// Lists selected by upper levels for recent queries of this session. Centroids are
+// only changed by a rebuild, which assigns a new relfilenode, but entries of an index
+// are dropped once the index is modified in this session anyway.
+static PROBE_CACHE: Mutex<Vec<(ProbeCacheKey, Box<dyn Any + Send>)>> = Mutex::new(Vec::new());
+
+const PROBE_CACHE_CAPACITY: usize = 16;
+
+pub fn probe_cache_get<T: Clone + 'static>(key: &ProbeCacheKey) -> Option<T> {
+    let cache = PROBE_CACHE.lock().unwrap();
+    let (_, value) = cache.iter().find(|(k, _)| k == key)?;
+    value.downcast_ref::<T>().cloned()
+}
+
+pub fn probe_cache_put<T: Send + 'static>(key: ProbeCacheKey, value: T) {
+    let mut cache = PROBE_CACHE.lock().unwrap();
+    cache.retain(|(k, _)| *k != key);
+    if cache.len() >= PROBE_CACHE_CAPACITY {
+        cache.remove(0);
+    }
+    cache.push((key, Box::new(value)));
+}
+
+pub fn probe_cache_invalidate(index: Oid) {
+    PROBE_CACHE
+        .lock()
+        .unwrap()
+        .retain(|(k, _)| k.index.0 != index);
+}
+
+static LAST_SCAN_REPORT: Mutex<Option<ScanReport>> = Mutex::new(None);
+
+pub fn set_last_scan_report(report: ScanReport) {
+    *LAST_SCAN_REPORT.lock().unwrap() = Some(report);
+}
+
+pub fn last_scan_report() -> Option<ScanReport> {
+    *LAST_SCAN_REPORT.lock().unwrap()
+}
+
+// process-wide totals of all scans since startup, kept in shared memory
+static GLOBAL_SCANS: PgAtomic<AtomicU64> = unsafe { PgAtomic::new(c"vchord_global_scans") };
+static GLOBAL_LISTS: PgAtomic<AtomicU64> = unsafe { PgAtomic::new(c"vchord_global_lists") };
+static GLOBAL_CANDIDATES: PgAtomic<AtomicU64> =
+    unsafe { PgAtomic::new(c"vchord_global_candidates") };
+static GLOBAL_RERANKED: PgAtomic<AtomicU64> = unsafe { PgAtomic::new(c"vchord_global_reranked") };
+static GLOBAL_PAGES: PgAtomic<AtomicU64> = unsafe { PgAtomic::new(c"vchord_global_pages") };
+
+pub fn init() {
+    pgrx::pg_shmem_init!(GLOBAL_SCANS);
+    pgrx::pg_shmem_init!(GLOBAL_LISTS);
+    pgrx::pg_shmem_init!(GLOBAL_CANDIDATES);
+    pgrx::pg_shmem_init!(GLOBAL_RERANKED);
+    pgrx::pg_shmem_init!(GLOBAL_PAGES);
+}
+
+fn global_counters() -> [&'static AtomicU64; 5] {
+    [
+        GLOBAL_SCANS.get(),
+        GLOBAL_LISTS.get(),
+        GLOBAL_CANDIDATES.get(),
+        GLOBAL_RERANKED.get(),
+        GLOBAL_PAGES.get(),
+    ]
+}
+
+pub fn record_global_report(report: ScanReport) {
+    let [scans, lists, candidates, reranked, pages] = global_counters();
+    scans.fetch_add(1, Ordering::Relaxed);
+    lists.fetch_add(report.lists, Ordering::Relaxed);
+    candidates.fetch_add(report.candidates, Ordering::Relaxed);
+    reranked.fetch_add(report.reranked, Ordering::Relaxed);
+    pages.fetch_add(report.pages, Ordering::Relaxed);
+}
+
+// returns the number of scans and the sum of their reports
+pub fn global_report() -> (u64, ScanReport) {
+    let [scans, lists, candidates, reranked, pages] = global_counters();
+    (
+        scans.load(Ordering::Relaxed),
+        ScanReport {
+            lists: lists.load(Ordering::Relaxed),
+            candidates: candidates.load(Ordering::Relaxed),
+            reranked: reranked.load(Ordering::Relaxed),
+            pages: pages.load(Ordering::Relaxed),
+            // centroids are only reported for the last scan
+            centroids: 0,
+        },
+    )
+}
+
+pub fn reset_global_report() {
+    for counter in global_counters() {
+        counter.store(0, Ordering::Relaxed);
+    }
+}
+
+// The first `n` results are reordered by keys, and each result is given a lower bound
+// of distances of it and all results after it, so that the executor restores the order
+// by distances. Distances computed by the executor differ by rounding, or by precision
+// of reranking, so bounds are loosened.
+pub fn reorder_by_key<'a>(
+    mut iter: Box<dyn Iterator<Item = (f32, [u16; 3], bool)> + 'a>,
+    n: u32,
+) -> Box<dyn Iterator<Item = (f32, [u16; 3], bool)> + 'a> {
+    if n == 0 {
+        return iter;
+    }
+    let loosen = |x: f32| x - (x.abs() + 1.0) * 1e-2;
+    let mut head = iter.by_ref().take(n as _).collect::<Vec<_>>();
+    let lowerbound = head
+        .iter()
+        .map(|&(distance, ..)| distance)
+        .fold(f32::INFINITY, f32::min);
+    head.sort_unstable_by_key(|&(_, key, _)| key);
+    Box::new(
+        head.into_iter()
+            .map(move |(_, key, recheck)| (loosen(lowerbound), key, recheck))
+            .chain(iter.map(move |(distance, key, recheck)| (loosen(distance), key, recheck))),
+    )
+}
+
+// results with equal distances are ordered by key, or shuffled by `seed`
+pub fn order_ties<'a>(
+    iter: Box<dyn Iterator<Item = (f32, [u16; 3], bool)> + 'a>,
+    seed: Option<i64>,
+) -> Box<dyn Iterator<Item = (f32, [u16; 3], bool)> + 'a> {
+    let mut iter = iter.peekable();
+    let mut group = Vec::new();
+    Box::new(std::iter::from_fn(move || {
+        if group.is_empty() {
+            let first = iter.next()?;
+            group.push(first);
+            while let Some(next) = iter.next_if(|&(distance, ..)| distance == first.0) {
+                group.push(next);
+            }
+            // reversed, since results are popped from the back
+            if let Some(seed) = seed {
+                group.sort_unstable_by_key(|&(_, key, _)| Reverse((mix(seed, key), key)));
+            } else {
+                group.sort_unstable_by_key(|&(_, key, _)| Reverse(key));
+            }
+        }
+        group.pop()
+    }))
+}
+
+// splitmix64
+pub fn mix(seed: i64, [a, b, c]: [u16; 3]) -> u64 {
+    let key = ((a as u64) << 32) | ((b as u64) << 16) | (c as u64);
+    let mut x = (seed as u64 ^ key).wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+fn observe<'a, T: 'a, F: 'a, P: 'a>(
+    mut reranker: Reranker<T, F, P>,
+    stats: &'a ScanStats,
+) -> impl Iterator<Item = (Distance, NonZero<u64>)> + 'a
+where
+    Reranker<T, F, P>: Iterator<Item = (Distance, NonZero<u64>)>,
+{
+    std::iter::from_fn(move || {
+        let next = reranker.next();
+        stats.reranked.set(reranker.reranked());
+        next
+    })
+}
+
+fn observe_incremental<'a, R: 'a, O: Operator, F: 'a>(
+    mut incremental: Incremental<R, O, F>,
+    stats: &'a ScanStats,
+    upper: u64,
+) -> impl Iterator<Item = (Distance, NonZero<u64>)> + 'a
+where
+    Incremental<R, O, F>: Iterator<Item = (Distance, NonZero<u64>)>,
+{
+    std::iter::from_fn(move || {
+        let next = incremental.next();
+        stats.lists.set(upper + incremental.lists());
+        stats.candidates.set(incremental.candidates());
+        stats.reranked.set(incremental.reranked());
+        next
+    })
+}
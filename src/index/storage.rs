@@ -4,12 +4,19 @@ use std::iter::{Chain, Flatten};
 use std::mem::{MaybeUninit, offset_of};
 use std::ops::{Deref, DerefMut};
 use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static PAGES_READ: AtomicU64 = AtomicU64::new(0);
+
+pub fn pages_read() -> u64 {
+    PAGES_READ.load(Ordering::Relaxed)
+}
 
 const _: () = assert!(
     offset_of!(pgrx::pg_sys::PageHeaderData, pd_linp) % pgrx::pg_sys::MAXIMUM_ALIGNOF as usize == 0
 );
 
-const fn size_of_contents() -> usize {
+pub const fn size_of_contents() -> usize {
     use pgrx::pg_sys::{BLCKSZ, PageHeaderData};
     let size_of_page = BLCKSZ as usize;
     let size_of_header = offset_of!(PageHeaderData, pd_linp);
@@ -133,6 +140,21 @@ impl Page for PostgresPage {
             if i == 0 { None } else { Some(i) }
         }
     }
+    fn alloc_aligned(&mut self, data: &[u8], offset: u16, align: u16) -> Option<u16> {
+        use pgrx::pg_sys::MAXIMUM_ALIGNOF;
+        assert!(align.is_power_of_two() && align as u32 >= MAXIMUM_ALIGNOF);
+        assert!(offset as u32 % MAXIMUM_ALIGNOF == 0);
+        let upper = self.header.pd_upper as usize;
+        let start = upper.checked_sub(data.len().next_multiple_of(MAXIMUM_ALIGNOF as usize))?;
+        // tuples are placed from the end of the page, so trailing zeros move the tuple forward
+        let padding = (start + offset as usize) % align as usize;
+        if padding == 0 {
+            return self.alloc(data);
+        }
+        let mut padded = data.to_vec();
+        padded.resize(data.len() + padding, 0);
+        self.alloc(&padded)
+    }
     fn free(&mut self, i: u16) {
         unsafe {
             pgrx::pg_sys::PageIndexTupleDeleteNoCompact((self as *mut Self).cast(), i);
@@ -284,6 +306,7 @@ impl RelationRead for PostgresRelation {
                 std::ptr::null_mut(),
             );
             LockBuffer(buf, BUFFER_LOCK_SHARE as _);
+            PAGES_READ.fetch_add(1, Ordering::Relaxed);
             let page = NonNull::new(BufferGetPage(buf).cast()).expect("failed to get page");
             PostgresBufferReadGuard { buf, page, id }
         }
@@ -476,6 +499,7 @@ where
                     };
                     let buf = read_stream_next_buffer(self.raw, core::ptr::null_mut());
                     LockBuffer(buf, BUFFER_LOCK_SHARE as _);
+                    PAGES_READ.fetch_add(1, Ordering::Relaxed);
                     let page = NonNull::new(BufferGetPage(buf).cast()).expect("failed to get page");
                     PostgresBufferReadGuard {
                         buf,
@@ -0,0 +1,142 @@
+use algorithm::FastHeap;
+use always_equal::AlwaysEqual;
+use pgrx::datum::Internal;
+use pgrx::memcxt::PgMemoryContexts;
+use pgrx::pg_sys::{Datum, FunctionCallInfo, MemoryContext};
+use std::cmp::Reverse;
+
+// keys are ordered by the total order of `f64`, and ties by arrival
+type Key = (i64, u64);
+
+struct State {
+    k: usize,
+    items: Vec<Item>,
+    threshold: Option<Key>,
+    sequence: u64,
+    element: pgrx::pg_sys::Oid,
+    typlen: i16,
+    typbyval: bool,
+    typalign: core::ffi::c_char,
+}
+
+type Item = (Key, AlwaysEqual<Option<Datum>>);
+
+// `FastHeap` selects the k smallest items, and the rest are left in the heap.
+fn top(items: Vec<Item>, k: usize) -> (Vec<Item>, FastHeap<Reverse<Item>>) {
+    let mut heap = FastHeap::from_vec_with_threshold(items.into_iter().map(Reverse).collect(), k);
+    let top = std::iter::from_fn(|| heap.pop())
+        .take(k)
+        .map(|Reverse(item)| item)
+        .collect();
+    (top, heap)
+}
+
+impl State {
+    fn shrink(&mut self) {
+        let (top, rest) = top(std::mem::take(&mut self.items), self.k);
+        if !self.typbyval {
+            for Reverse((_, AlwaysEqual(value))) in rest {
+                if let Some(value) = value {
+                    unsafe { pgrx::pg_sys::pfree(value.cast_mut_ptr()) };
+                }
+            }
+        }
+        self.items = top;
+        self.threshold = (self.items.len() == self.k).then(|| self.items[self.k - 1].0);
+    }
+}
+
+fn key(x: f64) -> i64 {
+    let bits = x.to_bits() as i64;
+    bits ^ (((bits >> 63) as u64) >> 1) as i64
+}
+
+unsafe fn aggcontext(fcinfo: FunctionCallInfo) -> MemoryContext {
+    let mut aggcontext = std::ptr::null_mut();
+    if unsafe { pgrx::pg_sys::AggCheckCallContext(fcinfo, &mut aggcontext) } == 0 {
+        pgrx::error!("vchord_topk is called in a non-aggregate context");
+    }
+    aggcontext
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_topk_transition(fcinfo: FunctionCallInfo) -> Internal {
+    unsafe {
+        let aggcontext = aggcontext(fcinfo);
+        let state = match pgrx::fcinfo::pg_getarg_datum(fcinfo, 0) {
+            Some(state) => state.cast_mut_ptr::<State>(),
+            None => {
+                let k = pgrx::fcinfo::pg_getarg::<i32>(fcinfo, 3);
+                let Some(k) = k.filter(|&k| k > 0) else {
+                    pgrx::error!("k must be positive");
+                };
+                let element = pgrx::pg_sys::get_fn_expr_argtype((*fcinfo).flinfo, 1);
+                let (mut typlen, mut typbyval, mut typalign) = (0, false, 0);
+                pgrx::pg_sys::get_typlenbyvalalign(
+                    element,
+                    &mut typlen,
+                    &mut typbyval,
+                    &mut typalign,
+                );
+                PgMemoryContexts::For(aggcontext).leak_and_drop_on_delete(State {
+                    k: k as usize,
+                    items: Vec::new(),
+                    threshold: None,
+                    sequence: 0,
+                    element,
+                    typlen,
+                    typbyval,
+                    typalign,
+                })
+            }
+        };
+        let state = &mut *state;
+        if let Some(x) = pgrx::fcinfo::pg_getarg::<f64>(fcinfo, 2) {
+            let key = (key(x), state.sequence);
+            state.sequence += 1;
+            if state.threshold.is_none_or(|threshold| key < threshold) {
+                let value = pgrx::fcinfo::pg_getarg_datum(fcinfo, 1).map(|value| {
+                    PgMemoryContexts::For(aggcontext).switch_to(|_| {
+                        pgrx::pg_sys::datumCopy(value, state.typbyval, state.typlen as _)
+                    })
+                });
+                state.items.push((key, AlwaysEqual(value)));
+                if state.items.len() >= 2 * state.k {
+                    state.shrink();
+                }
+            }
+        }
+        Internal::from(Some(Datum::from(state as *mut State)))
+    }
+}
+
+#[pgrx::pg_extern(sql = "")]
+fn _vchord_topk_final(fcinfo: FunctionCallInfo) -> Internal {
+    unsafe {
+        let _ = aggcontext(fcinfo);
+        let Some(state) = pgrx::fcinfo::pg_getarg_datum(fcinfo, 0) else {
+            return Internal::from(None);
+        };
+        let state = &*state.cast_mut_ptr::<State>();
+        let (top, _) = top(state.items.clone(), state.k);
+        let mut nulls = top.iter().map(|(_, x)| x.0.is_none()).collect::<Vec<_>>();
+        let mut datums = top
+            .into_iter()
+            .map(|(_, x)| x.0.unwrap_or(Datum::null()))
+            .collect::<Vec<_>>();
+        let mut dims = [datums.len() as i32];
+        let mut lbs = [1];
+        let array = pgrx::pg_sys::construct_md_array(
+            datums.as_mut_ptr(),
+            nulls.as_mut_ptr(),
+            1,
+            dims.as_mut_ptr(),
+            lbs.as_mut_ptr(),
+            state.element,
+            state.typlen as _,
+            state.typbyval,
+            state.typalign,
+        );
+        Internal::from(Some(Datum::from(array)))
+    }
+}
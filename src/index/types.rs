@@ -4,6 +4,7 @@ use validator::{Validate, ValidationError, ValidationErrors};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 #[serde(deny_unknown_fields)]
+#[validate(schema(function = "Self::validate_self"))]
 pub struct VchordrqInternalBuildOptions {
     #[serde(default = "VchordrqInternalBuildOptions::default_lists")]
     #[validate(length(min = 0, max = 8), custom(function = VchordrqInternalBuildOptions::validate_lists))]
@@ -19,6 +20,22 @@ pub struct VchordrqInternalBuildOptions {
     #[serde(default = "VchordrqInternalBuildOptions::default_build_threads")]
     #[validate(range(min = 1, max = 255))]
     pub build_threads: u16,
+    #[serde(default = "VchordrqInternalBuildOptions::default_auto_lists")]
+    pub auto_lists: bool,
+    #[serde(default = "VchordrqInternalBuildOptions::default_min_lists")]
+    #[validate(range(min = 1, max = 16777216))]
+    pub min_lists: u32,
+    #[serde(default = "VchordrqInternalBuildOptions::default_max_lists")]
+    #[validate(range(min = 1, max = 16777216))]
+    pub max_lists: u32,
+    // samples are spilled to a temporary file if they don't fit in `maintenance_work_mem`;
+    // centroids are never spilled, so those of the widest level must fit in memory
+    #[serde(default = "VchordrqInternalBuildOptions::default_build_spill")]
+    pub build_spill: bool,
+    // the trained model is persisted, so a build that fails after clustering resumes from it,
+    // but all rows are inserted again
+    #[serde(default = "VchordrqInternalBuildOptions::default_checkpoint")]
+    pub checkpoint: bool,
 }
 
 impl VchordrqInternalBuildOptions {
@@ -46,6 +63,39 @@ impl VchordrqInternalBuildOptions {
     fn default_build_threads() -> u16 {
         1
     }
+    fn default_auto_lists() -> bool {
+        false
+    }
+    fn default_min_lists() -> u32 {
+        1
+    }
+    fn default_max_lists() -> u32 {
+        1 << 24
+    }
+    fn default_build_spill() -> bool {
+        false
+    }
+    fn default_checkpoint() -> bool {
+        false
+    }
+    pub fn validate_self(&self) -> Result<(), ValidationError> {
+        if self.auto_lists && !self.lists.is_empty() {
+            return Err(ValidationError::new(
+                "`lists` should be empty if `auto_lists` is enabled",
+            ));
+        }
+        if self.min_lists > self.max_lists {
+            return Err(ValidationError::new(
+                "`min_lists` should not be greater than `max_lists`",
+            ));
+        }
+        Ok(())
+    }
+    // the number of lists is `4 * sqrt(n)`, clamped to `[min_lists, max_lists]`
+    pub fn auto_lists(&self, rows: f64) -> u32 {
+        let lists = (4.0 * rows.max(0.0).sqrt()).round().min(u32::MAX as f64) as u32;
+        lists.clamp(self.min_lists, self.max_lists)
+    }
 }
 
 impl Default for VchordrqInternalBuildOptions {
@@ -56,6 +106,11 @@ impl Default for VchordrqInternalBuildOptions {
             sampling_factor: Self::default_sampling_factor(),
             kmeans_iterations: Self::default_kmeans_iterations(),
             build_threads: Self::default_build_threads(),
+            auto_lists: Self::default_auto_lists(),
+            min_lists: Self::default_min_lists(),
+            max_lists: Self::default_max_lists(),
+            build_spill: Self::default_build_spill(),
+            checkpoint: Self::default_checkpoint(),
         }
     }
 }
@@ -66,12 +121,22 @@ pub struct VchordrqExternalBuildOptions {
     pub table: String,
 }
 
+// a model exported by `vchord_export_model`, which is encoded in hex
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct VchordrqModelBuildOptions {
+    pub hex: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "snake_case")]
 pub enum VchordrqBuildSourceOptions {
     Internal(VchordrqInternalBuildOptions),
     External(VchordrqExternalBuildOptions),
+    Model(VchordrqModelBuildOptions),
+    // the name of an existing index, whose centroids are reused
+    CentroidsFrom(String),
 }
 
 impl Default for VchordrqBuildSourceOptions {
@@ -86,30 +151,89 @@ impl Validate for VchordrqBuildSourceOptions {
         match self {
             Internal(internal_build) => internal_build.validate(),
             External(external_build) => external_build.validate(),
+            Model(model_build) => model_build.validate(),
+            CentroidsFrom(_) => Ok(()),
         }
     }
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "snake_case")]
 pub struct VchordrqBuildOptions {
     #[serde(flatten)]
+    #[validate(nested)]
     pub source: VchordrqBuildSourceOptions,
     #[serde(default = "VchordrqBuildOptions::default_pin")]
     pub pin: bool,
+    #[serde(default)]
+    pub dedup: VchordrqDedup,
+    #[serde(default = "VchordrqBuildOptions::default_verify")]
+    pub verify: bool,
+    // only this percent of rows is indexed, so the index does not cover all rows;
+    // rows inserted after the build are always indexed
+    #[serde(default = "VchordrqBuildOptions::default_index_sample_percent")]
+    #[validate(range(exclusive_min = 0.0, max = 100.0))]
+    pub index_sample_percent: f64,
+    // the table is clustered, so similar vectors are next to each other, and they
+    // are inserted in order of the table to keep members of a list on adjacent pages
+    #[serde(default = "VchordrqBuildOptions::default_input_sorted")]
+    pub input_sorted: bool,
+    // no row is indexed and there is only one list, so the index is a target of
+    // `vchord_restore` in the same transaction
+    #[serde(default = "VchordrqBuildOptions::default_empty")]
+    pub empty: bool,
 }
 
 impl VchordrqBuildOptions {
     pub fn default_pin() -> bool {
         false
     }
+    pub fn default_verify() -> bool {
+        false
+    }
+    pub fn default_index_sample_percent() -> f64 {
+        100.0
+    }
+    pub fn default_input_sorted() -> bool {
+        false
+    }
+    pub fn default_empty() -> bool {
+        false
+    }
+}
+
+impl Default for VchordrqBuildOptions {
+    fn default() -> Self {
+        Self {
+            source: Default::default(),
+            pin: Self::default_pin(),
+            dedup: Default::default(),
+            verify: Self::default_verify(),
+            index_sample_percent: Self::default_index_sample_percent(),
+            input_sorted: Self::default_input_sorted(),
+            empty: Self::default_empty(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VchordrqDedup {
+    #[default]
+    Off,
+    // one code per unique vector, which refers to all rows with this vector
+    Codes,
+    // one code per row, but duplicates are counted
+    Rows,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
 #[serde(deny_unknown_fields)]
 pub struct VchordrqIndexingOptions {
     #[serde(flatten)]
+    #[validate(nested)]
     pub index: VchordrqIndexOptions,
+    #[validate(nested)]
     pub build: VchordrqBuildOptions,
 }
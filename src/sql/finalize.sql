@@ -12,6 +12,16 @@ CREATE TYPE scalar8 (
     ALIGNMENT = double
 );
 
+CREATE TYPE vector_array (
+    INPUT = _vchord_vector_array_in,
+    OUTPUT = _vchord_vector_array_out,
+    TYPMOD_IN = _vchord_typmod_in_65535,
+    TYPMOD_OUT = _vchord_typmod_out,
+    STORAGE = EXTERNAL,
+    INTERNALLENGTH = VARIABLE,
+    ALIGNMENT = double
+);
+
 CREATE TYPE sphere_vector AS (
     center vector,
     radius REAL
@@ -50,6 +60,48 @@ CREATE OPERATOR <=> (
     COMMUTATOR = <=>
 );
 
+CREATE OPERATOR <-> (
+    PROCEDURE = _vchord_vector_halfvec_operator_l2,
+    LEFTARG = vector,
+    RIGHTARG = halfvec,
+    COMMUTATOR = <->
+);
+
+CREATE OPERATOR <-> (
+    PROCEDURE = _vchord_halfvec_vector_operator_l2,
+    LEFTARG = halfvec,
+    RIGHTARG = vector,
+    COMMUTATOR = <->
+);
+
+CREATE OPERATOR <#> (
+    PROCEDURE = _vchord_vector_halfvec_operator_ip,
+    LEFTARG = vector,
+    RIGHTARG = halfvec,
+    COMMUTATOR = <#>
+);
+
+CREATE OPERATOR <#> (
+    PROCEDURE = _vchord_halfvec_vector_operator_ip,
+    LEFTARG = halfvec,
+    RIGHTARG = vector,
+    COMMUTATOR = <#>
+);
+
+CREATE OPERATOR <=> (
+    PROCEDURE = _vchord_vector_halfvec_operator_cosine,
+    LEFTARG = vector,
+    RIGHTARG = halfvec,
+    COMMUTATOR = <=>
+);
+
+CREATE OPERATOR <=> (
+    PROCEDURE = _vchord_halfvec_vector_operator_cosine,
+    LEFTARG = halfvec,
+    RIGHTARG = vector,
+    COMMUTATOR = <=>
+);
+
 CREATE OPERATOR <<->> (
     PROCEDURE = _vchord_vector_sphere_l2_in,
     LEFTARG = vector,
@@ -125,6 +177,18 @@ CREATE OPERATOR @# (
     RIGHTARG = halfvec[]
 );
 
+CREATE OPERATOR @# (
+    PROCEDURE = _vchord_vector_array_operator_maxsim,
+    LEFTARG = vector_array,
+    RIGHTARG = vector_array
+);
+
+-- List of casts
+
+CREATE CAST (scalar8 AS scalar8) WITH FUNCTION _vchord_scalar8_typmod(scalar8, integer, boolean) AS IMPLICIT;
+
+CREATE CAST (vector_array AS vector_array) WITH FUNCTION _vchord_vector_array_typmod(vector_array, integer, boolean) AS IMPLICIT;
+
 -- List of functions
 
 CREATE FUNCTION sphere(vector, real) RETURNS sphere_vector
@@ -142,12 +206,227 @@ IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_vector_
 CREATE FUNCTION quantize_to_scalar8(halfvec) RETURNS scalar8
 IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_halfvec_quantize_to_scalar8_wrapper';
 
+CREATE FUNCTION vchord_validate(v vector, expected_dims integer, require_finite boolean default true, require_normalized boolean default false) RETURNS boolean
+IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_vector_validate_wrapper';
+
+CREATE FUNCTION vchord_validate(v halfvec, expected_dims integer, require_finite boolean default true, require_normalized boolean default false) RETURNS boolean
+IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_halfvec_validate_wrapper';
+
+CREATE FUNCTION vector_lerp(a vector, b vector, t double precision) RETURNS vector
+IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_vector_lerp_wrapper';
+
+CREATE FUNCTION vector_transform(x vector, m real[]) RETURNS vector
+IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_vector_transform_wrapper';
+
+CREATE FUNCTION vector_unchunk(v vector, chunk_dim integer) RETURNS SETOF vector
+IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_vector_unchunk_wrapper';
+
+CREATE FUNCTION vector_array_mean(vector_array) RETURNS vector
+IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_vector_array_mean_wrapper';
+
+CREATE FUNCTION vchord_dimension_variance(sample vector[]) RETURNS vector
+IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_vector_dimension_variance_wrapper';
+
+CREATE FUNCTION vchord_intrinsic_dim(sample vector[]) RETURNS double precision
+IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_vector_intrinsic_dim_wrapper';
+
+CREATE FUNCTION _vchord_halfvec_accum(double precision[], halfvec) RETURNS double precision[]
+IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_halfvec_accum_wrapper';
+
+CREATE FUNCTION _vchord_halfvec_combine(double precision[], double precision[]) RETURNS double precision[]
+IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_halfvec_combine_wrapper';
+
+CREATE FUNCTION _vchord_halfvec_sum(double precision[]) RETURNS halfvec
+IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_halfvec_sum_wrapper';
+
+CREATE FUNCTION _vchord_halfvec_avg(double precision[]) RETURNS halfvec
+IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_halfvec_avg_wrapper';
+
+CREATE AGGREGATE vchord_sum(halfvec) (
+    SFUNC = _vchord_halfvec_accum,
+    STYPE = double precision[],
+    FINALFUNC = _vchord_halfvec_sum,
+    COMBINEFUNC = _vchord_halfvec_combine,
+    INITCOND = '{0}',
+    PARALLEL = SAFE
+);
+
+CREATE AGGREGATE vchord_avg(halfvec) (
+    SFUNC = _vchord_halfvec_accum,
+    STYPE = double precision[],
+    FINALFUNC = _vchord_halfvec_avg,
+    COMBINEFUNC = _vchord_halfvec_combine,
+    INITCOND = '{0}',
+    PARALLEL = SAFE
+);
+
+CREATE FUNCTION vchord_vector_accum(state double precision[], v vector, weight double precision) RETURNS double precision[]
+IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_vector_accum_wrapper';
+
+CREATE FUNCTION vchord_vector_combine(double precision[], double precision[]) RETURNS double precision[]
+IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_vector_combine_wrapper';
+
 CREATE FUNCTION vchordrq_amhandler(internal) RETURNS index_am_handler
 IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_amhandler_wrapper';
 
 CREATE FUNCTION vchordrq_prewarm(regclass, integer default 0) RETURNS TEXT
 STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_prewarm_wrapper';
 
+CREATE FUNCTION vchord_compact(index regclass) RETURNS bigint
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_compact_wrapper';
+
+CREATE FUNCTION vchord_reconstruct(index regclass, row_id tid) RETURNS vector
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_reconstruct_wrapper';
+
+CREATE FUNCTION vchord_distances_for(index regclass, query vector, ctids tid[]) RETURNS TABLE(ctid tid, distance double precision)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_distances_for_wrapper';
+
+CREATE FUNCTION vchord_multi_query_distances(index regclass, queries vector[], ctids tid[]) RETURNS TABLE(query_idx integer, ctid tid, distance double precision)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_multi_query_distances_wrapper';
+
+CREATE FUNCTION vchord_row_distance(index regclass, a tid, b tid) RETURNS double precision
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_row_distance_wrapper';
+
+CREATE FUNCTION vchord_knn_after(index regclass, query vector, k integer, after_distance double precision, after_ctid tid) RETURNS TABLE(ctid tid, distance double precision)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_knn_after_wrapper';
+
+CREATE FUNCTION vchord_knn_distinct(index regclass, query vector, k integer, group_attr text) RETURNS TABLE(ctid tid, distance double precision)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_knn_distinct_wrapper';
+
+CREATE FUNCTION vchord_knn_mmr(index regclass, query vector, k integer, lambda double precision) RETURNS TABLE(ctid tid, score double precision)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_knn_mmr_wrapper';
+
+CREATE FUNCTION vchord_knn_bounds(index regclass, query vector, k integer) RETURNS TABLE(ctid tid, est_distance double precision, lower_bound double precision, upper_bound double precision)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_knn_bounds_wrapper';
+
+CREATE FUNCTION vchord_error_profile(index regclass, queries vector[], k integer) RETURNS TABLE(distance_bucket double precision, mean_signed_error double precision, count bigint)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_error_profile_wrapper';
+
+CREATE FUNCTION vchord_assignments(index regclass) RETURNS TABLE(ctid tid, list_id integer)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_assignments_wrapper';
+
+CREATE FUNCTION vchord_lists(index regclass) RETURNS TABLE(list_id integer, centroid vector, count bigint)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_lists_wrapper';
+
+CREATE FUNCTION vchord_audit(index regclass) RETURNS TABLE(ctid tid, issue text)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_audit_wrapper';
+
+CREATE FUNCTION vchord_compare_indexes(a regclass, b regclass, queries vector[], k integer) RETURNS TABLE(mean_overlap double precision, count bigint)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_compare_indexes_wrapper';
+
+CREATE FUNCTION _vchordrq_recall_vs_truth(regclass, vector[], tid[], integer, integer, integer, integer) RETURNS double precision
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_recall_vs_truth_wrapper';
+
+CREATE FUNCTION vchord_recall_vs_truth(index regclass, queries vector[], truth tid[][], k integer, probes integer) RETURNS double precision
+STRICT LANGUAGE sql AS $$ SELECT _vchordrq_recall_vs_truth(index, queries, truth, coalesce(array_ndims(truth), 0), coalesce(array_length(truth, 1), 0), k, probes) $$;
+
+CREATE FUNCTION vchord_bench_scan(index regclass, query vector, k integer, heap_strategy text) RETURNS TABLE(latency_ms double precision, checksum bigint)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_bench_scan_wrapper';
+
+CREATE FUNCTION vchord_export_model(index regclass) RETURNS bytea
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_export_model_wrapper';
+
+CREATE FUNCTION vchord_dump(index regclass, path text) RETURNS bigint
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_dump_wrapper';
+
+CREATE FUNCTION vchord_restore(index regclass, path text) RETURNS bigint
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_restore_wrapper';
+
+CREATE FUNCTION vchord_nearest_centroid(index regclass, query vector) RETURNS TABLE(list_id integer, centroid vector, distance double precision)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_nearest_centroid_wrapper';
+
+CREATE FUNCTION vchord_explain_scan(index regclass, query vector, probes integer) RETURNS TABLE(probe_order integer, list_id integer, centroid_distance double precision, list_size integer)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_explain_scan_wrapper';
+
+CREATE FUNCTION _vchordrq_index_info(regclass) RETURNS TABLE(dims integer, metric text, quantizer text, lists integer, residual_quantization boolean, store_originals boolean)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_index_info_wrapper';
+
+CREATE FUNCTION vchord_indexes() RETURNS TABLE(index regclass, "table" regclass, dims integer, metric text, quantizer text, lists integer, residual_quantization boolean, store_originals boolean)
+STRICT LANGUAGE sql AS $$
+    SELECT c.oid::regclass, i.indrelid::regclass, f.*
+    FROM pg_index i
+    JOIN pg_class c ON c.oid = i.indexrelid
+    JOIN pg_am a ON a.oid = c.relam
+    CROSS JOIN LATERAL _vchordrq_index_info(c.oid) f
+    WHERE a.amname = 'vchordrq' AND c.relkind = 'i' AND i.indisvalid
+    ORDER BY c.oid::regclass::text
+$$;
+
+CREATE FUNCTION _vchordrq_misaligned_codes(regclass) RETURNS bigint
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_misaligned_codes_wrapper';
+
+CREATE FUNCTION _vchordrq_index_options(regclass) RETURNS TABLE(dims integer, vector text, metric text, quantizer text, bits integer, lists integer[], residual_quantization boolean, rerank_in_table boolean, sort_lists boolean, code_alignment integer)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchordrq_index_options_wrapper';
+
+CREATE FUNCTION vchord_index_options(index regclass) RETURNS jsonb
+STRICT LANGUAGE sql AS $$
+    SELECT jsonb_build_object(
+        'version', 1,
+        'dims', f.dims,
+        'vector', f.vector,
+        'metric', f.metric,
+        'quantizer', f.quantizer,
+        'bits', f.bits,
+        'lists', to_jsonb(f.lists),
+        'residual_quantization', f.residual_quantization,
+        'rerank_in_table', f.rerank_in_table,
+        'store_originals', NOT f.rerank_in_table,
+        'sort_lists', f.sort_lists,
+        'code_alignment', f.code_alignment,
+        'rotation', 'random_orthogonal'
+    )
+    FROM _vchordrq_index_options(index) f
+$$;
+
+CREATE FUNCTION vchord_build_async("table" regclass, "column" name, options jsonb) RETURNS bigint
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_build_async_wrapper';
+
+CREATE FUNCTION _vchord_build_job(bigint) RETURNS TABLE(state text, pid integer, error text)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_build_job_wrapper';
+
+CREATE FUNCTION vchord_build_status(job_id bigint) RETURNS TABLE(state text, tuples_done bigint, tuples_total bigint, error text)
+STRICT LANGUAGE sql AS $$
+    SELECT j.state, p.tuples_done, p.tuples_total, j.error
+    FROM _vchord_build_job(job_id) j LEFT JOIN pg_stat_progress_create_index p ON p.pid = j.pid
+$$;
+
+CREATE FUNCTION vchord_checkpoint_cleanup() RETURNS bigint
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_checkpoint_cleanup_wrapper';
+
+CREATE FUNCTION vchord_dimension_histogram("table" regclass, "column" name) RETURNS TABLE(dims integer, count bigint)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_dimension_histogram_wrapper';
+
+CREATE FUNCTION vchord_suggest_lists(sample vector[], metric text) RETURNS integer
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_suggest_lists_wrapper';
+
+CREATE FUNCTION vchord_estimate_size(row_count bigint, dims integer, bits integer, lists integer, store_originals boolean) RETURNS bigint
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_estimate_size_wrapper';
+
+CREATE FUNCTION vchord_similarity_score(distance double precision, metric text) RETURNS double precision
+IMMUTABLE STRICT PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_similarity_score_wrapper';
+
+CREATE FUNCTION _vchord_topk_transition(internal, anyelement, double precision, integer) RETURNS internal
+LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_topk_transition_wrapper';
+
+CREATE FUNCTION _vchord_topk_final(internal, anyelement, double precision, integer) RETURNS anyarray
+LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_topk_final_wrapper';
+
+CREATE AGGREGATE vchord_topk(value anyelement, key double precision, k integer) (
+    SFUNC = _vchord_topk_transition,
+    STYPE = internal,
+    FINALFUNC = _vchord_topk_final,
+    FINALFUNC_EXTRA
+);
+
+CREATE FUNCTION vchord_last_scan_stats() RETURNS TABLE(lists bigint, candidates bigint, reranked bigint, pages bigint, centroids bigint)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_last_scan_stats_wrapper';
+
+CREATE FUNCTION vchord_stats() RETURNS TABLE(scans bigint, lists bigint, candidates bigint, reranked bigint, pages bigint, avg_lists double precision)
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_stats_wrapper';
+
+CREATE FUNCTION vchord_stats_reset() RETURNS void
+STRICT LANGUAGE c AS 'MODULE_PATHNAME', '_vchord_stats_reset_wrapper';
+
 -- List of access methods
 
 CREATE ACCESS METHOD vchordrq TYPE INDEX HANDLER vchordrq_amhandler;
@@ -157,6 +436,7 @@ CREATE ACCESS METHOD vchordrq TYPE INDEX HANDLER vchordrq_amhandler;
 CREATE OPERATOR FAMILY vector_l2_ops USING vchordrq;
 CREATE OPERATOR FAMILY vector_ip_ops USING vchordrq;
 CREATE OPERATOR FAMILY vector_cosine_ops USING vchordrq;
+CREATE OPERATOR FAMILY vector_cosine_normalized_ops USING vchordrq;
 CREATE OPERATOR FAMILY halfvec_l2_ops USING vchordrq;
 CREATE OPERATOR FAMILY halfvec_ip_ops USING vchordrq;
 CREATE OPERATOR FAMILY halfvec_cosine_ops USING vchordrq;
@@ -183,6 +463,13 @@ CREATE OPERATOR CLASS vector_cosine_ops
     OPERATOR 2 <<=>> (vector, sphere_vector) FOR SEARCH,
     FUNCTION 1 _vchordrq_support_vector_cosine_ops();
 
+-- vectors must be normalized, otherwise distances of the index are not cosine distances
+CREATE OPERATOR CLASS vector_cosine_normalized_ops
+    FOR TYPE vector USING vchordrq FAMILY vector_cosine_normalized_ops AS
+    OPERATOR 1 <=> (vector, vector) FOR ORDER BY float_ops,
+    OPERATOR 2 <<=>> (vector, sphere_vector) FOR SEARCH,
+    FUNCTION 1 _vchordrq_support_vector_cosine_normalized_ops();
+
 CREATE OPERATOR CLASS halfvec_l2_ops
     FOR TYPE halfvec USING vchordrq FAMILY halfvec_l2_ops AS
     OPERATOR 1 <-> (halfvec, halfvec) FOR ORDER BY float_ops,
@@ -201,6 +488,24 @@ CREATE OPERATOR CLASS halfvec_cosine_ops
     OPERATOR 2 <<=>> (halfvec, sphere_halfvec) FOR SEARCH,
     FUNCTION 1 _vchordrq_support_halfvec_cosine_ops();
 
+ALTER OPERATOR FAMILY vector_l2_ops USING vchordrq ADD
+    OPERATOR 1 <-> (vector, halfvec) FOR ORDER BY float_ops;
+
+ALTER OPERATOR FAMILY halfvec_l2_ops USING vchordrq ADD
+    OPERATOR 1 <-> (halfvec, vector) FOR ORDER BY float_ops;
+
+ALTER OPERATOR FAMILY vector_ip_ops USING vchordrq ADD
+    OPERATOR 1 <#> (vector, halfvec) FOR ORDER BY float_ops;
+
+ALTER OPERATOR FAMILY halfvec_ip_ops USING vchordrq ADD
+    OPERATOR 1 <#> (halfvec, vector) FOR ORDER BY float_ops;
+
+ALTER OPERATOR FAMILY vector_cosine_ops USING vchordrq ADD
+    OPERATOR 1 <=> (vector, halfvec) FOR ORDER BY float_ops;
+
+ALTER OPERATOR FAMILY halfvec_cosine_ops USING vchordrq ADD
+    OPERATOR 1 <=> (halfvec, vector) FOR ORDER BY float_ops;
+
 CREATE OPERATOR CLASS vector_maxsim_ops
     FOR TYPE vector[] USING vchordrq FAMILY vector_maxsim_ops AS
     OPERATOR 3 @# (vector[], vector[]) FOR ORDER BY float_ops,
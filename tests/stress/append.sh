@@ -0,0 +1,31 @@
+#!/usr/bin/env bash
+# Many sessions append to the only list of an index at once, so that its tail is
+# extended and linked by racing inserters. Every row must be found in the list.
+set -euo pipefail
+
+cd "$(dirname "$0")"
+
+psql -v ON_ERROR_STOP=1 -q <<'SQL'
+DROP TABLE IF EXISTS stress_append;
+CREATE TABLE stress_append (id bigserial, val vector(64));
+CREATE INDEX stress_append_val_idx ON stress_append USING vchordrq (val vector_l2_ops)
+WITH (options = $$
+[build.internal]
+lists = []
+$$);
+SQL
+
+pgbench -n -c 16 -j 16 -t 200 -f append.sql
+
+result=$(psql -v ON_ERROR_STOP=1 -tA <<'SQL'
+SELECT COUNT(1) = 32000 AND COUNT(DISTINCT a.ctid) = 32000
+FROM vchord_assignments('stress_append_val_idx') a JOIN stress_append t ON a.ctid = t.ctid;
+SQL
+)
+
+psql -v ON_ERROR_STOP=1 -q -c 'DROP TABLE stress_append;'
+
+if [ "$result" != "t" ]; then
+    echo "rows are lost by concurrent appends"
+    exit 1
+fi
@@ -0,0 +1 @@
+INSERT INTO stress_append (val) SELECT array_agg(0.5 + random() * 0.01)::real[]::vector FROM generate_series(1, 64 * 10) i GROUP BY i % 10;